@@ -1,7 +1,10 @@
+// * the coordinates of the scene are written in double precision, they are rounded with the single-precision feature
+#![cfg_attr(feature = "single-precision", allow(clippy::excessive_precision))]
+
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use ray_tracing_3d::{
     geometry::{point::Point, shape::Sphere},
-    object::Object,
+    object::{Object, VisibilityFlags},
     optic::{
         color::{self, DiffusionCoefficient},
         material::Material,
@@ -62,8 +65,9 @@ pub fn criterion_benchmark(c: &mut Criterion) {
 	)
 	.unwrap();
 	let object_support = Object {
-		shape: sphere_support,
+		shape: Box::new(sphere_support),
 		material: sphere_support_material,
+		visibility: VisibilityFlags::default(),
 	};
 
 	let small_sphere_1_material = Material::new(
@@ -95,20 +99,24 @@ pub fn criterion_benchmark(c: &mut Criterion) {
 	)
 	.unwrap();
 	let object_small_sphere_1 = Object {
-		shape: small_sphere_1,
+		shape: Box::new(small_sphere_1),
 		material: small_sphere_1_material,
+		visibility: VisibilityFlags::default(),
 	};
 	let object_small_sphere_2 = Object {
-		shape: small_sphere_2,
+		shape: Box::new(small_sphere_2),
 		material: small_sphere_2_material,
+		visibility: VisibilityFlags::default(),
 	};
 	let object_small_sphere_3 = Object {
-		shape: small_sphere_3,
+		shape: Box::new(small_sphere_3),
 		material: small_sphere_3_material,
+		visibility: VisibilityFlags::default(),
 	};
 	let object_small_sphere_4 = Object {
-		shape: small_sphere_4,
+		shape: Box::new(small_sphere_4),
 		material: small_sphere_4_material,
+		visibility: VisibilityFlags::default(),
 	};
 
 	let light_source_material = Material::new(
@@ -119,8 +127,9 @@ pub fn criterion_benchmark(c: &mut Criterion) {
 	)
 	.unwrap();
 	let object_light_source = Object {
-		shape: light_source,
+		shape: Box::new(light_source),
 		material: light_source_material,
+		visibility: VisibilityFlags::default(),
 	};
 
 	let objects = vec![
@@ -0,0 +1,863 @@
+use crate::{
+    error::RayTracingError,
+    geometry::{
+        aabb::Aabb,
+        point::Point,
+        ray::{HitInfo, Ray, RayKind},
+        Scalar, TOLERANCE,
+    },
+    object::Object,
+};
+
+// leaves of the BVH hold at most this many objects
+const MAX_OBJECTS_PER_LEAF: usize = 2;
+// `Bvh::refit` builds the tree again once the boxes of its nodes are this many times larger than when it was built
+const REBUILD_AREA_RATIO: Scalar = 2.;
+// a uniform grid is split in about this many voxels per bounded object
+const VOXELS_PER_OBJECT: Scalar = 2.;
+// keeps the voxel table to a reasonable size when the objects are spread far apart
+const MAX_VOXELS_PER_AXIS: usize = 128;
+
+/// Finds the objects hit by a ray, the implementations hold their own copy of the objects
+pub trait Accelerator: std::fmt::Debug + Send + Sync {
+    /// Closest object hit in front of the ray origin
+    fn first_hit(&self, ray: &Ray) -> Result<Option<HitInfo<'_>>, RayTracingError>;
+
+    /// Same as `Ray::first_visible_hit` on the objects of the accelerator, which is what the renderer looks for
+    fn first_visible_hit(
+        &self,
+        ray: &Ray,
+        ignore_object: Option<&Object>,
+        ray_kind: RayKind,
+    ) -> Result<Option<HitInfo<'_>>, RayTracingError>;
+
+    /// Every object hit in front of the ray origin, sorted by distance, closest first
+    fn all_hits(&self, ray: &Ray) -> Result<Vec<HitInfo<'_>>, RayTracingError>;
+
+    /// Whether an object seen by the shadow rays is hit between `TOLERANCE` and `max_distance` from the ray origin,
+    /// it returns as soon as one is found
+    fn is_occluded(&self, ray: &Ray, max_distance: Scalar) -> Result<bool, RayTracingError>;
+
+    /// Copy of the accelerator along with its objects, so that a boxed accelerator can be cloned
+    fn box_clone(&self) -> Box<dyn Accelerator>;
+
+    /// Accelerator of the same kind built from `objects`, for when the objects it was built from changed
+    fn rebuilt(&self, objects: &[Object]) -> Box<dyn Accelerator>;
+}
+
+impl Clone for Box<dyn Accelerator> {
+    fn clone(&self) -> Self {
+        self.box_clone()
+    }
+}
+
+/// Tries every object, which is what the renderer does
+#[derive(Debug, Clone)]
+pub struct LinearScan {
+    objects: Vec<Object>,
+}
+
+impl LinearScan {
+    pub fn new(objects: &[Object]) -> Self {
+        LinearScan {
+            objects: objects.to_vec(),
+        }
+    }
+}
+
+impl Accelerator for LinearScan {
+    fn first_hit(&self, ray: &Ray) -> Result<Option<HitInfo<'_>>, RayTracingError> {
+        let objects: Vec<&Object> = self.objects.iter().collect();
+        ray.first_point_hit_by_ray(&objects, None)
+    }
+
+    fn first_visible_hit(
+        &self,
+        ray: &Ray,
+        ignore_object: Option<&Object>,
+        ray_kind: RayKind,
+    ) -> Result<Option<HitInfo<'_>>, RayTracingError> {
+        let objects: Vec<&Object> = self.objects.iter().collect();
+        ray.first_visible_hit(&objects, ignore_object, ray_kind)
+    }
+
+    fn all_hits(&self, ray: &Ray) -> Result<Vec<HitInfo<'_>>, RayTracingError> {
+        let mut hits = Vec::new();
+        for object in &self.objects {
+            if let Some(hit_info) = ray.intersect(object)? {
+                hits.push(hit_info);
+            }
+        }
+        sort_by_distance(&mut hits);
+        Ok(hits)
+    }
+
+    fn is_occluded(&self, ray: &Ray, max_distance: Scalar) -> Result<bool, RayTracingError> {
+        for object in &self.objects {
+            if blocks_shadow_ray(ray, object, max_distance)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn box_clone(&self) -> Box<dyn Accelerator> {
+        Box::new(self.clone())
+    }
+
+    fn rebuilt(&self, objects: &[Object]) -> Box<dyn Accelerator> {
+        Box::new(LinearScan::new(objects))
+    }
+}
+
+#[derive(Debug, Clone)]
+enum BvhNode {
+    Leaf {
+        bounding_box: Aabb,
+        object_indices: Vec<usize>,
+    },
+    Branch {
+        bounding_box: Aabb,
+        children: (usize, usize), // indices in the node table
+    },
+}
+
+impl BvhNode {
+    fn bounding_box(&self) -> &Aabb {
+        match self {
+            BvhNode::Leaf { bounding_box, .. } | BvhNode::Branch { bounding_box, .. } => {
+                bounding_box
+            }
+        }
+    }
+}
+
+/// Bounding volume hierarchy, the objects are split in two halves along the longest axis of their box
+/// until there are few enough of them, so a ray only tries the objects whose boxes it goes through
+#[derive(Debug, Clone)]
+pub struct Bvh {
+    objects: Vec<Object>,
+    nodes: Vec<BvhNode>, // * the root is the first node, None if there is no bounded object
+    unbounded_objects: Vec<usize>, // objects without a bounding box, tried by every ray
+    built_area: Scalar, // total surface area of the boxes of the nodes when the tree was last built
+}
+
+impl Bvh {
+    pub fn new(objects: &[Object]) -> Self {
+        let mut bvh = Bvh {
+            objects: objects.to_vec(),
+            nodes: Vec::new(),
+            unbounded_objects: Vec::new(),
+            built_area: 0.,
+        };
+        bvh.rebuild();
+        bvh
+    }
+
+    /// Objects of the BVH, to move them between frames, `refit` has to be called once they are moved
+    pub fn objects_mut(&mut self) -> &mut [Object] {
+        &mut self.objects
+    }
+
+    /// Updates the boxes of the nodes to the moved objects while keeping the tree, which is faster than building it again
+    /// but the tree gets worse as the objects move away from where it was built, so it is built again once the boxes
+    /// have grown `REBUILD_AREA_RATIO` times larger, or if an object gained or lost its bounding box,
+    /// returns whether the tree was built again
+    pub fn refit(&mut self) -> bool {
+        let same_unbounded_objects = (0..self.objects.len())
+            .filter(|index| self.objects[*index].shape.bounding_box().is_none())
+            .eq(self.unbounded_objects.iter().copied());
+        if !same_unbounded_objects {
+            self.rebuild();
+            return true;
+        }
+        // * the children of a node are always after it in the table, so going backwards updates them first
+        for node_index in (0..self.nodes.len()).rev() {
+            let refitted_box = match &self.nodes[node_index] {
+                BvhNode::Leaf { object_indices, .. } => object_indices[1..]
+                    .iter()
+                    .fold(self.object_box(object_indices[0]), |bounding_box, index| {
+                        bounding_box.union(&self.object_box(*index))
+                    }),
+                BvhNode::Branch { children, .. } => self.nodes[children.0]
+                    .bounding_box()
+                    .union(self.nodes[children.1].bounding_box()),
+            };
+            match &mut self.nodes[node_index] {
+                BvhNode::Leaf { bounding_box, .. } | BvhNode::Branch { bounding_box, .. } => {
+                    *bounding_box = refitted_box
+                }
+            }
+        }
+        if self.nodes_area() > REBUILD_AREA_RATIO * self.built_area {
+            self.rebuild();
+            return true;
+        }
+        false
+    }
+
+    /// Builds the tree from scratch, as `new` does
+    fn rebuild(&mut self) {
+        let (mut object_indices, unbounded_objects): (Vec<usize>, Vec<usize>) = (0..self.objects.len())
+            .partition(|index| self.objects[*index].shape.bounding_box().is_some());
+        self.unbounded_objects = unbounded_objects;
+        self.nodes.clear();
+        if !object_indices.is_empty() {
+            self.build(&mut object_indices);
+        }
+        self.built_area = self.nodes_area();
+    }
+
+    fn nodes_area(&self) -> Scalar {
+        self.nodes.iter().map(|node| node.bounding_box().surface_area()).sum()
+    }
+
+    /// Box of an object that has one, as only those are put in the nodes
+    fn object_box(&self, object_index: usize) -> Aabb {
+        self.objects[object_index]
+            .shape
+            .bounding_box()
+            .expect("only the bounded objects are put in the nodes")
+    }
+
+    /// Adds the node holding `object_indices` and its children to the table, returns its index
+    fn build(&mut self, object_indices: &mut [usize]) -> usize {
+        let boxes: Vec<Aabb> = object_indices.iter().map(|index| self.object_box(*index)).collect();
+        let bounding_box = boxes[1..]
+            .iter()
+            .fold(boxes[0], |bounding_box, object_box| {
+                bounding_box.union(object_box)
+            });
+        let node_index = self.nodes.len();
+        if object_indices.len() <= MAX_OBJECTS_PER_LEAF {
+            self.nodes.push(BvhNode::Leaf {
+                bounding_box,
+                object_indices: object_indices.to_vec(),
+            });
+            return node_index;
+        }
+        // * the node is pushed first so that the root stays at index 0, its children are set once built
+        self.nodes.push(BvhNode::Branch {
+            bounding_box,
+            children: (0, 0),
+        });
+        let extent = [
+            bounding_box.max.x - bounding_box.min.x,
+            bounding_box.max.y - bounding_box.min.y,
+            bounding_box.max.z - bounding_box.min.z,
+        ];
+        let longest_axis = (0..3)
+            .max_by(|axis_1, axis_2| extent[*axis_1].total_cmp(&extent[*axis_2]))
+            .unwrap_or(0);
+        let center_along_axis = |index: &usize| {
+            let object_box = self.object_box(*index);
+            [
+                object_box.min.x + object_box.max.x,
+                object_box.min.y + object_box.max.y,
+                object_box.min.z + object_box.max.z,
+            ][longest_axis]
+                / 2.
+        };
+        object_indices.sort_by(|index_1, index_2| {
+            center_along_axis(index_1).total_cmp(&center_along_axis(index_2))
+        });
+        let (first_half, second_half) = object_indices.split_at_mut(object_indices.len() / 2);
+        let children = (self.build(first_half), self.build(second_half));
+        self.nodes[node_index] = BvhNode::Branch {
+            bounding_box,
+            children,
+        };
+        node_index
+    }
+
+    /// Goes through the nodes whose box is hit by the ray at a distance below `max_distance`,
+    /// `visit` is given the objects of the leaves, and first the unbounded objects, and returns the new `max_distance`
+    fn traverse<F>(
+        &self,
+        ray: &Ray,
+        mut max_distance: Scalar,
+        mut visit: F,
+    ) -> Result<(), RayTracingError>
+    where
+        F: FnMut(&[usize], Scalar) -> Result<Scalar, RayTracingError>,
+    {
+        if !self.unbounded_objects.is_empty() {
+            max_distance = visit(&self.unbounded_objects, max_distance)?;
+        }
+        if self.nodes.is_empty() {
+            return Ok(());
+        }
+        let mut nodes_to_visit = vec![0];
+        while let Some(node_index) = nodes_to_visit.pop() {
+            let node = &self.nodes[node_index];
+            match node.bounding_box().entry_distance(ray)? {
+                Some(entry_distance) if entry_distance <= max_distance => {}
+                _ => continue,
+            }
+            match node {
+                BvhNode::Leaf { object_indices, .. } => {
+                    max_distance = visit(object_indices, max_distance)?
+                }
+                BvhNode::Branch { children, .. } => {
+                    nodes_to_visit.push(children.0);
+                    nodes_to_visit.push(children.1);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Closest hit among the objects for which `can_be_hit` is true
+    fn closest_hit(
+        &self,
+        ray: &Ray,
+        can_be_hit: impl Fn(&Object) -> bool,
+    ) -> Result<Option<HitInfo<'_>>, RayTracingError> {
+        let mut closest_hit: Option<HitInfo<'_>> = None;
+        self.traverse(ray, Scalar::INFINITY, |object_indices, max_distance| {
+            let mut max_distance = max_distance;
+            for index in object_indices {
+                let object = &self.objects[*index];
+                if !can_be_hit(object) {
+                    continue;
+                }
+                if let Some(hit_info) = ray.intersect(object)? {
+                    if hit_info.hit_distance <= max_distance {
+                        max_distance = hit_info.hit_distance;
+                        closest_hit = Some(hit_info);
+                    }
+                }
+            }
+            Ok(max_distance)
+        })?;
+        Ok(closest_hit)
+    }
+}
+
+impl Accelerator for Bvh {
+    fn first_hit(&self, ray: &Ray) -> Result<Option<HitInfo<'_>>, RayTracingError> {
+        self.closest_hit(ray, |_| true)
+    }
+
+    fn first_visible_hit(
+        &self,
+        ray: &Ray,
+        ignore_object: Option<&Object>,
+        ray_kind: RayKind,
+    ) -> Result<Option<HitInfo<'_>>, RayTracingError> {
+        self.closest_hit(ray, |object| can_be_hit(object, ignore_object, ray_kind))
+    }
+
+    fn all_hits(&self, ray: &Ray) -> Result<Vec<HitInfo<'_>>, RayTracingError> {
+        let mut hits = Vec::new();
+        self.traverse(ray, Scalar::INFINITY, |object_indices, max_distance| {
+            for index in object_indices {
+                if let Some(hit_info) = ray.intersect(&self.objects[*index])? {
+                    hits.push(hit_info);
+                }
+            }
+            Ok(max_distance)
+        })?;
+        sort_by_distance(&mut hits);
+        Ok(hits)
+    }
+
+    fn is_occluded(&self, ray: &Ray, max_distance: Scalar) -> Result<bool, RayTracingError> {
+        let mut is_occluded = false;
+        self.traverse(ray, max_distance, |object_indices, max_distance| {
+            for index in object_indices {
+                if blocks_shadow_ray(ray, &self.objects[*index], max_distance)? {
+                    is_occluded = true;
+                    // * no node is entered before negative infinity, the traversal ends
+                    return Ok(Scalar::NEG_INFINITY);
+                }
+            }
+            Ok(max_distance)
+        })?;
+        Ok(is_occluded)
+    }
+
+    fn box_clone(&self) -> Box<dyn Accelerator> {
+        Box::new(self.clone())
+    }
+
+    fn rebuilt(&self, objects: &[Object]) -> Box<dyn Accelerator> {
+        Box::new(Bvh::new(objects))
+    }
+}
+
+/// Box around the bounded objects split in voxels of the same size, each voxel lists the objects whose boxes overlap it,
+/// a ray goes through the voxels in order and stops at the first one where it hits something
+#[derive(Debug, Clone)]
+pub struct UniformGrid {
+    objects: Vec<Object>,
+    bounds: Option<Aabb>, // * None if there is no bounded object
+    resolution: [usize; 3], // number of voxels along x, y and z
+    voxels: Vec<Vec<usize>>, // objects of each voxel, x varying first, then y and z
+    unbounded_objects: Vec<usize>, // objects without a bounding box, tried by every ray
+}
+
+impl UniformGrid {
+    pub fn new(objects: &[Object]) -> Self {
+        let mut grid = UniformGrid {
+            objects: objects.to_vec(),
+            bounds: None,
+            resolution: [1; 3],
+            voxels: Vec::new(),
+            unbounded_objects: Vec::new(),
+        };
+        let mut object_boxes = Vec::new();
+        for (index, object) in objects.iter().enumerate() {
+            match object.shape.bounding_box() {
+                Some(object_box) => object_boxes.push((index, object_box)),
+                None => grid.unbounded_objects.push(index),
+            }
+        }
+        let Some(bounds) = object_boxes
+            .iter()
+            .map(|(_, object_box)| *object_box)
+            .reduce(|bounds, object_box| bounds.union(&object_box))
+        else {
+            return grid;
+        };
+        let extent = [
+            bounds.max.x - bounds.min.x,
+            bounds.max.y - bounds.min.y,
+            bounds.max.z - bounds.min.z,
+        ];
+        let number_of_voxels = VOXELS_PER_OBJECT * object_boxes.len() as Scalar;
+        let volume = extent[0] * extent[1] * extent[2];
+        // * side of a cubic voxel, a flat grid is only split along its sides
+        let voxel_side = if volume > 0. {
+            (volume / number_of_voxels).cbrt()
+        } else {
+            extent.iter().fold(0., |longest: Scalar, side| longest.max(*side)) / number_of_voxels.cbrt()
+        };
+        grid.resolution = extent.map(|side| {
+            if voxel_side > 0. {
+                ((side / voxel_side).ceil() as usize).clamp(1, MAX_VOXELS_PER_AXIS)
+            } else {
+                1
+            }
+        });
+        grid.bounds = Some(bounds);
+        grid.voxels = vec![Vec::new(); grid.resolution.iter().product()];
+        for (index, object_box) in object_boxes {
+            let first_voxel = grid.voxel_of(&object_box.min);
+            let last_voxel = grid.voxel_of(&object_box.max);
+            for z in first_voxel[2]..=last_voxel[2] {
+                for y in first_voxel[1]..=last_voxel[1] {
+                    for x in first_voxel[0]..=last_voxel[0] {
+                        let voxel_index = grid.voxel_index([x, y, z]);
+                        grid.voxels[voxel_index].push(index);
+                    }
+                }
+            }
+        }
+        grid
+    }
+
+    /// Size of a voxel along x, y and z, 0 along the axes where the grid is flat
+    fn voxel_size(&self, bounds: &Aabb) -> [Scalar; 3] {
+        [
+            (bounds.max.x - bounds.min.x) / self.resolution[0] as Scalar,
+            (bounds.max.y - bounds.min.y) / self.resolution[1] as Scalar,
+            (bounds.max.z - bounds.min.z) / self.resolution[2] as Scalar,
+        ]
+    }
+
+    /// Voxel holding the point, the points outside of the grid are given the closest voxel
+    fn voxel_of(&self, point: &Point) -> [usize; 3] {
+        let Some(bounds) = self.bounds else {
+            return [0; 3];
+        };
+        let voxel_size = self.voxel_size(&bounds);
+        let from_min = [
+            point.x - bounds.min.x,
+            point.y - bounds.min.y,
+            point.z - bounds.min.z,
+        ];
+        [0, 1, 2].map(|axis| {
+            if voxel_size[axis] > 0. {
+                ((from_min[axis] / voxel_size[axis]).max(0.) as usize).min(self.resolution[axis] - 1)
+            } else {
+                0
+            }
+        })
+    }
+
+    fn voxel_index(&self, voxel: [usize; 3]) -> usize {
+        voxel[0] + self.resolution[0] * (voxel[1] + self.resolution[1] * voxel[2])
+    }
+
+    /// Goes through the voxels crossed by the ray in order, `visit` is given their objects along with the distance where
+    /// the ray leaves the voxel, it returns true to stop there, the unbounded objects are given first and can't stop it
+    /// as the voxels could hold something closer
+    fn traverse<F>(&self, ray: &Ray, mut visit: F) -> Result<(), RayTracingError>
+    where
+        F: FnMut(&[usize], Scalar) -> Result<bool, RayTracingError>,
+    {
+        if !self.unbounded_objects.is_empty() {
+            visit(&self.unbounded_objects, Scalar::INFINITY)?;
+        }
+        let Some(bounds) = self.bounds else {
+            return Ok(());
+        };
+        let Some(entry_distance) = bounds.entry_distance(ray)? else {
+            return Ok(());
+        };
+        let direction = ray.normalized_direction()?;
+        let mut voxel = self.voxel_of(&(&ray.origin + &(entry_distance * &direction)));
+        let voxel_size = self.voxel_size(&bounds);
+        let origin = [ray.origin.x, ray.origin.y, ray.origin.z];
+        let direction = [direction.x, direction.y, direction.z];
+        let min = [bounds.min.x, bounds.min.y, bounds.min.z];
+        // * distance along the ray to the next voxel boundary on each axis, and between two boundaries
+        let mut next_boundary_distance = [Scalar::INFINITY; 3];
+        let mut boundary_spacing = [Scalar::INFINITY; 3];
+        for axis in 0..3 {
+            if direction[axis] == 0. {
+                continue;
+            }
+            let next_voxel = if direction[axis] > 0. { voxel[axis] + 1 } else { voxel[axis] };
+            let boundary = min[axis] + next_voxel as Scalar * voxel_size[axis];
+            next_boundary_distance[axis] = (boundary - origin[axis]) / direction[axis];
+            boundary_spacing[axis] = voxel_size[axis] / direction[axis].abs();
+        }
+        loop {
+            let axis = (0..3)
+                .min_by(|axis_1, axis_2| next_boundary_distance[*axis_1].total_cmp(&next_boundary_distance[*axis_2]))
+                .unwrap_or(0);
+            if visit(&self.voxels[self.voxel_index(voxel)], next_boundary_distance[axis])? {
+                return Ok(());
+            }
+            if direction[axis] > 0. && voxel[axis] + 1 < self.resolution[axis] {
+                voxel[axis] += 1;
+            } else if direction[axis] < 0. && voxel[axis] > 0 {
+                voxel[axis] -= 1;
+            } else {
+                // * the ray leaves the grid
+                return Ok(());
+            }
+            next_boundary_distance[axis] += boundary_spacing[axis];
+        }
+    }
+
+    /// Closest hit among the objects for which `can_be_hit` is true
+    fn closest_hit(
+        &self,
+        ray: &Ray,
+        can_be_hit: impl Fn(&Object) -> bool,
+    ) -> Result<Option<HitInfo<'_>>, RayTracingError> {
+        let mut closest_hit: Option<HitInfo<'_>> = None;
+        self.traverse(ray, |object_indices, exit_distance| {
+            for index in object_indices {
+                let object = &self.objects[*index];
+                if !can_be_hit(object) {
+                    continue;
+                }
+                if let Some(hit_info) = ray.intersect(object)? {
+                    if closest_hit.map_or(true, |closest_hit| hit_info.hit_distance < closest_hit.hit_distance) {
+                        closest_hit = Some(hit_info);
+                    }
+                }
+            }
+            // * an object can go beyond the voxel, what it is hit by after it has to be compared to the next voxels
+            Ok(closest_hit.is_some_and(|closest_hit| closest_hit.hit_distance <= exit_distance))
+        })?;
+        Ok(closest_hit)
+    }
+}
+
+impl Accelerator for UniformGrid {
+    fn first_hit(&self, ray: &Ray) -> Result<Option<HitInfo<'_>>, RayTracingError> {
+        self.closest_hit(ray, |_| true)
+    }
+
+    fn first_visible_hit(
+        &self,
+        ray: &Ray,
+        ignore_object: Option<&Object>,
+        ray_kind: RayKind,
+    ) -> Result<Option<HitInfo<'_>>, RayTracingError> {
+        self.closest_hit(ray, |object| can_be_hit(object, ignore_object, ray_kind))
+    }
+
+    fn all_hits(&self, ray: &Ray) -> Result<Vec<HitInfo<'_>>, RayTracingError> {
+        let mut hits = Vec::new();
+        // * an object is in every voxel its box overlaps, it is only tried once
+        let mut tried_objects = vec![false; self.objects.len()];
+        self.traverse(ray, |object_indices, _| {
+            for index in object_indices {
+                if tried_objects[*index] {
+                    continue;
+                }
+                tried_objects[*index] = true;
+                if let Some(hit_info) = ray.intersect(&self.objects[*index])? {
+                    hits.push(hit_info);
+                }
+            }
+            Ok(false)
+        })?;
+        sort_by_distance(&mut hits);
+        Ok(hits)
+    }
+
+    fn is_occluded(&self, ray: &Ray, max_distance: Scalar) -> Result<bool, RayTracingError> {
+        let mut is_occluded = false;
+        self.traverse(ray, |object_indices, exit_distance| {
+            if !is_occluded {
+                for index in object_indices {
+                    if blocks_shadow_ray(ray, &self.objects[*index], max_distance)? {
+                        is_occluded = true;
+                        break;
+                    }
+                }
+            }
+            // * the voxels beyond the end of the segment can't hold anything in the way
+            Ok(is_occluded || exit_distance >= max_distance)
+        })?;
+        Ok(is_occluded)
+    }
+
+    fn box_clone(&self) -> Box<dyn Accelerator> {
+        Box::new(self.clone())
+    }
+
+    fn rebuilt(&self, objects: &[Object]) -> Box<dyn Accelerator> {
+        Box::new(UniformGrid::new(objects))
+    }
+}
+
+/// Whether `Ray::first_visible_hit` tries the object
+fn can_be_hit(object: &Object, ignore_object: Option<&Object>, ray_kind: RayKind) -> bool {
+    object.visibility.is_visible_to(ray_kind)
+        && ignore_object.map_or(true, |object_to_ignore| *object.shape != *object_to_ignore.shape)
+}
+
+/// Whether the object is seen by the shadow rays and hit between `TOLERANCE` and `max_distance` from the ray origin
+fn blocks_shadow_ray(ray: &Ray, object: &Object, max_distance: Scalar) -> Result<bool, RayTracingError> {
+    if !object.visibility.is_visible_to(RayKind::Shadow) {
+        return Ok(false);
+    }
+    Ok(ray
+        .intersect(object)?
+        .is_some_and(|hit_info| hit_info.hit_distance > TOLERANCE && hit_info.hit_distance < max_distance))
+}
+
+fn sort_by_distance(hits: &mut [HitInfo<'_>]) {
+    hits.sort_by(|first_hit, second_hit| {
+        first_hit.hit_distance.total_cmp(&second_hit.hit_distance)
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        geometry::{
+            shape::{Plane, Sphere},
+            triangle::Triangle,
+            vector::Vector,
+        },
+        object::VisibilityFlags,
+        optic::material::Material,
+    };
+    use rand::{Rng, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    #[test]
+    fn test_implementations_agree() -> Result<(), RayTracingError> {
+        let mut rng = XorShiftRng::seed_from_u64(7);
+        let mut random_point = |half_size: Scalar| {
+            Point::new(
+                rng.gen_range(-half_size..half_size),
+                rng.gen_range(-half_size..half_size),
+                rng.gen_range(-half_size..half_size),
+            )
+        };
+        let mut objects: Vec<Object> = (0..40)
+            .map(|_| Object {
+                shape: Box::new(Sphere::new_from_radius(&random_point(20.), 1.5)),
+                material: Material::default(),
+                visibility: VisibilityFlags::default(),
+            })
+            .collect();
+        // * a room around everything, rays starting inside of it
+        objects.push(Object {
+            shape: Box::new(Sphere::new_inward_from_radius(&Point::new(0., 0., 0.), 100.)),
+            material: Material::default(),
+            visibility: VisibilityFlags::default(),
+        });
+        // * a floor across the spheres, the unbounded objects are tried before the voxels but can be behind them
+        objects.push(Object {
+            shape: Box::new(Plane::new(Point::new(0., 0., 0.), Vector::new_from_coordinates(0., 1., 0.))?),
+            material: Material::default(),
+            visibility: VisibilityFlags::default(),
+        });
+        let linear_scan = LinearScan::new(&objects);
+        let bvh = Bvh::new(&objects);
+        let uniform_grid = UniformGrid::new(&objects);
+        let grid_without_room = UniformGrid::new(&objects[..objects.len() - 2]);
+        let linear_scan_without_room = LinearScan::new(&objects[..objects.len() - 2]);
+
+        for _ in 0..500 {
+            let origin = random_point(25.);
+            let target = random_point(25.);
+            let ray = Ray::new(origin, Vector::new_from_points(&origin, &target))?;
+
+            let linear_first_hit = linear_scan
+                .first_hit(&ray)?
+                .expect("the room surrounds the rays");
+            let bvh_first_hit = bvh.first_hit(&ray)?.expect("the room surrounds the rays");
+            let linear_hits = linear_scan.all_hits(&ray)?;
+            let bvh_hits = bvh.all_hits(&ray)?;
+
+            assert_eq!(*bvh_first_hit.object.shape, *linear_first_hit.object.shape);
+            assert_eq!(bvh_first_hit.point_hit, linear_first_hit.point_hit);
+            assert_eq!(bvh_hits.len(), linear_hits.len());
+            for (bvh_hit, linear_hit) in bvh_hits.iter().zip(&linear_hits) {
+                assert_eq!(*bvh_hit.object.shape, *linear_hit.object.shape);
+            }
+            let grid_first_hit = uniform_grid.first_hit(&ray)?.expect("the room surrounds the rays");
+            let grid_hits = uniform_grid.all_hits(&ray)?;
+            assert_eq!(*grid_first_hit.object.shape, *linear_first_hit.object.shape);
+            assert_eq!(grid_first_hit.point_hit, linear_first_hit.point_hit);
+            assert_eq!(grid_hits.len(), linear_hits.len());
+            for (grid_hit, linear_hit) in grid_hits.iter().zip(&linear_hits) {
+                assert_eq!(*grid_hit.object.shape, *linear_hit.object.shape);
+            }
+            // * the segment to the target ends before the room
+            let distance = origin.distance(&target);
+            let is_occluded = linear_hits
+                .iter()
+                .any(|hit_info| hit_info.hit_distance > TOLERANCE && hit_info.hit_distance < distance);
+            assert_eq!(linear_scan.is_occluded(&ray, distance)?, is_occluded);
+            assert_eq!(bvh.is_occluded(&ray, distance)?, is_occluded);
+            assert_eq!(uniform_grid.is_occluded(&ray, distance)?, is_occluded);
+            assert_eq!(
+                grid_without_room.is_occluded(&ray, distance)?,
+                linear_scan_without_room.is_occluded(&ray, distance)?
+            );
+            // * without the room, the grid is only as large as the small spheres and many rays miss everything
+            assert_eq!(
+                grid_without_room.first_hit(&ray)?.map(|hit_info| hit_info.point_hit),
+                linear_scan_without_room.first_hit(&ray)?.map(|hit_info| hit_info.point_hit)
+            );
+        }
+        let ray = Ray::new(Point::new(0., 0., 0.), Vector::new_from_coordinates(0., 0., 1.))?;
+        assert!(Bvh::new(&[]).first_hit(&ray)?.is_none());
+        assert!(UniformGrid::new(&[]).first_hit(&ray)?.is_none());
+        // * a sphere in front of a plane
+        let sphere_before_plane = [
+            Object {
+                shape: Box::new(Plane::new(Point::new(0., 0., 20.), Vector::new_from_coordinates(0., 0., -1.))?),
+                material: Material::default(),
+                visibility: VisibilityFlags::default(),
+            },
+            Object {
+                shape: Box::new(Sphere::new_from_radius(&Point::new(0., 0., 10.), 1.)),
+                material: Material::default(),
+                visibility: VisibilityFlags::default(),
+            },
+        ];
+        assert_eq!(
+            UniformGrid::new(&sphere_before_plane).first_hit(&ray)?.map(|hit_info| hit_info.point_hit),
+            Some(Point::new(0., 0., 9.))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_uniform_grid_of_flat_scene() -> Result<(), RayTracingError> {
+        // * a floor of triangles, the grid has no thickness
+        let mut objects = Vec::new();
+        for x in -5..5 {
+            for y in -5..5 {
+                let corner = Point::new(x as Scalar, y as Scalar, 5.);
+                objects.push(Object {
+                    shape: Box::new(Triangle::new(
+                        corner,
+                        &corner + &Vector::new_from_coordinates(1., 0., 0.),
+                        &corner + &Vector::new_from_coordinates(0., 1., 0.),
+                    )),
+                    material: Material::default(),
+                    visibility: VisibilityFlags::default(),
+                });
+            }
+        }
+        let linear_scan = LinearScan::new(&objects);
+        let uniform_grid = UniformGrid::new(&objects);
+        let mut rng = XorShiftRng::seed_from_u64(13);
+
+        for _ in 0..300 {
+            let origin = Point::new(rng.gen_range(-8. ..8.), rng.gen_range(-8. ..8.), rng.gen_range(-5. ..15.));
+            let target = Point::new(rng.gen_range(-8. ..8.), rng.gen_range(-8. ..8.), 5.);
+            let ray = Ray::new(origin, Vector::new_from_points(&origin, &target))?;
+
+            let linear_first_hit = linear_scan.first_hit(&ray)?;
+            let grid_first_hit = uniform_grid.first_hit(&ray)?;
+
+            assert_eq!(
+                grid_first_hit.map(|hit_info| hit_info.point_hit),
+                linear_first_hit.map(|hit_info| hit_info.point_hit)
+            );
+            assert_eq!(uniform_grid.all_hits(&ray)?.len(), linear_scan.all_hits(&ray)?.len());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_refit() -> Result<(), RayTracingError> {
+        let mut rng = XorShiftRng::seed_from_u64(11);
+        let mut random_vector = |half_size: Scalar| {
+            Vector::new_from_coordinates(
+                rng.gen_range(-half_size..half_size),
+                rng.gen_range(-half_size..half_size),
+                rng.gen_range(-half_size..half_size),
+            )
+        };
+        let origin = Point::new(0., 0., 0.);
+        let objects: Vec<Object> = (0..40)
+            .map(|_| Object {
+                shape: Box::new(Sphere::new_from_radius(&(&origin + &random_vector(20.)), 1.5)),
+                material: Material::default(),
+                visibility: VisibilityFlags::default(),
+            })
+            .collect();
+        let mut bvh = Bvh::new(&objects);
+        let offsets: Vec<Vector> = (0..objects.len()).map(|_| random_vector(1.)).collect();
+
+        // * every object moves a little, the tree is kept
+        for (object, offset) in bvh.objects_mut().iter_mut().zip(&offsets) {
+            object.shape.translate(offset);
+        }
+        assert!(!bvh.refit());
+        let rebuilt_bvh = Bvh::new(bvh.objects_mut());
+        for _ in 0..500 {
+            let ray_origin = &origin + &random_vector(25.);
+            let ray = Ray::new(ray_origin, Vector::new_from_points(&ray_origin, &(&origin + &random_vector(25.))))?;
+
+            let refitted_hits = bvh.all_hits(&ray)?;
+            let rebuilt_hits = rebuilt_bvh.all_hits(&ray)?;
+
+            assert_eq!(refitted_hits.len(), rebuilt_hits.len());
+            for (refitted_hit, rebuilt_hit) in refitted_hits.iter().zip(&rebuilt_hits) {
+                assert_eq!(*refitted_hit.object.shape, *rebuilt_hit.object.shape);
+            }
+            assert_eq!(
+                bvh.first_hit(&ray)?.map(|hit_info| hit_info.point_hit),
+                rebuilt_bvh.first_hit(&ray)?.map(|hit_info| hit_info.point_hit)
+            );
+        }
+        // * the objects scatter far from where the tree was built, it is built again
+        for object in bvh.objects_mut() {
+            object.shape.translate(&random_vector(200.));
+        }
+        assert!(bvh.refit());
+
+        Ok(())
+    }
+}
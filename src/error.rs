@@ -1,15 +1,19 @@
-use crate::geometry::{point::Point, shape::Sphere};
+use crate::geometry::{point::Point, shape::Sphere, Scalar};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum RayTracingError {
-    #[error("Can't create vector of norme 1 from vector of norme 0")]
+    #[error("Can't create vector of norme 1 from vector of norme 0: {0}")]
     UnitVectorFromZeroVector(String),
     #[error("The point {0:?} is not a point of the sphere {1:?}")]
     PointNotOnSphere(Point, Sphere),
     #[error("There is no sphere of index {0}, the number of spheres is {1}")]
     NoSphereAtIndex(usize, usize),
-    #[error("The ray from point {0:?} to point {1:?} doesn't go through the point {1:?} (uh ?)")]
+    #[error("There is no object of index {0}, the number of objects is {1}")]
+    NoObjectAtIndex(usize, usize),
+    #[error("There is no material of id {0}, the number of materials is {1}")]
+    NoMaterialWithId(usize, usize),
+    #[error("The ray from point {0:?} to point {1:?} doesn't go through its destination point (uh ?)")]
     RayBetweenPointsDoesNotHitPoint(Point, Point),
     #[error("Source is not visible from point: {0}")]
     SourceNotVisibleFromPoint(String),
@@ -20,7 +24,106 @@ pub enum RayTracingError {
     #[error("Diffusion value should be a float coefficient between 0 and 1, got : dr = {0} | dg = {1} | db = {2}")]
     DiffusionCoefficientOOB(f32, f32, f32),
     #[error("Value should be a float coefficient between {1} and {2}, got {0}")]
-    CoefficientOOB(f64, f64, f64),
+    CoefficientOOB(Scalar, Scalar, Scalar),
+    #[error("Grids should have the same dimensions, got {0}x{1} and {2}x{3}")]
+    GridDimensionsMismatch(usize, usize, usize, usize),
     #[error("The iterator doesn't have values anymore")]
     IteratorDepleted(),
+    #[error("Could not read the scene: {0}")]
+    InvalidScene(String),
+    #[error("Could not read the render state: {0}")]
+    InvalidRenderState(String),
+    #[error("Input/output error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("{0}: {1}")]
+    WithContext(String, Box<RayTracingError>),
+}
+
+impl RayTracingError {
+    /// Prepends what the caller was doing to the error message
+    pub fn context(self, context: &str) -> Self {
+        RayTracingError::WithContext(context.to_string(), Box::new(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POINT_1: Point = Point {
+        x: 1.5,
+        y: -2.,
+        z: 7.,
+    };
+    const POINT_2: Point = Point {
+        x: -4.,
+        y: 0.25,
+        z: 12.,
+    };
+
+    #[test]
+    fn test_display_contains_data() {
+        let sphere = Sphere::new_from_radius(&POINT_2, 3.5);
+        let messages = [
+            (
+                RayTracingError::UnitVectorFromZeroVector("vector from the eye".to_string()),
+                vec!["vector from the eye"],
+            ),
+            (
+                RayTracingError::PointNotOnSphere(POINT_1, sphere),
+                vec!["x: 1.5", "radius: 3.5"],
+            ),
+            (RayTracingError::NoSphereAtIndex(7, 3), vec!["7", "3"]),
+            (RayTracingError::NoObjectAtIndex(5, 2), vec!["object of index 5", "2"]),
+            (RayTracingError::NoMaterialWithId(12, 4), vec!["12", "4"]),
+            (
+                RayTracingError::SourceNotVisibleFromPoint("source behind".to_string()),
+                vec!["source behind"],
+            ),
+            (
+                RayTracingError::ColorCoefficientOOB(1.5, 0.25, -1.),
+                vec!["r = 1.5", "g = 0.25", "b = -1"],
+            ),
+            (
+                RayTracingError::DiffusionCoefficientOOB(2., 0.5, 0.75),
+                vec!["dr = 2", "dg = 0.5", "db = 0.75"],
+            ),
+            (
+                RayTracingError::CoefficientOOB(1.25, 0., 1.),
+                vec!["between 0 and 1", "got 1.25"],
+            ),
+        ];
+
+        for (error, expected_data) in messages {
+            let message = error.to_string();
+            for data in expected_data {
+                assert!(
+                    message.contains(data),
+                    "message \"{}\" should contain \"{}\"",
+                    message,
+                    data
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_ray_between_points_shows_both_points() {
+        let message = RayTracingError::RayBetweenPointsDoesNotHitPoint(POINT_1, POINT_2).to_string();
+
+        assert!(message.contains(&format!("{:?}", POINT_1)));
+        assert!(message.contains(&format!("{:?}", POINT_2)));
+    }
+
+    #[test]
+    fn test_context() {
+        let error = RayTracingError::NoSphereAtIndex(7, 3)
+            .context("visibility of the light source")
+            .context("rendering pixel (3, 4)");
+
+        assert_eq!(
+            error.to_string(),
+            "rendering pixel (3, 4): visibility of the light source: There is no sphere of index 7, the number of spheres is 3"
+        );
+    }
 }
@@ -1,4 +1,60 @@
+pub mod aabb;
+pub mod csg;
 pub mod point;
 pub mod ray;
 pub mod shape;
+pub mod triangle;
 pub mod vector;
+
+/// Floating point type of the geometry, `f32` with the `single-precision` feature to halve the memory it takes
+#[cfg(not(feature = "single-precision"))]
+pub type Scalar = f64;
+#[cfg(feature = "single-precision")]
+pub type Scalar = f32;
+
+#[cfg(not(feature = "single-precision"))]
+pub use std::f64::consts;
+#[cfg(feature = "single-precision")]
+pub use std::f32::consts;
+
+/// Distances below this are taken as zero, for instance points closer than this are equal, a picometer in double precision
+/// which is pretty small (smaller than an atom of hydrogen by a factor 10 at least)
+#[cfg(not(feature = "single-precision"))]
+pub const TOLERANCE: Scalar = 1e-12;
+#[cfg(feature = "single-precision")]
+pub const TOLERANCE: Scalar = 1e-5;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{error::RayTracingError, object::{Object, VisibilityFlags}, optic::material::Material};
+    use point::Point;
+    use ray::Ray;
+    use shape::Sphere;
+    use vector::Vector;
+
+    #[test]
+    fn test_geometry_within_tolerance() -> Result<(), RayTracingError> {
+        assert_eq!(std::mem::size_of::<Point>(), 3 * std::mem::size_of::<Scalar>());
+
+        let direction = Vector::new_from_coordinates(1., 2., 2.);
+        assert!((direction.normalize()?.norme_vec() - 1.).abs() < TOLERANCE);
+        assert!((direction.norme_vec() - 3.).abs() < TOLERANCE);
+
+        let object = Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(0., 0., 5.), 1.)),
+            material: Material::default(),
+            visibility: VisibilityFlags::default(),
+        };
+        let ray = Ray::new(Point::new(0., 0., 0.), Vector::new_from_coordinates(0., 0., 1.))?;
+        let hit_info = ray.intersect(&object)?.expect("the ray goes through the center of the sphere");
+        assert!((hit_info.hit_distance - 4.).abs() < TOLERANCE);
+        assert_eq!(hit_info.point_hit, Point::new(0., 0., 4.));
+        assert!(object.shape.as_sphere().unwrap().point_is_on_sphere(&hit_info.point_hit));
+
+        let reflected = Vector::new_from_coordinates(1., -1., 0.).reflect(&Vector::new_from_coordinates(0., 1., 0.))?;
+        assert!(reflected == Vector::new_from_coordinates(1., 1., 0.));
+
+        Ok(())
+    }
+}
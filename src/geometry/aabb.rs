@@ -0,0 +1,155 @@
+use super::point::Point;
+use super::ray::Ray;
+use super::shape::Sphere;
+use super::Scalar;
+use crate::error::RayTracingError;
+
+/// Axis aligned bounding box, `min` holds the smallest coordinates and `max` the largest
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Point,
+    pub max: Point,
+}
+
+impl Aabb {
+    /// Box having the two points as opposite corners, in any order
+    pub fn new(corner_1: &Point, corner_2: &Point) -> Self {
+        Aabb {
+            min: Point::new(
+                corner_1.x.min(corner_2.x),
+                corner_1.y.min(corner_2.y),
+                corner_1.z.min(corner_2.z),
+            ),
+            max: Point::new(
+                corner_1.x.max(corner_2.x),
+                corner_1.y.max(corner_2.y),
+                corner_1.z.max(corner_2.z),
+            ),
+        }
+    }
+
+    /// Smallest box containing the sphere
+    pub fn from_sphere(sphere: &Sphere) -> Self {
+        let Point { x, y, z } = sphere.center;
+        let radius = sphere.radius();
+        Aabb {
+            min: Point::new(x - radius, y - radius, z - radius),
+            max: Point::new(x + radius, y + radius, z + radius),
+        }
+    }
+
+    /// Smallest box containing both boxes
+    pub fn union(&self, other: &Aabb) -> Self {
+        Aabb::new(
+            &Point::new(
+                self.min.x.min(other.min.x),
+                self.min.y.min(other.min.y),
+                self.min.z.min(other.min.z),
+            ),
+            &Point::new(
+                self.max.x.max(other.max.x),
+                self.max.y.max(other.max.y),
+                self.max.z.max(other.max.z),
+            ),
+        )
+    }
+
+    /// Area of the six faces of the box
+    pub fn surface_area(&self) -> Scalar {
+        let (x, y, z) = (self.max.x - self.min.x, self.max.y - self.min.y, self.max.z - self.min.z);
+        2. * (x * y + y * z + z * x)
+    }
+
+    /// Distance along the ray to where it enters the box, 0 if it starts inside, None if it misses the box
+    pub fn entry_distance(&self, ray: &Ray) -> Result<Option<Scalar>, RayTracingError> {
+        let direction = ray.normalized_direction()?;
+        let mut entry: Scalar = 0.;
+        let mut exit = Scalar::INFINITY;
+        // * slab method, the ray is in the box where it is between the two planes of every axis
+        for (origin, direction, min, max) in [
+            (ray.origin.x, direction.x, self.min.x, self.max.x),
+            (ray.origin.y, direction.y, self.min.y, self.max.y),
+            (ray.origin.z, direction.z, self.min.z, self.max.z),
+        ] {
+            if direction == 0. {
+                if origin < min || origin > max {
+                    return Ok(None);
+                }
+                continue;
+            }
+            let (distance_1, distance_2) = ((min - origin) / direction, (max - origin) / direction);
+            entry = entry.max(distance_1.min(distance_2));
+            exit = exit.min(distance_1.max(distance_2));
+        }
+        Ok(if entry <= exit { Some(entry) } else { None })
+    }
+
+    /// The 8 corners, the bits of the index tell which of min (0) or max (1) is taken for x, y and z
+    pub fn corners(&self) -> [Point; 8] {
+        std::array::from_fn(|index| {
+            Point::new(
+                if index & 1 == 0 { self.min.x } else { self.max.x },
+                if index & 2 == 0 { self.min.y } else { self.max.y },
+                if index & 4 == 0 { self.min.z } else { self.max.z },
+            )
+        })
+    }
+
+    /// The 12 edges as pairs of corners
+    pub fn edges(&self) -> [(Point, Point); 12] {
+        let corners = self.corners();
+        // * two corners are linked by an edge when their indices differ by a single bit
+        let mut edges = [(self.min, self.min); 12];
+        let mut edge_index = 0;
+        for (corner_index, corner) in corners.iter().enumerate() {
+            for bit in [1, 2, 4] {
+                if corner_index & bit == 0 {
+                    edges[edge_index] = (*corner, corners[corner_index | bit]);
+                    edge_index += 1;
+                }
+            }
+        }
+        edges
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::vector::Vector;
+
+    #[test]
+    fn test_entry_distance() -> Result<(), RayTracingError> {
+        let aabb = Aabb::new(&Point::new(-1., -1., 4.), &Point::new(1., 1., 6.));
+        let along_z = |x: Scalar, z: Scalar| Ray::new(Point::new(x, 0., z), Vector::new_from_coordinates(0., 0., 3.));
+
+        assert_eq!(aabb.entry_distance(&along_z(0., 0.)?)?, Some(4.));
+        assert_eq!(aabb.entry_distance(&along_z(0., 5.)?)?, Some(0.));
+        assert_eq!(aabb.entry_distance(&along_z(2., 0.)?)?, None);
+        assert_eq!(aabb.entry_distance(&along_z(0., 7.)?)?, None);
+        let diagonal = Ray::new(Point::new(-3., 0., 0.), Vector::new_from_coordinates(1., 0., 1.))?;
+        assert!(aabb.entry_distance(&diagonal)?.is_some());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_edges() {
+        let aabb = Aabb::new(&Point::new(1., 2., 3.), &Point::new(-1., 0., 0.));
+        let edges = aabb.edges();
+
+        assert_eq!(aabb.min, Point::new(-1., 0., 0.));
+        assert_eq!(aabb.max, Point::new(1., 2., 3.));
+        for (start, end) in edges {
+            // * an edge goes along a single axis
+            let differing_coordinates = [start.x != end.x, start.y != end.y, start.z != end.z]
+                .iter()
+                .filter(|differs| **differs)
+                .count();
+            assert_eq!(differing_coordinates, 1);
+        }
+        for (index, edge) in edges.iter().enumerate() {
+            assert!(!edges[index + 1..].contains(edge));
+        }
+    }
+}
@@ -0,0 +1,310 @@
+use super::aabb::Aabb;
+use super::point::Point;
+use super::ray::Ray;
+use super::shape::{Shape, ShapeHit};
+use super::vector::Vector;
+use super::{consts, Scalar};
+use crate::error::RayTracingError;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CsgOp {
+    /// what is inside `a` but not inside `b`
+    Difference,
+    /// what is inside both `a` and `b`
+    Intersection,
+}
+
+/// Constructive solid geometry from two solids, the operands must give the part of a ray inside of them with
+/// `Shape::intersect_both`, such as spheres or intersections of them
+#[derive(Debug, Clone)]
+pub struct Csg {
+    pub a: Box<dyn Shape>,
+    pub b: Box<dyn Shape>,
+    pub op: CsgOp,
+}
+
+impl PartialEq for Csg {
+    fn eq(&self, other: &Self) -> bool {
+        *self.a == *other.a && *self.b == *other.b && self.op == other.op
+    }
+}
+
+/// Operand whose surface bounds a part of the solid
+#[derive(Clone, Copy)]
+enum Operand {
+    A,
+    B,
+}
+
+impl Csg {
+    pub fn new(a: Box<dyn Shape>, b: Box<dyn Shape>, op: CsgOp) -> Self {
+        Csg { a, b, op }
+    }
+
+    /// Normal pointing out of the solid at a point on the surface of `operand`
+    fn operand_normal(&self, operand: Operand, point: &Point) -> Vector {
+        match (operand, self.op) {
+            (Operand::A, _) => self.a.normal_at(point),
+            (Operand::B, CsgOp::Intersection) => self.b.normal_at(point),
+            // the surface of b bounds the hollow it carves in a, the solid is outside of b
+            (Operand::B, CsgOp::Difference) => -self.b.normal_at(point),
+        }
+    }
+
+    /// Parts of the ray inside the solid, each bound is tagged with the operand it lies on
+    fn inside_intervals(&self, ray: &Ray) -> Result<Vec<Interval>, RayTracingError> {
+        let Some((a_entry, a_exit)) = self.a.intersect_both(ray)? else {
+            // both operations only keep parts of a
+            return Ok(Vec::new());
+        };
+        let b_interval = self.b.intersect_both(ray)?;
+
+        let mut intervals = Vec::with_capacity(2);
+        match (self.op, b_interval) {
+            (CsgOp::Difference, None) => intervals.push(((a_entry, Operand::A), (a_exit, Operand::A))),
+            (CsgOp::Difference, Some((b_entry, b_exit))) => {
+                if a_entry < b_entry.min(a_exit) {
+                    intervals.push(if b_entry < a_exit {
+                        ((a_entry, Operand::A), (b_entry, Operand::B))
+                    } else {
+                        ((a_entry, Operand::A), (a_exit, Operand::A))
+                    });
+                }
+                if a_entry.max(b_exit) < a_exit {
+                    intervals.push(if a_entry < b_exit {
+                        ((b_exit, Operand::B), (a_exit, Operand::A))
+                    } else {
+                        ((a_entry, Operand::A), (a_exit, Operand::A))
+                    });
+                }
+            }
+            (CsgOp::Intersection, None) => {}
+            (CsgOp::Intersection, Some((b_entry, b_exit))) => {
+                let entry = if a_entry >= b_entry { (a_entry, Operand::A) } else { (b_entry, Operand::B) };
+                let exit = if a_exit <= b_exit { (a_exit, Operand::A) } else { (b_exit, Operand::B) };
+                if entry.0 < exit.0 {
+                    intervals.push((entry, exit));
+                }
+            }
+        }
+        Ok(intervals)
+    }
+
+    /// Areas of the surface of a inside b and of the surface of b inside a, from the caps cut by the circle where
+    /// two spheres meet, None for the other operands
+    fn areas_inside_other(&self) -> Option<(Scalar, Scalar)> {
+        let (a, b) = (self.a.as_sphere()?, self.b.as_sphere()?);
+        let (a_radius, b_radius) = (a.radius(), b.radius());
+        let distance = a.center.distance(&b.center);
+        Some(if distance >= a_radius + b_radius {
+            (0., 0.)
+        } else if distance + b_radius <= a_radius {
+            (0., b.surface_area())
+        } else if distance + a_radius <= b_radius {
+            (a.surface_area(), 0.)
+        } else {
+            // * distance from the center of each sphere to the plane of the circle, towards the other center
+            let a_to_circle = (distance * distance + a_radius * a_radius - b_radius * b_radius) / (2. * distance);
+            let b_to_circle = distance - a_to_circle;
+            (
+                2. * consts::PI * a_radius * (a_radius - a_to_circle),
+                2. * consts::PI * b_radius * (b_radius - b_to_circle),
+            )
+        })
+    }
+}
+
+/// Entry and exit distances along a ray, with the operand on whose surface they are
+type Interval = ((Scalar, Operand), (Scalar, Operand));
+
+/// Distance from the point to the surface of the solid along its normal, infinite if the shape can't tell
+fn distance_to_surface(shape: &dyn Shape, point: &Point) -> Scalar {
+    let Ok(ray) = Ray::new(*point, shape.normal_at(point)) else {
+        return Scalar::INFINITY;
+    };
+    match shape.intersect_both(&ray) {
+        Ok(Some((entry, exit))) => entry.abs().min(exit.abs()),
+        _ => Scalar::INFINITY,
+    }
+}
+
+impl Shape for Csg {
+    /// Nearest point in front of the ray where it enters or leaves the solid
+    fn intersect(&self, ray: &Ray) -> Result<Option<ShapeHit>, RayTracingError> {
+        let nearest_bound = self
+            .inside_intervals(ray)?
+            .iter()
+            .flat_map(|(entry, exit)| [*entry, *exit])
+            .filter(|(distance, _)| *distance >= 0.)
+            .min_by(|(distance_1, _), (distance_2, _)| distance_1.total_cmp(distance_2));
+        let Some((hit_distance, operand)) = nearest_bound else {
+            return Ok(None);
+        };
+
+        let point_hit = ray.point_at_a_distance(hit_distance)?;
+        Ok(Some(ShapeHit {
+            point_hit,
+            normal: self.operand_normal(operand, &point_hit),
+            hit_distance,
+            uv: self.uv_at(&point_hit),
+        }))
+    }
+
+    /// The point is taken to be on the surface of the operand it is the closest to
+    fn normal_at(&self, point: &Point) -> Vector {
+        let operand = if distance_to_surface(&*self.a, point) <= distance_to_surface(&*self.b, point) {
+            Operand::A
+        } else {
+            Operand::B
+        };
+        self.operand_normal(operand, point)
+    }
+
+    /// Both operations only keep parts of a
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.a.bounding_box()
+    }
+
+    fn position(&self) -> Point {
+        self.a.position()
+    }
+
+    fn translate(&mut self, offset: &Vector) {
+        self.a.translate(offset);
+        self.b.translate(offset);
+    }
+
+    /// Exact for two spheres, for the other operands it is the area of both of them, which is more than the surface
+    fn surface_area(&self) -> Scalar {
+        let Some((a_inside_b, b_inside_a)) = self.areas_inside_other() else {
+            return self.a.surface_area() + self.b.surface_area();
+        };
+        match self.op {
+            CsgOp::Intersection => a_inside_b + b_inside_a,
+            CsgOp::Difference => self.a.surface_area() - a_inside_b + b_inside_a,
+        }
+    }
+
+    /// The bite taken by a difference is hollow
+    fn is_concave(&self) -> bool {
+        self.op == CsgOp::Difference
+    }
+
+    /// Only an intersection is left in one piece along any ray, as its operands are
+    fn intersect_both(&self, ray: &Ray) -> Result<Option<(Scalar, Scalar)>, RayTracingError> {
+        Ok(match self.op {
+            CsgOp::Intersection => self
+                .inside_intervals(ray)?
+                .first()
+                .map(|((entry, _), (exit, _))| (*entry, *exit)),
+            CsgOp::Difference => None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::{shape::{Plane, Sphere}, TOLERANCE};
+    use float_cmp::approx_eq;
+
+    const ORIGIN: Point = Point {
+        x: 0.,
+        y: 0.,
+        z: 0.,
+    };
+
+    fn ray_along_z(x: Scalar) -> Result<Ray, RayTracingError> {
+        Ray::new(Point::new(x, 0., 0.), Vector::new_from_coordinates(0., 0., 1.))
+    }
+
+    #[test]
+    fn test_difference_bite() -> Result<(), RayTracingError> {
+        // * a bite is taken out of the front of a
+        let bitten = Csg::new(
+            Box::new(Sphere::new_from_radius(&Point::new(0., 0., 5.), 1.)),
+            Box::new(Sphere::new_from_radius(&Point::new(0., 0., 4.), 0.5)),
+            CsgOp::Difference,
+        );
+
+        let hit_in_bite = bitten.intersect(&ray_along_z(0.)?)?.expect("the ray goes through a");
+        let hit_beside_bite = bitten.intersect(&ray_along_z(0.8)?)?.expect("the ray goes through a");
+
+        assert!(approx_eq!(Scalar, hit_in_bite.hit_distance, 4.5, epsilon = TOLERANCE));
+        // * the bottom of the bite faces the ray
+        assert!(hit_in_bite.normal.normalize()? == Vector::new_from_coordinates(0., 0., -1.));
+        assert!(approx_eq!(Scalar, hit_beside_bite.hit_distance, 5. - 0.6, epsilon = TOLERANCE));
+        assert!(bitten.intersect(&ray_along_z(2.)?)?.is_none());
+        // * starting inside the bite, the ray meets the surface of b first
+        let ray_in_bite = Ray::new(Point::new(0., 0., 4.), Vector::new_from_coordinates(0., 0., 1.))?;
+        let hit_from_bite = bitten.intersect(&ray_in_bite)?.expect("the ray is in front of the bottom of the bite");
+        assert!(approx_eq!(Scalar, hit_from_bite.hit_distance, 0.5, epsilon = TOLERANCE));
+        // * the cap of a taken away is smaller than the bottom of the bite
+        assert!(approx_eq!(Scalar, bitten.surface_area(), 4.125 * consts::PI, epsilon = TOLERANCE));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_intersection_lens() -> Result<(), RayTracingError> {
+        let lens = Csg::new(
+            Box::new(Sphere::new_from_radius(&Point::new(0., 0., 5.), 1.)),
+            Box::new(Sphere::new_from_radius(&Point::new(0., 0., 6.), 1.)),
+            CsgOp::Intersection,
+        );
+
+        let hit = lens.intersect(&ray_along_z(0.)?)?.expect("the ray goes through the lens");
+
+        assert!(approx_eq!(Scalar, hit.hit_distance, 5., epsilon = TOLERANCE));
+        assert_eq!(hit.point_hit, Point::new(0., 0., 5.));
+        // * the front face of the lens is on b
+        assert!(hit.normal.normalize()? == Vector::new_from_coordinates(0., 0., -1.));
+        // * the ray goes through both spheres but never through both at the same time
+        assert!(lens.intersect(&ray_along_z(0.9)?)?.is_none());
+        // * two caps of height 1/2
+        assert!(approx_eq!(Scalar, lens.surface_area(), 2. * consts::PI, epsilon = TOLERANCE));
+        let disjoint = Csg::new(
+            Box::new(Sphere::new_from_radius(&ORIGIN, 1.)),
+            Box::new(Sphere::new_from_radius(&Point::new(0., 0., 5.), 1.)),
+            CsgOp::Intersection,
+        );
+        assert!(disjoint.intersect(&ray_along_z(0.)?)?.is_none());
+        assert_eq!(disjoint.surface_area(), 0.);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_nested_operands() -> Result<(), RayTracingError> {
+        let lens = Csg::new(
+            Box::new(Sphere::new_from_radius(&Point::new(0., 0., 5.), 1.)),
+            Box::new(Sphere::new_from_radius(&Point::new(0., 0., 6.), 1.)),
+            CsgOp::Intersection,
+        );
+        // * the front half of the lens is left, its back is on the surface of the carving sphere
+        let half_lens = Csg::new(
+            Box::new(lens.clone()),
+            Box::new(Sphere::new_from_radius(&Point::new(0., 0., 7.), 1.5)),
+            CsgOp::Difference,
+        );
+
+        let hit = half_lens.intersect(&ray_along_z(0.)?)?.expect("the ray goes through the lens");
+        let ray_inside = Ray::new(Point::new(0., 0., 5.2), Vector::new_from_coordinates(0., 0., 1.))?;
+        let back_hit = half_lens.intersect(&ray_inside)?.expect("the ray leaves the half lens");
+
+        let (entry, exit) = lens.intersect_both(&ray_along_z(0.)?)?.expect("the ray goes through the lens");
+        assert!(approx_eq!(Scalar, entry, 5., epsilon = TOLERANCE) && approx_eq!(Scalar, exit, 6., epsilon = TOLERANCE));
+        assert!(approx_eq!(Scalar, hit.hit_distance, 5., epsilon = TOLERANCE));
+        assert!(approx_eq!(Scalar, back_hit.hit_distance, 0.3, epsilon = TOLERANCE));
+        // * the back faces away from the center of the carving sphere, out of the solid
+        assert!(back_hit.normal.normalize()? == Vector::new_from_coordinates(0., 0., 1.));
+        assert!(half_lens.normal_at(&back_hit.point_hit).normalize()? == back_hit.normal.normalize()?);
+        assert!(half_lens.intersect_both(&ray_along_z(0.)?)?.is_none());
+        // * a plane bounds no solid, nothing is left of an intersection with it
+        let floor = Plane::new(ORIGIN, Vector::new_from_coordinates(0., 1., 0.))?;
+        let cut = Csg::new(Box::new(Sphere::new_from_radius(&ORIGIN, 1.)), Box::new(floor), CsgOp::Intersection);
+        assert!(cut.intersect(&ray_along_z(0.)?)?.is_none());
+
+        Ok(())
+    }
+}
@@ -1,30 +1,48 @@
-use crate::geometry::vector::Vector;
+use crate::geometry::{ray::Ray, vector::Vector, Scalar, TOLERANCE};
 use std::cmp::PartialEq;
 use std::ops::{Add, Sub};
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Point {
-    pub x: f64,
-    pub y: f64,
-    pub z: f64,
+    pub x: Scalar,
+    pub y: Scalar,
+    pub z: Scalar,
 }
 
 impl Point {
-    pub fn new(x: f64, y: f64, z: f64) -> Self {
+    pub const fn new(x: Scalar, y: Scalar, z: Scalar) -> Self {
         Point { x, y, z }
     }
-    pub fn distance(&self, other: &Point) -> f64 {
+    #[must_use]
+    pub fn distance(&self, other: &Point) -> Scalar {
         let Point { x, y, z } = self - other;
         Vector::norme(x, y, z)
     }
+
+    /// Index of the cell of size `cell` containing the point, points closer than `cell` usually share the same key
+    /// so it can be used to weld vertices with a `HashMap`
+    pub fn quantized_key(&self, cell: Scalar) -> (i64, i64, i64) {
+        (
+            (self.x / cell).round() as i64,
+            (self.y / cell).round() as i64,
+            (self.z / cell).round() as i64,
+        )
+    }
+
+    /// Perpendicular distance from the point to the infinite line carrying the ray
+    #[must_use]
+    pub fn distance_to_ray(&self, ray: &Ray) -> Scalar {
+        let origin_to_point = Vector::new_from_points(&ray.origin, self);
+        origin_to_point.cross_product(&ray.direction()).norme_vec() / ray.direction().norme_vec()
+    }
 }
 
 impl PartialEq for Point {
     fn eq(&self, other: &Self) -> bool {
         let Point { x, y, z } = self - other;
         let distance = Vector::norme(x, y, z);
-        (0. ..=1e-12_f64).contains(&distance)
-        // ie if points are closer than a picometer, which is pretty small (smaller than an atom of hydrogen by a factor 10 at least)
+        (0. ..=TOLERANCE).contains(&distance)
     }
 }
 
@@ -121,4 +139,28 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_distance_to_ray() -> Result<(), RayTracingError> {
+        let ray = Ray::new(POINT_2, Vector::new_from_coordinates(2., 0., 0.))?;
+        let point_on_ray = Point::new(5., 0., -2.);
+        let point_off_ray = Point::new(5., 0., 1.);
+
+        assert!(point_on_ray.distance_to_ray(&ray).abs() < 1e-12);
+        assert!((point_off_ray.distance_to_ray(&ray) - 3.).abs() < 1e-12);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_quantized_key() {
+        let cell = 1e-3;
+        let point = Point::new(1.0001, -2.0002, 3.);
+        let close_point = Point::new(1.0003, -2.0001, 3.0002);
+        let far_point = Point::new(1.0021, -2.0002, 3.);
+
+        assert_eq!(point.quantized_key(cell), close_point.quantized_key(cell));
+        assert_ne!(point.quantized_key(cell), far_point.quantized_key(cell));
+        assert_eq!(point.quantized_key(cell), (1000, -2000, 3000));
+    }
 }
@@ -2,31 +2,159 @@ use crate::error::RayTracingError;
 use crate::object::Object;
 
 use super::point::Point;
-use super::shape::Sphere;
+use super::shape::{Shape, Sphere};
 use super::vector::Vector;
+use super::{Scalar, TOLERANCE};
 
 use rand_xorshift::{self, XorShiftRng};
-use rand_distr::{self, DistIter, UnitDisc, UnitSphere};
+use rand_distr::{self, DistIter, UnitSphere};
+
+/// Distance by which secondary rays are pushed off the surface they start from
+#[cfg(not(feature = "single-precision"))]
+pub const SPAWN_OFFSET: Scalar = 1e-9;
+#[cfg(feature = "single-precision")]
+pub const SPAWN_OFFSET: Scalar = 1e-4;
+const MAX_GLOSSY_SAMPLES: usize = 16;
+
+/// What a ray is traced for, each object says which kinds of rays can hit it, see `VisibilityFlags`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum RayKind {
+    Camera,
+    Shadow,
+    Reflection,
+}
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Ray {
     pub origin: Point,
-    pub direction: Vector,
+    direction: Vector,
+    unit_direction: Option<Vector>, // * cached as every intersection needs it, None if the direction is zero
 }
 
 impl Ray {
-    pub fn new_from_points(origin: &Point, destination: &Point) -> Result<Self, RayTracingError> {
-        let dest = Vector::new_from_points(origin, destination);
+    /// Fails if the direction is zero
+    pub fn new(origin: Point, direction: Vector) -> Result<Self, RayTracingError> {
+        let unit_direction = direction.normalize()?;
         Ok(Ray {
-            origin: *origin,
-            direction: dest,
+            origin,
+            direction,
+            unit_direction: Some(unit_direction),
         })
     }
 
-    pub fn intersect<'a>(
+    /// Doesn't check the direction, any use of a ray with a zero direction fails later on,
+    /// meant for the hot paths of the renderer where the direction can't be zero, see `new` for the others
+    pub fn new_unchecked(origin: Point, direction: Vector) -> Self {
+        Ray {
+            origin,
+            direction,
+            unit_direction: direction.normalize().ok(),
+        }
+    }
+
+    pub fn direction(&self) -> Vector {
+        self.direction
+    }
+
+    /// Normalized direction computed once when the ray is built
+    pub fn normalized_direction(&self) -> Result<Vector, RayTracingError> {
+        self.unit_direction.ok_or(RayTracingError::VectorHasNormeZero)
+    }
+
+    /// Point at `distance` from the origin along the ray, the distance is measured along the normalized direction
+    /// like the hit distances are
+    pub fn point_at_a_distance(&self, distance: Scalar) -> Result<Point, RayTracingError> {
+        Ok(&self.origin + &(distance * &self.normalized_direction()?))
+    }
+
+    pub fn new_from_points(origin: &Point, destination: &Point) -> Result<Self, RayTracingError> {
+        let dest = Vector::new_from_points(origin, destination);
+        Ray::new(*origin, dest)
+    }
+
+    /// Builds a secondary ray starting from a surface point, the origin is moved off the surface along the normal
+    /// on the side the ray goes to, so that the ray doesn't intersect the surface it starts from due to float errors
+    pub fn spawn(origin: &Point, direction: Vector, normal: &Vector) -> Self {
+        let offset_origin = match normal.normalize() {
+            Ok(unit_normal) if direction.scalar_product(&unit_normal) >= 0. => {
+                origin + &(SPAWN_OFFSET * &unit_normal)
+            }
+            Ok(unit_normal) => origin + &(-SPAWN_OFFSET * &unit_normal),
+            // no normal to offset along, the ray is kept as is
+            Err(_) => *origin,
+        };
+        Ray::new_unchecked(offset_origin, direction)
+    }
+
+    /// Ray leaving the hit point in the mirror direction of this ray
+    pub fn reflect_about_normal(&self, hit_point: &Point, normal: &Vector) -> Result<Ray, RayTracingError> {
+        let reflected_direction = self.direction.reflect(normal)?;
+        Ok(Ray::spawn(hit_point, reflected_direction, normal))
+    }
+
+    /// Ray going through the surface at the hit point following Snell's law, from a medium of refractive index `ior_in`
+    /// to one of `ior_out`, None when the ray is totally reflected
+    pub fn refracted_ray(
         &self,
-        object: &'a Object,
-    ) -> Result<Option<HitInfo<'a>>, RayTracingError> {
+        hit_point: &Point,
+        normal: &Vector,
+        ior_in: Scalar,
+        ior_out: Scalar,
+    ) -> Result<Option<Ray>, RayTracingError> {
+        let direction = self.normalized_direction()?;
+        let mut unit_normal = normal.normalize()?;
+        // * the normal is taken on the side the ray comes from
+        let mut cos_incidence = -direction.scalar_product(&unit_normal);
+        if cos_incidence < 0. {
+            unit_normal = -unit_normal;
+            cos_incidence = -cos_incidence;
+        }
+        let ior_ratio = ior_in / ior_out;
+        let sin_squared_refraction = ior_ratio * ior_ratio * (1. - cos_incidence * cos_incidence);
+        if sin_squared_refraction > 1. {
+            return Ok(None);
+        }
+        let cos_refraction = (1. - sin_squared_refraction).sqrt();
+        let refracted_direction =
+            ior_ratio * &direction + (ior_ratio * cos_incidence - cos_refraction) * &unit_normal;
+        Ok(Some(Ray::spawn(hit_point, refracted_direction, &unit_normal)))
+    }
+
+    /// Mirror reflection perturbed in a cone whose width grows with `roughness`, directions going below the surface are drawn again
+    pub fn glossy_reflect_about_normal(
+        &self,
+        hit_point: &Point,
+        normal: &Vector,
+        roughness: Scalar,
+        unit_sphere_iter: &mut DistIter<UnitSphere, XorShiftRng, [Scalar; 3]>,
+    ) -> Result<Ray, RayTracingError> {
+        let mirror_direction = self.direction.reflect(normal)?.normalize()?;
+        if roughness == 0. {
+            return Ok(Ray::spawn(hit_point, mirror_direction, normal));
+        }
+        let unit_normal = normal.normalize()?;
+        for _ in 0..MAX_GLOSSY_SAMPLES {
+            let [x, y, z] = match unit_sphere_iter.next() {
+                Some(arr) => arr,
+                None => return Err(RayTracingError::IteratorDepleted()),
+            };
+            let perturbation = roughness * &Vector::new_from_coordinates(x, y, z);
+            let direction = match (mirror_direction + perturbation).normalize() {
+                Ok(direction) => direction,
+                Err(_) => continue,
+            };
+            // the mirror direction is on the same side of the surface as the normal
+            if direction.scalar_product(&unit_normal) * mirror_direction.scalar_product(&unit_normal) > 0. {
+                return Ok(Ray::spawn(hit_point, direction, normal));
+            }
+        }
+        // grazing reflection where every perturbed direction went below the surface
+        Ok(Ray::spawn(hit_point, mirror_direction, normal))
+    }
+
+    /// Distances along the normalized direction at which the ray enters and leaves the sphere, smallest first,
+    /// negative distances are behind the origin of the ray
+    pub fn intersect_both(&self, sphere: &Sphere) -> Result<Option<(Scalar, Scalar)>, RayTracingError> {
         /* A sphere and a ray intersect if and only if the equation:
         d^2 + 2d(u . CO) + CO^2 - r^2 = 0
         has solutions, where:
@@ -41,89 +169,99 @@ impl Ray {
         b = 2(u . CO)
         c = CO^2 - r^2
         The equation is d^2 + bd + c = 0 (classic quadratic form)
+
+        For spheres far from the origin of the ray, CO^2 and r^2 are of very different magnitudes and the usual
+        formulas lose most of their precision, so (see Ray Tracing Gems, chapter 7):
+        - delta = b^2 - 4c is computed as 4(r^2 - |CO - (u . CO)u|^2), using the distance from C to the ray
+        - c is computed as (|CO| - r)(|CO| + r)
+        - the root of largest magnitude q is computed first, the other one is c / q
         */
-        let normalized_dir = &self.direction.normalize()?;
-        let eps = 1.0e-12_f64;
-        let vector_co = Vector::new_from_points(&object.shape.center, &self.origin);
-        let b = 2. * normalized_dir.scalar_product(&vector_co);
-        let c = vector_co.scalar_product(&vector_co) - object.shape.radius.powi(2);
-        let delta = b * b - 4. * c;
-
-        if cfg!(test) {
-            println!("Ray::intersect : delta = {}", delta)
-        }
+        let normalized_dir = &self.normalized_direction()?;
+        let eps = TOLERANCE;
+        let vector_co = Vector::new_from_points(&sphere.center, &self.origin);
+        let half_b = normalized_dir.scalar_product(&vector_co);
+        let b = 2. * half_b;
+        let distance_to_center = vector_co.norme_vec();
+        let c = (distance_to_center - sphere.radius()) * (distance_to_center + sphere.radius());
+        let center_to_ray = &vector_co - &(half_b * normalized_dir);
+        let delta = 4. * (sphere.radius_squared() - center_to_ray.scalar_product(&center_to_ray));
+
+        log::trace!("Ray::intersect : delta = {}", delta);
 
         if delta < -eps {
-            return Ok(None)
+            Ok(None)
+        } else if (-eps..=eps).contains(&delta) {
+            Ok(Some((-b / 2., -b / 2.)))
         } else {
-            // ? so it is possible to have non assigned value if later on we see we will always assign something to it
-            let hit_distance: f64;
-
-            if (-eps..=eps).contains(&delta) {
-                hit_distance = -b / 2.;
-            } else {
-                let first_distance = (-b - delta.sqrt()) / 2.;
-                let second_distance = (-b + delta.sqrt()) / 2.;
-                if first_distance >= 0. && second_distance >= 0. {
-                    hit_distance = first_distance.min(second_distance);
-                }
-                else if first_distance >= 0. {
-                    // meaning the second distance was <= 0 due to lazy evaluation of the &&
-                    // previous check necessarily failed due to the second_distance < 0
-                    hit_distance = first_distance;
-                } else  {
-                    //both are negative, we know this because of the lazy evaluation 
-                    return Ok(None)
-                }
-            }
-            let point_hit = &self.origin + &(hit_distance * normalized_dir);
-            let normal = Vector::new_from_points(&(object.shape.center), &point_hit);
-            Ok(Some(HitInfo {
-                object,
-                point_hit,
-                normal,
-                hit_distance,
-            }))
+            // adding two numbers of the same sign so there is no cancellation
+            let q = -(b + b.signum() * delta.sqrt()) / 2.;
+            let other_root = c / q;
+            Ok(Some((q.min(other_root), q.max(other_root))))
         }
     }
 
+    pub fn intersect<'a>(
+        &self,
+        object: &'a Object,
+    ) -> Result<Option<HitInfo<'a>>, RayTracingError> {
+        let Some(shape_hit) = object.shape.intersect(self)? else {
+            return Ok(None);
+        };
+        Ok(Some(HitInfo {
+            object,
+            point_hit: shape_hit.point_hit,
+            normal: shape_hit.normal,
+            hit_distance: shape_hit.hit_distance,
+            uv: shape_hit.uv,
+        }))
+    }
+
     pub fn first_point_hit_by_ray<'a>(
         &self,
-        objects: &Vec<&'a Object>,
+        objects: &[&'a Object],
         ignore_object: Option<&Object>, 
         /* this is needed in the case where we don't want a ray to be trapped inside a sphere 
         due to float point error when calculating intersections in several consecutive bounces */ 
     ) -> Result<Option<HitInfo<'a>>, RayTracingError> {
-        let mut hit_info_closest_point = HitInfo {
-            object: objects[0],
-            normal: Vector::new_from_coordinates(1., 0., 0.),
-            point_hit: Point {
-                x: 0.,
-                y: 0.,
-                z: 0.,
-            },
-            hit_distance: f64::MAX,
-        };
-        let mut ray_has_hit_object = false;
-        let objects_to_iter = objects.iter().filter(|object| match ignore_object {
-            Some(object_to_ignore) => object.shape != object_to_ignore.shape,
-            None => true,
+        self.closest_hit(objects, ignore_object, |_| true)
+    }
+
+    /// Same as `first_point_hit_by_ray` but the objects hidden from this kind of ray are left out
+    pub fn first_visible_hit<'a>(
+        &self,
+        objects: &[&'a Object],
+        ignore_object: Option<&Object>,
+        ray_kind: RayKind,
+    ) -> Result<Option<HitInfo<'a>>, RayTracingError> {
+        self.closest_hit(objects, ignore_object, |object| object.visibility.is_visible_to(ray_kind))
+    }
+
+    fn closest_hit<'a>(
+        &self,
+        objects: &[&'a Object],
+        ignore_object: Option<&Object>,
+        can_be_hit: impl Fn(&Object) -> bool,
+    ) -> Result<Option<HitInfo<'a>>, RayTracingError> {
+        // * None until an object is hit, an empty scene is hit by no ray
+        let mut hit_info_closest_point: Option<HitInfo<'a>> = None;
+        let objects_to_iter = objects.iter().filter(|object| {
+            can_be_hit(object)
+                && match ignore_object {
+                    Some(object_to_ignore) => *object.shape != *object_to_ignore.shape,
+                    None => true,
+                }
         });
         // ! PERF : it might be faster to just check against the value inside the for loop
         for object in objects_to_iter {
             if let Some(hit_info) = self.intersect(object)? {
-                if hit_info.hit_distance <= hit_info_closest_point.hit_distance {
-                    hit_info_closest_point = hit_info;
+                match hit_info_closest_point {
+                    Some(closest_hit) if closest_hit.hit_distance < hit_info.hit_distance => {}
                     // no else, it means the point is further away
+                    _ => hit_info_closest_point = Some(hit_info),
                 }
-                ray_has_hit_object = true
             }
         }
-        if ray_has_hit_object {
-            Ok(Some(hit_info_closest_point))
-        } else {
-            Ok(None)
-        }
+        Ok(hit_info_closest_point)
     }
 
     pub fn reflected_ray(
@@ -138,25 +276,18 @@ impl Ray {
                 object, surface_point, source
             )))
         } else {
-            let point_to_source_vector = Vector::new_from_points(surface_point, source).normalize()?;
-            let mut surface_normal_vector = Vector::new_from_points(&object.center, surface_point).normalize()?;
-            // Let D such as R(ay) = N(ormal) + D, thus D = R - N
-            // the sym S is : S = N - D = N - (R - N) = 2N - R
-            // we also need a right angle between D and N for this to work, so we normalise N to the correct norme
-            let teta = point_to_source_vector.angle_with(&surface_normal_vector);
-            surface_normal_vector = &point_to_source_vector * teta.cos();
-            let sym_vector = &(2. * &surface_normal_vector) - &point_to_source_vector;
-            Ok(Ray {
-                origin: *surface_point,
-                direction: sym_vector,
-            })
+            // the light arrives from the source and leaves in the mirror direction, keeping its length
+            // so that the reflected ray points at the mirror image of the source
+            let source_to_point_vector = Vector::new_from_points(source, surface_point);
+            let sym_vector = source_to_point_vector.reflect(&object.normal_at(surface_point))?;
+            Ok(Ray::new_unchecked(*surface_point, sym_vector))
         }
     }
    
     pub fn cos_weighted_random_ray_unit_sphere(
         point: &Point,
         normal: &Vector,
-        unit_sphere_iter: &mut DistIter<UnitSphere, XorShiftRng, [f64; 3]>,
+        unit_sphere_iter: &mut DistIter<UnitSphere, XorShiftRng, [Scalar; 3]>,
     ) -> Result<Self, RayTracingError> {
         // based on https://www.iue.tuwien.ac.at/phd/ertl/node100.html
         let [x,y,z] = match unit_sphere_iter.next() {
@@ -165,10 +296,29 @@ impl Ray {
         };
         let direction = normal.normalize()? + Vector::new_from_coordinates(x, y, z);
 
-        Ok(Ray{origin: *point, direction: direction.normalize()?})
+        Ok(Ray::new_unchecked(*point, direction.normalize()?))
         
     }
     
+    /// Same as `cos_weighted_random_ray_unit_sphere` for `number_of_rays` rays at once, the normal is only normalized once
+    pub fn cos_weighted_batch(
+        point: &Point,
+        normal: &Vector,
+        number_of_rays: usize,
+        unit_sphere_iter: &mut DistIter<UnitSphere, XorShiftRng, [Scalar; 3]>,
+    ) -> Result<Vec<Self>, RayTracingError> {
+        let unit_normal = normal.normalize()?;
+        let mut rays = Vec::with_capacity(number_of_rays);
+        for [x, y, z] in unit_sphere_iter.take(number_of_rays) {
+            let direction = unit_normal + Vector::new_from_coordinates(x, y, z);
+            rays.push(Ray::new_unchecked(*point, direction.normalize()?));
+        }
+        if rays.len() < number_of_rays {
+            return Err(RayTracingError::IteratorDepleted());
+        }
+        Ok(rays)
+    }
+
     // pub fn cos_weighted_random_ray_unit_disc(
     //     point: &Point,
     //     normal: &Vector,
@@ -197,7 +347,7 @@ impl Ray {
     pub fn uniform_weighted_random_ray(
         point: &Point,
         normal: &Vector,
-        unit_sphere_iter: &mut DistIter<UnitSphere, XorShiftRng, [f64; 3]>,
+        unit_sphere_iter: &mut DistIter<UnitSphere, XorShiftRng, [Scalar; 3]>,
     ) -> Result<Self, RayTracingError> {
         let [x,y,z] = match unit_sphere_iter.next() {
             Some(arr) => arr,
@@ -205,11 +355,11 @@ impl Ray {
         };
         let direction = Vector::new_from_coordinates(x, y, z);
         if normal.scalar_product(&direction) < 0. {
-            let reverse_direction = -1. * &direction;
-            Ok(Ray{origin: *point, direction: reverse_direction})
+            let reverse_direction = -direction;
+            Ok(Ray::new_unchecked(*point, reverse_direction))
         }
         else {
-            Ok(Ray{origin: *point, direction})
+            Ok(Ray::new_unchecked(*point, direction))
         }
         
     }
@@ -222,12 +372,14 @@ pub struct HitInfo<'a> {
     pub object: &'a Object,
     pub point_hit: Point,
     pub normal: Vector,
-    pub hit_distance: f64,
+    pub hit_distance: Scalar,
+    pub uv: (Scalar, Scalar), // position on the surface of the object, see `Shape::uv_at`
 }
 
 #[cfg(test)]
 mod tests {
 
+    use crate::object::VisibilityFlags;
     use crate::optic::material::Material;
 
     use super::*;
@@ -264,28 +416,174 @@ mod tests {
         let ray = Ray::new_from_points(&ORIGIN, &DESTINATION)?;
         let direction = Vector::new_from_points(&ORIGIN, &DESTINATION);
 
-        assert_eq!(ray.direction, direction);
+        assert_eq!(ray.direction(), direction);
         assert_eq!(ray.origin, ORIGIN);
 
         Ok(())
     }
 
-    // #[test]
-    // fn test_point_at_a_distance() -> Result<(), RayTracingError> {
-    //     let ray = Ray::new_from_points(&ORIGIN, &DESTINATION)?;
-    //     let scalar = 7.;
-    //     let result_point = ray.point_at_a_distance(scalar);
-
-    //     let expected_point = Point {
-    //         x: ORIGIN.x + ray.direction.x() * scalar,
-    //         y: ORIGIN.y + ray.direction.y() * scalar,
-    //         z: ORIGIN.z + ray.direction.z() * scalar,
-    //     };
+    #[test]
+    fn test_new() -> Result<(), RayTracingError> {
+        let direction = Vector::new_from_coordinates(0., 3., 4.);
+        let ray = Ray::new(ORIGIN, direction)?;
 
-    //     assert_eq!(result_point, expected_point);
+        assert_eq!(ray, Ray::new_unchecked(ORIGIN, direction));
+        assert!(matches!(
+            Ray::new(ORIGIN, Vector::new_from_coordinates(0., 0., 0.)),
+            Err(RayTracingError::VectorHasNormeZero)
+        ));
+        assert!(Ray::new_from_points(&ORIGIN, &ORIGIN).is_err());
 
-    //     Ok(())
-    // }
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalized_direction() -> Result<(), RayTracingError> {
+        let ray = Ray::new_from_points(&ORIGIN, &DESTINATION)?;
+        let zero_ray = Ray::new_unchecked(ORIGIN, Vector::new_from_coordinates(0., 0., 0.));
+
+        assert_eq!(ray.normalized_direction()?, ray.direction().normalize()?);
+        assert_eq!(ray.direction(), Vector::new_from_points(&ORIGIN, &DESTINATION));
+        assert!(zero_ray.normalized_direction().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_spawn() -> Result<(), RayTracingError> {
+        let surface_point = Point::new(1., 2., 3.);
+        let normal = Vector::new_from_coordinates(0., 0., 5.);
+
+        let outgoing_direction = Vector::new_from_coordinates(1., 0., 1.);
+        let outgoing_ray = Ray::spawn(&surface_point, outgoing_direction, &normal);
+        let ingoing_direction = Vector::new_from_coordinates(1., 0., -1.);
+        let ingoing_ray = Ray::spawn(&surface_point, ingoing_direction, &normal);
+
+        assert_eq!(outgoing_ray.direction(), outgoing_direction);
+        assert!(approx_eq!(Scalar, outgoing_ray.origin.z - surface_point.z, SPAWN_OFFSET));
+        assert_eq!(ingoing_ray.direction(), ingoing_direction);
+        assert!(approx_eq!(Scalar, ingoing_ray.origin.z - surface_point.z, -SPAWN_OFFSET));
+        assert_eq!(outgoing_ray.origin.x, surface_point.x);
+        assert_eq!(outgoing_ray.origin.y, surface_point.y);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reflect_about_normal() -> Result<(), RayTracingError> {
+        let ray = Ray::new(Point::new(-1., -1., 0.), Vector::new_from_coordinates(1., 1., 0.))?;
+        let hit_point = Point::new(0., 0., 0.);
+        let normal = Vector::new_from_coordinates(0., -2., 0.);
+
+        let reflected_ray = ray.reflect_about_normal(&hit_point, &normal)?;
+
+        assert_eq!(reflected_ray.direction(), Vector::new_from_coordinates(1., -1., 0.));
+        assert!(approx_eq!(
+            Scalar,
+            reflected_ray.direction().angle_with(&normal),
+            crate::geometry::consts::FRAC_PI_4,
+            epsilon = 1e-12
+        ));
+        assert!(reflected_ray.origin.y < 0.);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_refracted_ray() -> Result<(), RayTracingError> {
+        let ray = Ray::new(Point::new(-1., -1., 0.), Vector::new_from_coordinates(1., 1., 0.))?;
+        let hit_point = Point::new(0., 0., 0.);
+        let normal = Vector::new_from_coordinates(0., -2., 0.);
+
+        let refracted_ray = ray
+            .refracted_ray(&hit_point, &normal, 1., 1.5)?
+            .expect("a ray entering glass is never totally reflected");
+        let refracted_direction = refracted_ray.normalized_direction()?;
+
+        // * sin(refraction) = sin(incidence) / 1.5, the ray bends towards the normal and goes below the surface
+        let sin_incidence = crate::geometry::consts::FRAC_1_SQRT_2;
+        assert!((refracted_direction.x - sin_incidence / 1.5).abs() < 1e-6);
+        assert!(refracted_direction.y > 0.);
+        assert!(refracted_ray.origin.y > 0.);
+        // * the same ray would leave glass at an angle above the critical one
+        assert!(ray.refracted_ray(&hit_point, &normal, 1.5, 1.)?.is_none());
+        // * a ray along the normal goes straight through, the side of the normal doesn't matter
+        let straight_ray = Ray::new(Point::new(0., -1., 0.), Vector::new_from_coordinates(0., 1., 0.))?;
+        let straight_through = straight_ray
+            .refracted_ray(&hit_point, &-normal, 1., 1.5)?
+            .expect("a ray along the normal goes through");
+        assert_eq!(straight_through.normalized_direction()?, Vector::new_from_coordinates(0., 1., 0.));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_point_at_a_distance() -> Result<(), RayTracingError> {
+        let ray = Ray::new_from_points(&ORIGIN, &DESTINATION)?;
+        let scalar = 7.;
+        let result_point = ray.point_at_a_distance(scalar)?;
+
+        let unit_direction = ray.direction().normalize()?;
+        let expected_point = Point {
+            x: ORIGIN.x + unit_direction.x * scalar,
+            y: ORIGIN.y + unit_direction.y * scalar,
+            z: ORIGIN.z + unit_direction.z * scalar,
+        };
+
+        assert_eq!(result_point, expected_point);
+        assert!(approx_eq!(Scalar, result_point.distance(&ORIGIN), scalar, epsilon = 1e-5));
+        assert!(Ray::new_unchecked(ORIGIN, Vector::new_from_coordinates(0., 0., 0.))
+            .point_at_a_distance(scalar)
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg_attr(feature = "single-precision", ignore = "mirror reflections are only exact to 1e-6 with double precision")]
+    fn test_glossy_reflect_about_normal() -> Result<(), RayTracingError> {
+        let ray = Ray::new(Point::new(-1., -1., 0.), Vector::new_from_coordinates(1., 1., 0.))?;
+        let hit_point = Point::new(0., 0., 0.);
+        let normal = Vector::new_from_coordinates(0., -1., 0.);
+        let mirror_ray = ray.reflect_about_normal(&hit_point, &normal)?;
+        let mut unit_sphere_iter: DistIter<UnitSphere, XorShiftRng, [Scalar; 3]> =
+            UnitSphere.sample_iter(XorShiftRng::seed_from_u64(6));
+
+        let mut mean_angles = vec![];
+        for roughness in [0., 0.1, 0.5] {
+            let mut angle_sum = 0.;
+            for _ in 0..1000 {
+                let glossy_ray = ray.glossy_reflect_about_normal(&hit_point, &normal, roughness, &mut unit_sphere_iter)?;
+                assert!(glossy_ray.direction().scalar_product(&normal) > 0.);
+                angle_sum += glossy_ray.direction().angle_with(&mirror_ray.direction());
+            }
+            mean_angles.push(angle_sum / 1000.);
+        }
+
+        assert!(mean_angles[0] < 1e-6);
+        assert!(mean_angles[0] < mean_angles[1] && mean_angles[1] < mean_angles[2], "{:?}", mean_angles);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cos_weighted_batch() -> Result<(), RayTracingError> {
+        let point = Point::new(1., 2., 3.);
+        let normal = Vector::new_from_coordinates(0., -4., 3.);
+        let mut unit_sphere_iter: DistIter<UnitSphere, XorShiftRng, [Scalar; 3]> =
+            UnitSphere.sample_iter(XorShiftRng::seed_from_u64(12));
+
+        let rays = Ray::cos_weighted_batch(&point, &normal, 500, &mut unit_sphere_iter)?;
+
+        assert_eq!(rays.len(), 500);
+        for ray in rays {
+            assert_eq!(ray.origin, point);
+            assert!(approx_eq!(Scalar, ray.direction().norme_vec(), 1., epsilon = TOLERANCE));
+            assert!(ray.direction().scalar_product(&normal) >= 0.);
+        }
+
+        Ok(())
+    }
 
     #[test]
     fn test_intersect_none() -> Result<(), RayTracingError> {
@@ -301,8 +599,9 @@ mod tests {
         };
         let sphere = Sphere::new_from_points(&center, &outer);
         let object = Object {
-            shape: sphere,
+            shape: Box::new(sphere),
             material: Material::default(),
+            visibility: VisibilityFlags::default(),
         };
 
         let ray = Ray::new_from_points(&ORIGIN_2, &DESTINATION_2)?;
@@ -328,8 +627,9 @@ mod tests {
         };
         let sphere = Sphere::new_from_points(&center, &outer);
         let object = Object {
-            shape: sphere,
+            shape: Box::new(sphere),
             material: Material::default(),
+            visibility: VisibilityFlags::default(),
         };
 
         let ray = Ray::new_from_points(&ORIGIN_2, &DESTINATION_2)?;
@@ -338,9 +638,9 @@ mod tests {
 
         assert!(intersect.is_some());
         if let Some(result_hit) = intersect {
-            assert_eq!(&(result_hit.object.shape), &sphere);
+            assert_eq!(result_hit.object.shape.as_sphere(), Some(&sphere));
             assert_eq!(&(result_hit.point_hit), &outer);
-            assert!(approx_eq!(f64, result_hit.hit_distance, 2. / f64::sqrt(3.)));
+            assert!(approx_eq!(Scalar, result_hit.hit_distance, 2. / Scalar::sqrt(3.)));
         }
 
         Ok(())
@@ -362,8 +662,9 @@ mod tests {
 
         let sphere = Sphere::new_from_radius(&center, 10.);
         let object = Object {
-            shape: sphere,
+            shape: Box::new(sphere),
             material: Material::default(),
+            visibility: VisibilityFlags::default(),
         };
 
         let ray = Ray::new_from_points(&ORIGIN, &DESTINATION)?;
@@ -372,15 +673,70 @@ mod tests {
 
         assert!(intersect.is_some());
         if let Some(result_hit) = intersect {
-            assert_eq!(&(result_hit.object.shape), &sphere);
+            assert_eq!(result_hit.object.shape.as_sphere(), Some(&sphere));
             assert_eq!(&(result_hit.point_hit), &expected_hit_point);
-            assert!(approx_eq!(f64, result_hit.hit_distance, 23.611665975469712));
+            assert!(approx_eq!(Scalar, result_hit.hit_distance, 23.611665975469712));
+            assert_eq!(result_hit.uv, sphere.uv_at(&result_hit.point_hit));
         }
 
         Ok(())
     }
 
     #[test]
+    #[cfg_attr(feature = "single-precision", ignore = "far spheres need double precision")]
+    fn test_intersect_far_sphere() -> Result<(), RayTracingError> {
+        let center = Point::new(0., 0., 1e6);
+        let object = Object {
+            shape: Box::new(Sphere::new_from_radius(&center, 1.)),
+            material: Material::default(),
+            visibility: VisibilityFlags::default(),
+        };
+        let ray = Ray::new_from_points(&Point::new(0., 0., -10.), &Point::new(0.8, 0., 1e6))?;
+
+        let hit_info = ray.intersect(&object)?.expect("the ray goes through the sphere");
+
+        // with CO^2 - r^2 the hit point was about 1e-5 away from the surface
+        assert!((hit_info.point_hit.distance(&center) - 1.).abs() < 1e-9);
+        assert!(hit_info.point_hit.z < center.z);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_intersect_from_inside() -> Result<(), RayTracingError> {
+        let center = Point::new(0., 0., 5.);
+        let ray = Ray::new(center, Vector::new_from_coordinates(0., 0., 1.))?;
+        let object = Object {
+            shape: Box::new(Sphere::new_from_radius(&center, 2.)),
+            material: Material::default(),
+            visibility: VisibilityFlags::default(),
+        };
+        let inward_object = Object {
+            shape: Box::new(Sphere::new_inward_from_radius(&center, 2.)),
+            material: Material::default(),
+            visibility: VisibilityFlags::default(),
+        };
+
+        let hit_info = ray.intersect(&object)?.expect("the ray starts inside the sphere");
+        let inward_hit_info = ray.intersect(&inward_object)?.expect("the ray starts inside the sphere");
+
+        assert_eq!(hit_info.point_hit, Point::new(0., 0., 7.));
+        assert!(approx_eq!(Scalar, hit_info.hit_distance, 2.));
+        assert_eq!(inward_hit_info.point_hit, Point::new(0., 0., 7.));
+        // the normal of an inward sphere points back inside, towards the ray origin
+        assert!(hit_info.normal.z > 0.);
+        assert!(inward_hit_info.normal.z < 0.);
+
+        // seen from the outside, only the far wall of an inward sphere faces the ray
+        let outside_ray = Ray::new(Point::new(0., 0., 0.), Vector::new_from_coordinates(0., 0., 1.))?;
+        let far_hit_info = outside_ray.intersect(&inward_object)?.expect("the ray goes through the sphere");
+        assert_eq!(far_hit_info.point_hit, Point::new(0., 0., 7.));
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg_attr(feature = "single-precision", ignore = "the expected values are given with double precision")]
     fn test_first_point_hit_by_ray() -> Result<(), RayTracingError> {
         let sphere_1 = Sphere::new_from_radius(&ORIGIN, 4.);
         let center_2 = Point::new(-6.055414909, 1.6263876648, 0.);
@@ -389,25 +745,29 @@ mod tests {
         let ray_destination = Point::new(-2.244677331, 2.7337430702, 0.);
         let ray = Ray::new_from_points(&source, &ray_destination)?;
         let object_1 = Object {
-            shape: sphere_1,
+            shape: Box::new(sphere_1),
             material: Material::default(),
+            visibility: VisibilityFlags::default(),
         };
         let object_2 = Object {
-            shape: sphere_2,
+            shape: Box::new(sphere_2),
             material: Material::default(),
+            visibility: VisibilityFlags::default(),
         };
         let mut objects = vec![&object_1, &object_2];
 
         /* First hit test, should hit sphere 2 */
+        // the expected distances were computed with the textbook quadratic formula, the stable formula rounds differently
+        // and lands 3 ulps away from them, the points hit are still exactly the same
 
         if let Some(hit) = ray.first_point_hit_by_ray(&objects, None)? {
-            assert_eq!(&(hit.object.shape), &sphere_2);
+            assert_eq!(hit.object.shape.as_sphere(), Some(&sphere_2));
             let expected_point =
                 Point::new(-5.256205273754008, -1.133469952831104, 0.862815095680144);
             let expected_distance = 8.64946487777813;
             assert_eq!(hit.point_hit, expected_point);
             assert!(
-                approx_eq!(f64, hit.hit_distance, expected_distance, ulps = 2),
+                approx_eq!(Scalar, hit.hit_distance, expected_distance, ulps = 4),
                 "expected distance {0}, got distance {1}",
                 expected_distance,
                 hit.hit_distance
@@ -421,21 +781,21 @@ mod tests {
         // ? this is not ideal, is there a better way
 
         let mut sphere_2_modified = sphere_2;
-        sphere_2_modified.radius = 2.;
-        let mut object_2_modified = object_2;
-        object_2_modified.shape = sphere_2_modified;
+        sphere_2_modified.set_radius(2.);
+        let mut object_2_modified = object_2.clone();
+        object_2_modified.shape = Box::new(sphere_2_modified);
 
         objects.remove(1);
         objects.push(&object_2_modified);
 
         if let Some(hit) = ray.first_point_hit_by_ray(&objects, None)? {
-            assert_eq!(&(hit.object.shape), &sphere_1);
+            assert_eq!(hit.object.shape.as_sphere(), Some(&sphere_1));
             let expected_point =
                 Point::new(-1.72455556675089, 3.40165042437406, -0.149017016715949);
             let expected_distance = 14.48587393749909;
             assert_eq!(hit.point_hit, expected_point);
             assert!(
-                approx_eq!(f64, hit.hit_distance, expected_distance, ulps = 2),
+                approx_eq!(Scalar, hit.hit_distance, expected_distance, ulps = 4),
                 "expected distance {0}, got distance {1}",
                 expected_distance,
                 hit.hit_distance
@@ -447,9 +807,9 @@ mod tests {
         /* Modify the sphere 1 too so that the ray doesn't hit anything */
 
         let mut sphere_1_modified = sphere_1;
-        sphere_1_modified.radius = 1.3;
-        let mut object_1_modified = object_1;
-        object_1_modified.shape = sphere_1_modified;
+        sphere_1_modified.set_radius(1.3);
+        let mut object_1_modified = object_1.clone();
+        object_1_modified.shape = Box::new(sphere_1_modified);
 
         objects.remove(0);
         objects.push(&object_1_modified);
@@ -464,7 +824,7 @@ mod tests {
     #[test]
     fn test_reflected_ray() -> Result<(), RayTracingError> {
         let source = Point::new(3., 3., 3.);
-        let surface_point = Point::new(0., 3. * 2_f64.sqrt() / 2., 3. * 2_f64.sqrt() / 2.);
+        let surface_point = Point::new(0., 3. * (2. as Scalar).sqrt() / 2., 3. * (2. as Scalar).sqrt() / 2.);
         let sphere_center = Point::new(0., 0., 0.);
 
         let object = Sphere::new_from_points(&sphere_center, &surface_point);
@@ -483,22 +843,22 @@ mod tests {
     fn draw_uniform_weighted_random_ray() -> Result<(), Box<dyn std::error::Error>> {
         const OUT_FILE_NAME: &str = "plot_output/uniform_weighted_random_ray.gif";
         let factor = 2;
-        let eps = 0.01 / (factor as f64);
+        let eps = 0.01 / (factor as Scalar);
         let total_frame_number = 157;
 
         let seed = 2;
         let rng = XorShiftRng::seed_from_u64(seed);
-        let mut iter_rng: DistIter<UnitSphere, XorShiftRng, [f64; 3]> = UnitSphere.sample_iter(rng);
+        let mut iter_rng: DistIter<UnitSphere, XorShiftRng, [Scalar; 3]> = UnitSphere.sample_iter(rng);
 
         let random_points = {
             let point_number = 2000 * factor;
             let mut temp_vec = vec![(0., 0., 0.); point_number];
-            for i in 0..point_number {
+            for point in temp_vec.iter_mut() {
                 let [x, y, z] = match iter_rng.next() {
                     Some(arr) => arr,
                     _ => panic!("Iterator over the sphere surface is over when is shouldn't be\n"),
                 };
-                temp_vec[i] = (x, y.abs(), z);
+                *point = (x, y.abs(), z);
             }
             temp_vec
         };
@@ -511,7 +871,7 @@ mod tests {
 
             let mut chart = ChartBuilder::on(&root)
                 .caption("2D Gaussian PDF", ("sans-serif", 20))
-                .build_cartesian_3d(-1.0..1.0, -1.0..1.0, -1.0..1.0)?;
+                .build_cartesian_3d(-1.0..1.0 as Scalar, -1.0..1.0 as Scalar, -1.0..1.0 as Scalar)?;
             chart.with_projection(|mut p| {
                 p.pitch = 1.57 - (1.57 - pitch as f64 / 50.0).abs();
                 p.yaw = 1.57 - (1.57 - pitch as f64 / 50.0).abs();
@@ -551,22 +911,22 @@ mod tests {
     fn draw_cos_weighted_random_ray() -> Result<(), Box<dyn std::error::Error>> {
         const OUT_FILE_NAME: &str = "plot_output/cos_weighted_random_ray.gif";
         let factor = 2;
-        let eps = 0.01 / (factor as f64);
+        let eps = 0.01 / (factor as Scalar);
         let total_frame_number = 157;
 
         let seed = 2;
         let rng = XorShiftRng::seed_from_u64(seed);
-        let mut iter_rng: DistIter<UnitSphere, XorShiftRng, [f64; 3]> = UnitSphere.sample_iter(rng);
+        let mut iter_rng: DistIter<UnitSphere, XorShiftRng, [Scalar; 3]> = UnitSphere.sample_iter(rng);
 
         let random_points = {
             let normal_vector = Vector::new_from_coordinates(0., 11., 0.);
             let origin_point = Point::new(-1., 0., 1.2);
             let point_number = 2000 * factor;
             let mut temp_vec = vec![(0., 0., 0.); point_number];
-            for i in 0..point_number {
+            for point in temp_vec.iter_mut() {
                 let ray =
                     Ray::cos_weighted_random_ray_unit_sphere(&origin_point, &normal_vector, &mut iter_rng)?;
-                temp_vec[i] = (ray.direction.x, ray.direction.y, ray.direction.z);
+                *point = (ray.direction().x, ray.direction().y, ray.direction().z);
             }
             temp_vec
         };
@@ -579,7 +939,7 @@ mod tests {
 
             let mut chart = ChartBuilder::on(&root)
                 .caption("2D Gaussian PDF", ("sans-serif", 20))
-                .build_cartesian_3d(-1.0..1.0, -1.0..1.0, -1.0..1.0)?;
+                .build_cartesian_3d(-1.0..1.0 as Scalar, -1.0..1.0 as Scalar, -1.0..1.0 as Scalar)?;
             chart.with_projection(|mut p| {
                 p.pitch = 1.57 - (1.57 - pitch as f64 / 50.0).abs();
                 p.yaw = 1.57 - (1.57 - pitch as f64 / 50.0).abs();
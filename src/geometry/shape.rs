@@ -1,14 +1,144 @@
+use super::aabb::Aabb;
 use super::point::Point;
 use super::ray::Ray;
+use super::triangle::Triangle;
 use super::vector::Vector;
+use super::{consts, Scalar, TOLERANCE};
 use crate::error::RayTracingError;
 use crate::object::Object;
 use float_cmp::{self, approx_eq};
+use std::any::Any;
 
+/// Where a ray meets the surface of a shape, the normal points to the rendered side
 #[derive(Debug, Clone, Copy)]
+pub struct ShapeHit {
+    pub point_hit: Point,
+    pub normal: Vector,
+    pub hit_distance: Scalar,
+    pub uv: (Scalar, Scalar), // see `Shape::uv_at`
+}
+
+/// Surface that can be rendered, an `Object` holds any of them so that a scene can mix several kinds of shapes
+pub trait Shape: AnyShape + std::fmt::Debug + Send + Sync {
+    /// Closest point in front of the ray origin where it meets the surface
+    fn intersect(&self, ray: &Ray) -> Result<Option<ShapeHit>, RayTracingError>;
+
+    /// Normal on the rendered side at a point of the surface, not normalized
+    fn normal_at(&self, point: &Point) -> Vector;
+
+    /// Box enclosing the shape, None for the shapes going on forever
+    fn bounding_box(&self) -> Option<Aabb>;
+
+    /// Point the shape is placed by, such as the center of a sphere, moving it moves the whole shape
+    fn position(&self) -> Point;
+
+    fn translate(&mut self, offset: &Vector);
+
+    /// Area of the rendered surface, infinite for the shapes going on forever
+    fn surface_area(&self) -> Scalar;
+
+    /// Position on the surface given to the materials, both between 0 and 1
+    fn uv_at(&self, _point: &Point) -> (Scalar, Scalar) {
+        (0., 0.)
+    }
+
+    /// A ray leaving the surface can meet it again, like on the inside of an inward sphere
+    fn is_concave(&self) -> bool {
+        false
+    }
+
+    /// Distances along the normalized direction at which the ray enters and leaves the solid bounded by the surface,
+    /// smallest first and negative behind the origin of the ray, used by the constructive solid geometry. None when
+    /// the ray misses the solid, or for the surfaces bounding no solid in one piece along every ray, such as a plane
+    fn intersect_both(&self, _ray: &Ray) -> Result<Option<(Scalar, Scalar)>, RayTracingError> {
+        Ok(None)
+    }
+}
+
+/// Lets the boxed shapes be cloned, compared and downcast, implemented for every shape that is `Clone` and `PartialEq`
+pub trait AnyShape {
+    fn box_clone(&self) -> Box<dyn Shape>;
+    fn as_any(&self) -> &dyn Any;
+    fn eq_shape(&self, other: &dyn Shape) -> bool;
+
+    /// The shape if it is a sphere, for what only works on spheres
+    fn as_sphere(&self) -> Option<&Sphere> {
+        self.as_any().downcast_ref()
+    }
+}
+
+impl<T: Shape + Clone + PartialEq + 'static> AnyShape for T {
+    fn box_clone(&self) -> Box<dyn Shape> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn eq_shape(&self, other: &dyn Shape) -> bool {
+        other.as_any().downcast_ref::<T>() == Some(self)
+    }
+}
+
+impl Clone for Box<dyn Shape> {
+    fn clone(&self) -> Self {
+        self.box_clone()
+    }
+}
+
+// * boxes are compared through their content, `*first == *second`, comparing the boxes would move the right one
+impl PartialEq for dyn Shape {
+    fn eq(&self, other: &Self) -> bool {
+        self.eq_shape(other)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "SphereDescription", into = "SphereDescription")
+)]
 pub struct Sphere {
     pub center: Point,
-    pub radius: f64,
+    radius: Scalar,
+    radius_squared: Scalar, // * cached as it is needed for every intersection
+    pub inward: bool, // * the inside of the sphere is the rendered surface, like the walls of a room
+}
+
+/// What is written in scene files for a sphere, the cached values are not stored, the radius must be positive
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SphereDescription {
+    center: Point,
+    radius: Scalar,
+    #[serde(default)]
+    inward: bool,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<SphereDescription> for Sphere {
+    type Error = RayTracingError;
+    fn try_from(description: SphereDescription) -> Result<Self, Self::Error> {
+        if !(description.radius > 0. && description.radius.is_finite()) {
+            return Err(RayTracingError::CoefficientOOB(description.radius, 0., Scalar::INFINITY));
+        }
+        let mut sphere = Sphere::new_from_radius(&description.center, description.radius);
+        sphere.inward = description.inward;
+        Ok(sphere)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<Sphere> for SphereDescription {
+    fn from(sphere: Sphere) -> Self {
+        SphereDescription {
+            center: sphere.center,
+            radius: sphere.radius,
+            inward: sphere.inward,
+        }
+    }
 }
 
 impl Sphere {
@@ -18,30 +148,56 @@ impl Sphere {
         Sphere {
             center: *center,
             radius,
+            radius_squared: radius * radius,
+            inward: false,
+        }
+    }
+
+    pub fn new_from_radius(center: &Point, radius: Scalar) -> Self {
+        Sphere {
+            center: *center,
+            radius,
+            radius_squared: radius * radius,
+            inward: false,
         }
     }
 
-    pub fn new_from_radius(center: &Point, radius: f64) -> Self {
+    /// Sphere seen from the inside, its normals point towards the center
+    pub fn new_inward_from_radius(center: &Point, radius: Scalar) -> Self {
         Sphere {
             center: *center,
             radius,
+            radius_squared: radius * radius,
+            inward: true,
         }
     }
 
+    pub fn radius(&self) -> Scalar {
+        self.radius
+    }
+
+    pub fn radius_squared(&self) -> Scalar {
+        self.radius_squared
+    }
+
+    pub fn set_radius(&mut self, radius: Scalar) {
+        self.radius = radius;
+        self.radius_squared = radius * radius;
+    }
+
     pub fn point_is_on_sphere(&self, point: &Point) -> bool {
         let Point { x, y, z } = point - &self.center;
         let point_distance_to_center = Vector::norme(x, y, z);
-        #[cfg(test)]
-        println!(
+        log::trace!(
             "point distance to center : {0} | sphere radius : {1}",
             point_distance_to_center, self.radius
         );
         approx_eq!(
-            f64,
+            Scalar,
             self.radius,
             point_distance_to_center,
             ulps = 2,
-            epsilon = 1e-12_f64
+            epsilon = TOLERANCE
         )
     }
 
@@ -54,14 +210,13 @@ impl Sphere {
         source is above the horizon if the scalar product between the normal to the sphere at the point on the sphere
         and the vector going from the sphere point to the source, is positive
         */
-        #[cfg(test)]
-        println!(
+        log::trace!(
             "sphere_point : {0:?} | source : {1:?}",
             sphere_point, source
         );
 
         if self.point_is_on_sphere(sphere_point) {
-            let normal = Vector::new_from_points(&self.center, sphere_point);
+            let normal = self.normal_at(sphere_point);
             let point_source_vec = Vector::new_from_points(sphere_point, source);
             Ok(normal.scalar_product(&point_source_vec) >= 0.)
         } else {
@@ -70,14 +225,15 @@ impl Sphere {
     }
 
     pub fn source_is_visible_from_sphere_point(
-        objects: &Vec<&Object>,
+        objects: &[&Object],
         sphere_index: usize,
         sphere_point: &Point,
         source: &Point,
     ) -> Result<bool, RayTracingError> {
-        let current_object = objects.get(sphere_index);
-        let current_object = match current_object {
-            Some(object) => *object,
+        // * the object must also be a sphere
+        let current_sphere = objects.get(sphere_index).and_then(|object| object.shape.as_sphere());
+        let current_sphere = match current_sphere {
+            Some(sphere) => sphere,
             _ => {
                 return Err(RayTracingError::NoSphereAtIndex(
                     sphere_index,
@@ -86,10 +242,7 @@ impl Sphere {
             }
         };
 
-        if current_object
-            .shape
-            .source_is_above_horizon(sphere_point, source)?
-        {
+        if current_sphere.source_is_above_horizon(sphere_point, source)? {
             let ray = Ray::new_from_points(source, sphere_point)?;
             if let Some(hit_info) = ray.first_point_hit_by_ray(objects, None)? {
                 Ok(*sphere_point == hit_info.point_hit)
@@ -103,17 +256,328 @@ impl Sphere {
             Ok(false)
         }
     }
+
+    /// Icosphere approximating the sphere, each subdivision splits every triangle of the icosahedron in 4,
+    /// giving `20 * 4^subdivisions` triangles whose vertices are on the sphere and whose normals point outwards
+    pub fn tessellate(&self, subdivisions: usize) -> Vec<Triangle> {
+        let golden_ratio = (1. + (5. as Scalar).sqrt()) / 2.;
+        let on_sphere = |x: Scalar, y: Scalar, z: Scalar| {
+            let norme = Vector::norme(x, y, z);
+            &self.center + &((self.radius / norme) * &Vector::new_from_coordinates(x, y, z))
+        };
+        let icosahedron_vertices = [
+            on_sphere(-1., golden_ratio, 0.),
+            on_sphere(1., golden_ratio, 0.),
+            on_sphere(-1., -golden_ratio, 0.),
+            on_sphere(1., -golden_ratio, 0.),
+            on_sphere(0., -1., golden_ratio),
+            on_sphere(0., 1., golden_ratio),
+            on_sphere(0., -1., -golden_ratio),
+            on_sphere(0., 1., -golden_ratio),
+            on_sphere(golden_ratio, 0., -1.),
+            on_sphere(golden_ratio, 0., 1.),
+            on_sphere(-golden_ratio, 0., -1.),
+            on_sphere(-golden_ratio, 0., 1.),
+        ];
+        let icosahedron_faces = [
+            [0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+            [1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+            [3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+            [4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1],
+        ];
+        let mut triangles: Vec<Triangle> = icosahedron_faces
+            .iter()
+            .map(|[index_1, index_2, index_3]| {
+                let triangle = Triangle::new(
+                    icosahedron_vertices[*index_1],
+                    icosahedron_vertices[*index_2],
+                    icosahedron_vertices[*index_3],
+                );
+                // * the winding of the list is not trusted, the normal must point away from the center
+                if triangle.normal().scalar_product(&Vector::new_from_points(&self.center, &triangle.vertices[0])) < 0. {
+                    Triangle::new(triangle.vertices[0], triangle.vertices[2], triangle.vertices[1])
+                } else {
+                    triangle
+                }
+            })
+            .collect();
+        let midpoint_on_sphere = |point_1: &Point, point_2: &Point| {
+            let Vector { x, y, z } =
+                Vector::new_from_points(&self.center, point_1) + Vector::new_from_points(&self.center, point_2);
+            on_sphere(x, y, z)
+        };
+        for _ in 0..subdivisions {
+            triangles = triangles
+                .iter()
+                .flat_map(|triangle| {
+                    let [vertex_1, vertex_2, vertex_3] = triangle.vertices;
+                    let middle_1_2 = midpoint_on_sphere(&vertex_1, &vertex_2);
+                    let middle_2_3 = midpoint_on_sphere(&vertex_2, &vertex_3);
+                    let middle_3_1 = midpoint_on_sphere(&vertex_3, &vertex_1);
+                    // * the 4 triangles keep the winding of their parent
+                    [
+                        Triangle::new(vertex_1, middle_1_2, middle_3_1),
+                        Triangle::new(middle_1_2, vertex_2, middle_2_3),
+                        Triangle::new(middle_3_1, middle_2_3, vertex_3),
+                        Triangle::new(middle_1_2, middle_2_3, middle_3_1),
+                    ]
+                })
+                .collect();
+        }
+        triangles
+    }
+}
+
+impl Shape for Sphere {
+    fn intersect(&self, ray: &Ray) -> Result<Option<ShapeHit>, RayTracingError> {
+        let Some((first_distance, second_distance)) = ray.intersect_both(self)? else {
+            return Ok(None);
+        };
+        // first_distance <= second_distance, negative distances are behind the ray origin
+        let hit_distance = if self.inward {
+            // only the far side of an inward sphere faces the ray
+            second_distance
+        } else if first_distance >= 0. {
+            first_distance
+        } else {
+            // the ray starts inside the sphere and goes out through the second point
+            second_distance
+        };
+        if hit_distance < 0. {
+            return Ok(None);
+        }
+        let point_hit = ray.point_at_a_distance(hit_distance)?;
+        Ok(Some(ShapeHit {
+            point_hit,
+            normal: self.normal_at(&point_hit),
+            hit_distance,
+            uv: self.uv_at(&point_hit),
+        }))
+    }
+
+    /// Normal on the rendered side of the sphere at a point of its surface, of norme the radius
+    fn normal_at(&self, sphere_point: &Point) -> Vector {
+        if self.inward {
+            Vector::new_from_points(sphere_point, &self.center)
+        } else {
+            Vector::new_from_points(&self.center, sphere_point)
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::from_sphere(self))
+    }
+
+    fn position(&self) -> Point {
+        self.center
+    }
+
+    fn translate(&mut self, offset: &Vector) {
+        self.center = &self.center + offset;
+    }
+
+    fn surface_area(&self) -> Scalar {
+        4. * consts::PI * self.radius_squared
+    }
+
+    /// Longitude around the y axis and latitude from the top of the sphere (y goes down)
+    fn uv_at(&self, sphere_point: &Point) -> (Scalar, Scalar) {
+        let Ok(unit_from_center) = Vector::new_from_points(&self.center, sphere_point).normalize() else {
+            return (0., 0.);
+        };
+        let u = 0.5 + unit_from_center.z.atan2(unit_from_center.x) / (2. * consts::PI);
+        let v = (-unit_from_center.y).clamp(-1., 1.).acos() / consts::PI;
+        (u, v)
+    }
+
+    fn is_concave(&self) -> bool {
+        self.inward
+    }
+
+    /// The ball inside of the sphere, even for an inward sphere
+    fn intersect_both(&self, ray: &Ray) -> Result<Option<(Scalar, Scalar)>, RayTracingError> {
+        ray.intersect_both(self)
+    }
 }
 
 impl PartialEq for Sphere {
     fn eq(&self, other: &Self) -> bool {
-        self.center == other.center && approx_eq!(f64, self.radius, other.radius, ulps = 2)
+        self.center == other.center
+            && approx_eq!(Scalar, self.radius, other.radius, ulps = 2)
+            && self.inward == other.inward
+    }
+}
+
+/// Infinite flat surface, both of its sides are rendered
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "PlaneDescription", into = "PlaneDescription")
+)]
+pub struct Plane {
+    pub point: Point,
+    pub normal: Vector, // * unit when made by `new`
+}
+
+/// What is written in scene files for a plane, the normal is made unit when it is read
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct PlaneDescription {
+    point: Point,
+    normal: Vector,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<PlaneDescription> for Plane {
+    type Error = RayTracingError;
+    fn try_from(description: PlaneDescription) -> Result<Self, Self::Error> {
+        Plane::new(description.point, description.normal)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<Plane> for PlaneDescription {
+    fn from(plane: Plane) -> Self {
+        PlaneDescription {
+            point: plane.point,
+            normal: plane.normal,
+        }
+    }
+}
+
+impl Plane {
+    pub fn new(point: Point, normal: Vector) -> Result<Self, RayTracingError> {
+        Ok(Plane {
+            point,
+            normal: normal.normalize()?,
+        })
+    }
+}
+
+impl Shape for Plane {
+    /// The normal of the hit faces the ray, whichever side of the plane it comes from
+    fn intersect(&self, ray: &Ray) -> Result<Option<ShapeHit>, RayTracingError> {
+        let direction = ray.normalized_direction()?;
+        let denominator = direction.scalar_product(&self.normal);
+        // * the ray is parallel to the plane
+        if denominator.abs() < TOLERANCE {
+            return Ok(None);
+        }
+        let hit_distance =
+            Vector::new_from_points(&ray.origin, &self.point).scalar_product(&self.normal) / denominator;
+        if hit_distance < 0. {
+            return Ok(None);
+        }
+        let normal = if denominator > 0. {
+            -self.normal
+        } else {
+            self.normal
+        };
+        let point_hit = &ray.origin + &(hit_distance * &direction);
+        Ok(Some(ShapeHit {
+            point_hit,
+            normal,
+            hit_distance,
+            uv: self.uv_at(&point_hit),
+        }))
+    }
+
+    fn normal_at(&self, _point: &Point) -> Vector {
+        self.normal
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        None
+    }
+
+    fn position(&self) -> Point {
+        self.point
+    }
+
+    fn translate(&mut self, offset: &Vector) {
+        self.point = &self.point + offset;
+    }
+
+    fn surface_area(&self) -> Scalar {
+        Scalar::INFINITY
+    }
+}
+
+/// Round flat surface, both of its sides are rendered
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Disc {
+    pub center: Point,
+    pub normal: Vector, // * unit when made by `new`
+    pub radius: Scalar,
+}
+
+impl Disc {
+    pub fn new(center: Point, normal: Vector, radius: Scalar) -> Result<Self, RayTracingError> {
+        if !(radius > 0. && radius.is_finite()) {
+            return Err(RayTracingError::CoefficientOOB(radius, 0., Scalar::INFINITY));
+        }
+        Ok(Disc {
+            center,
+            normal: normal.normalize()?,
+            radius,
+        })
+    }
+}
+
+impl Shape for Disc {
+    /// The normal of the hit faces the ray, whichever side of the disc it comes from
+    fn intersect(&self, ray: &Ray) -> Result<Option<ShapeHit>, RayTracingError> {
+        let plane = Plane {
+            point: self.center,
+            normal: self.normal,
+        };
+        Ok(plane
+            .intersect(ray)?
+            .filter(|shape_hit| shape_hit.point_hit.distance(&self.center) <= self.radius))
+    }
+
+    fn normal_at(&self, _point: &Point) -> Vector {
+        self.normal
+    }
+
+    /// The disc goes along an axis by its radius times the sine of the angle between the axis and the normal
+    fn bounding_box(&self) -> Option<Aabb> {
+        let normal = self.normal.normalize().ok()?;
+        let half_extent = |normal_coordinate: Scalar| {
+            self.radius * (1. - normal_coordinate * normal_coordinate).max(0.).sqrt()
+        };
+        let half_diagonal = Vector::new_from_coordinates(
+            half_extent(normal.x),
+            half_extent(normal.y),
+            half_extent(normal.z),
+        );
+        Some(Aabb::new(
+            &(&self.center + &-half_diagonal),
+            &(&self.center + &half_diagonal),
+        ))
+    }
+
+    fn position(&self) -> Point {
+        self.center
+    }
+
+    fn translate(&mut self, offset: &Vector) {
+        self.center = &self.center + offset;
+    }
+
+    fn surface_area(&self) -> Scalar {
+        consts::PI * self.radius * self.radius
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::optic::material::Material;
+    use crate::{
+        geometry::triangle::Triangle,
+        object::VisibilityFlags,
+        optic::{camera::Camera, material::Material},
+    };
 
     use super::*;
 
@@ -122,6 +586,20 @@ mod tests {
         y: 0.,
         z: 0.,
     };
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_deserialize_checks_radius() -> Result<(), serde_json::Error> {
+        let sphere: Sphere = serde_json::from_str(r#"{ "center": { "x": 0.0, "y": 0.0, "z": 0.0 }, "radius": 2.0 }"#)?;
+
+        assert_eq!(sphere, Sphere::new_from_radius(&CENTER, 2.));
+        for radius in ["0.0", "-2.0"] {
+            let json = format!(r#"{{ "center": {{ "x": 0.0, "y": 0.0, "z": 0.0 }}, "radius": {} }}"#, radius);
+            assert!(serde_json::from_str::<Sphere>(&json).is_err(), "a sphere of radius {} is read", radius);
+        }
+
+        Ok(())
+    }
     const OUTER: Point = Point {
         x: 15.,
         y: 12.,
@@ -133,12 +611,72 @@ mod tests {
         Sphere::new_from_points(&CENTER, &OUTER)
     }
 
+    #[test]
+    fn test_tessellate_silhouette() -> Result<(), RayTracingError> {
+        let sphere = Sphere::new_from_radius(&Point::new(0., 0., 10.), 5.);
+        let triangles = sphere.tessellate(3);
+        let camera = Camera::default();
+        let (width, height) = (64, 36);
+
+        // * which pixel centers see the sphere, exactly and through its mesh
+        let mut analytic_mask = vec![vec![false; width]; height];
+        let mut mesh_mask = vec![vec![false; width]; height];
+        for pixel_height_index in 0..height {
+            for pixel_width_index in 0..width {
+                let ray = camera.primary_ray(&camera.pixel_center_point(pixel_width_index, pixel_height_index, width, height)?)?;
+                analytic_mask[pixel_height_index][pixel_width_index] = ray.intersect_both(&sphere)?.is_some();
+                for triangle in &triangles {
+                    if triangle.intersect(&ray)?.is_some() {
+                        mesh_mask[pixel_height_index][pixel_width_index] = true;
+                        break;
+                    }
+                }
+            }
+        }
+
+        assert_eq!(triangles.len(), 20 * 4_usize.pow(3));
+        assert!(triangles.iter().all(|triangle| {
+            triangle.vertices.iter().all(|vertex| sphere.point_is_on_sphere(vertex))
+                && triangle.normal().scalar_product(&Vector::new_from_points(&sphere.center, &triangle.vertices[0])) > 0.
+        }));
+        assert!(analytic_mask.iter().flatten().filter(|hit| **hit).count() > 100);
+        // * the silhouettes may only differ by a pixel, next to the edge of the exact silhouette
+        for pixel_height_index in 1..height - 1 {
+            for pixel_width_index in 1..width - 1 {
+                if analytic_mask[pixel_height_index][pixel_width_index] != mesh_mask[pixel_height_index][pixel_width_index] {
+                    let neighbors: Vec<bool> = (pixel_height_index - 1..=pixel_height_index + 1)
+                        .flat_map(|neighbor_height_index| {
+                            analytic_mask[neighbor_height_index][pixel_width_index - 1..=pixel_width_index + 1].to_vec()
+                        })
+                        .collect();
+                    assert!(neighbors.contains(&true) && neighbors.contains(&false));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn test_new() {
         let sphere = make_test_sphere();
 
         assert_eq!(&(sphere.center), &CENTER);
-        assert_eq!(sphere.radius, Vector::norme(15., 12., -2.3));
+        assert_eq!(sphere.radius(), Vector::norme(15., 12., -2.3));
+    }
+
+    #[test]
+    fn test_radius_squared() {
+        let sphere_from_points = make_test_sphere();
+        let mut sphere_from_radius = Sphere::new_from_radius(&CENTER, 3.);
+        let inward_sphere = Sphere::new_inward_from_radius(&CENTER, 0.5);
+
+        assert!(approx_eq!(Scalar, sphere_from_points.radius_squared(), 15. * 15. + 12. * 12. + 2.3 * 2.3, ulps = 4));
+        assert_eq!(sphere_from_radius.radius_squared(), 9.);
+        assert_eq!(inward_sphere.radius_squared(), 0.25);
+        sphere_from_radius.set_radius(4.);
+        assert_eq!(sphere_from_radius.radius(), 4.);
+        assert_eq!(sphere_from_radius.radius_squared(), 16.);
     }
 
     #[test]
@@ -169,12 +707,14 @@ mod tests {
         let sphere_2 = Sphere::new_from_radius(&center_2, 3.);
         let source = Point::new(-10.4900536536, -7.8544458162, 2.3623341028);
         let object_1 = Object {
-            shape: sphere_1,
+            shape: Box::new(sphere_1),
             material: Material::default(),
+            visibility: VisibilityFlags::default(),
         };
         let object_2 = Object {
-            shape: sphere_2,
+            shape: Box::new(sphere_2),
             material: Material::default(),
+            visibility: VisibilityFlags::default(),
         };
         let mut objects = vec![&object_1, &object_2];
 
@@ -201,9 +741,9 @@ mod tests {
             &source
         )?);
         let mut sphere_2_modified = sphere_2;
-        sphere_2_modified.radius = 2.;
-        let mut object_2_modified = object_2;
-        object_2_modified.shape = sphere_2_modified;
+        sphere_2_modified.set_radius(2.);
+        let mut object_2_modified = object_2.clone();
+        object_2_modified.shape = Box::new(sphere_2_modified);
 
         objects.remove(1);
         objects.push(&object_2_modified);
@@ -219,4 +759,114 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_objects_of_any_shape() -> Result<(), RayTracingError> {
+        let sphere = Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(0., 0., 10.), 1.)),
+            material: Material::default(),
+            visibility: VisibilityFlags::default(),
+        };
+        let triangle = Object {
+            shape: Box::new(Triangle::new(
+                Point::new(-1., -1., 5.),
+                Point::new(-1., 1., 5.),
+                Point::new(1., -1., 5.),
+            )),
+            material: Material::default(),
+            visibility: VisibilityFlags::default(),
+        };
+        let ray = Ray::new(
+            Point::new(-0.5, -0.5, 0.),
+            Vector::new_from_coordinates(0., 0., 1.),
+        )?;
+
+        let hit = ray
+            .first_point_hit_by_ray(&[&sphere, &triangle], None)?
+            .expect("the triangle is in front of the sphere");
+
+        assert_eq!(*hit.object, triangle);
+        assert!(hit.object.shape.as_sphere().is_none());
+        assert_eq!(hit.point_hit, Point::new(-0.5, -0.5, 5.));
+        let behind_triangle = ray
+            .first_point_hit_by_ray(&[&sphere, &triangle], Some(&triangle))?
+            .expect("the ray goes on to the sphere");
+        assert_eq!(*behind_triangle.object, sphere);
+        let copy = sphere.clone();
+        assert_eq!(copy, sphere);
+        assert!(*copy.shape != *triangle.shape);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_plane() -> Result<(), RayTracingError> {
+        assert!(Plane::new(CENTER, Vector::new_from_coordinates(0., 0., 0.)).is_err());
+        // * a floor at y = 2, below the eye as y goes down
+        let floor = Plane::new(Point::new(0., 2., 0.), Vector::new_from_coordinates(0., -3., 0.))?;
+        assert!(floor.normal == Vector::new_from_coordinates(0., -1., 0.));
+        let object = Object {
+            shape: Box::new(floor),
+            material: Material::default(),
+            visibility: VisibilityFlags::default(),
+        };
+
+        let from_above = Ray::new(Point::new(1., -2., 0.), Vector::new_from_coordinates(0., 1., 1.))?;
+        let hit = from_above.intersect(&object)?.expect("the ray goes down to the floor");
+        assert_eq!(hit.point_hit, Point::new(1., 2., 4.));
+        assert!(approx_eq!(Scalar, hit.hit_distance, 4. * Scalar::sqrt(2.), epsilon = TOLERANCE));
+        assert!(hit.normal == Vector::new_from_coordinates(0., -1., 0.));
+        // * the normal is flipped towards a ray coming from below
+        let from_below = Ray::new(Point::new(0., 5., 0.), Vector::new_from_coordinates(0., -1., 0.))?;
+        let hit = from_below.intersect(&object)?.expect("the ray goes up to the floor");
+        assert!(hit.normal == Vector::new_from_coordinates(0., 1., 0.));
+        // * going away from the plane and along it
+        let upwards = Ray::new(Point::new(0., 0., 0.), Vector::new_from_coordinates(0., -1., 0.))?;
+        assert!(upwards.intersect(&object)?.is_none());
+        let along_floor = Ray::new(Point::new(0., 0., 0.), Vector::new_from_coordinates(1., 0., 0.))?;
+        assert!(along_floor.intersect(&object)?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_surface_area() -> Result<(), RayTracingError> {
+        let unit_sphere = Sphere::new_from_radius(&CENTER, 1.);
+        assert!(approx_eq!(Scalar, unit_sphere.surface_area(), 4. * consts::PI, ulps = 2));
+        assert!(approx_eq!(
+            Scalar,
+            Sphere::new_inward_from_radius(&CENTER, 3.).surface_area(),
+            36. * consts::PI,
+            ulps = 2
+        ));
+        let unit_disc = Disc::new(CENTER, Vector::new_from_coordinates(1., 1., 0.), 1.)?;
+        assert!(approx_eq!(Scalar, unit_disc.surface_area(), consts::PI, ulps = 2));
+        // * half of a 3 by 4 rectangle
+        let triangle = Triangle::new(CENTER, Point::new(3., 0., 0.), Point::new(0., 4., 0.));
+        assert!(approx_eq!(Scalar, triangle.surface_area(), 6., epsilon = TOLERANCE));
+        let floor = Plane::new(CENTER, Vector::new_from_coordinates(0., -1., 0.))?;
+        assert_eq!(floor.surface_area(), Scalar::INFINITY);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_disc() -> Result<(), RayTracingError> {
+        assert!(Disc::new(CENTER, Vector::new_from_coordinates(0., 0., 1.), 0.).is_err());
+        let disc = Disc::new(Point::new(0., 0., 5.), Vector::new_from_coordinates(0., 0., -1.), 2.)?;
+        let towards_disc = Vector::new_from_coordinates(0., 0., 1.);
+
+        let hit = disc
+            .intersect(&Ray::new(Point::new(1., 1., 0.), towards_disc)?)?
+            .expect("the ray goes through the disc");
+        assert_eq!(hit.point_hit, Point::new(1., 1., 5.));
+        assert!(disc
+            .intersect(&Ray::new(Point::new(1.5, 1.5, 0.), towards_disc)?)?
+            .is_none());
+        let Aabb { min, max } = disc.bounding_box().expect("a disc is bounded");
+        assert_eq!(min, Point::new(-2., -2., 5.));
+        assert_eq!(max, Point::new(2., 2., 5.));
+
+        Ok(())
+    }
 }
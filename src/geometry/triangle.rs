@@ -0,0 +1,168 @@
+use super::aabb::Aabb;
+use super::point::Point;
+use super::ray::Ray;
+use super::shape::{Shape, ShapeHit};
+use super::vector::Vector;
+use super::{Scalar, TOLERANCE};
+use crate::error::RayTracingError;
+
+/// Flat triangle, the vertices are given counterclockwise when seen from the side the normal points to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Triangle {
+    pub vertices: [Point; 3],
+}
+
+impl Triangle {
+    pub fn new(vertex_1: Point, vertex_2: Point, vertex_3: Point) -> Self {
+        Triangle {
+            vertices: [vertex_1, vertex_2, vertex_3],
+        }
+    }
+
+    /// Normal given by the order of the vertices, not normalized
+    pub fn normal(&self) -> Vector {
+        let [vertex_1, vertex_2, vertex_3] = self.vertices;
+        Vector::new_from_points(&vertex_1, &vertex_2)
+            .cross_product(&Vector::new_from_points(&vertex_1, &vertex_3))
+    }
+}
+
+impl Shape for Triangle {
+    /// Point where the ray goes through the triangle from any of its sides, with the Möller–Trumbore algorithm
+    fn intersect(&self, ray: &Ray) -> Result<Option<ShapeHit>, RayTracingError> {
+        let [vertex_1, vertex_2, vertex_3] = self.vertices;
+        let direction = ray.normalized_direction()?;
+        let edge_1 = Vector::new_from_points(&vertex_1, &vertex_2);
+        let edge_2 = Vector::new_from_points(&vertex_1, &vertex_3);
+        let p = direction.cross_product(&edge_2);
+        let determinant = edge_1.scalar_product(&p);
+        // * the ray is parallel to the plane of the triangle, or the triangle is degenerate
+        if determinant.abs() < TOLERANCE {
+            return Ok(None);
+        }
+        let inverse_determinant = 1. / determinant;
+        let from_vertex_1 = Vector::new_from_points(&vertex_1, &ray.origin);
+        // * barycentric coordinates of the point in the plane of the triangle
+        let u = inverse_determinant * from_vertex_1.scalar_product(&p);
+        if !(0. ..=1.).contains(&u) {
+            return Ok(None);
+        }
+        let q = from_vertex_1.cross_product(&edge_1);
+        let v = inverse_determinant * direction.scalar_product(&q);
+        if v < 0. || u + v > 1. {
+            return Ok(None);
+        }
+        let hit_distance = inverse_determinant * edge_2.scalar_product(&q);
+        if hit_distance < 0. {
+            return Ok(None);
+        }
+        Ok(Some(ShapeHit {
+            point_hit: &ray.origin + &(hit_distance * &direction),
+            normal: self.normal(),
+            hit_distance,
+            uv: (u, v),
+        }))
+    }
+
+    fn normal_at(&self, _point: &Point) -> Vector {
+        self.normal()
+    }
+
+    /// Barycentric coordinates of the point, the weights of the second and third vertices
+    fn uv_at(&self, point: &Point) -> (Scalar, Scalar) {
+        let [vertex_1, vertex_2, vertex_3] = self.vertices;
+        let edge_1 = Vector::new_from_points(&vertex_1, &vertex_2);
+        let edge_2 = Vector::new_from_points(&vertex_1, &vertex_3);
+        let from_vertex_1 = Vector::new_from_points(&vertex_1, point);
+        let (d_11, d_12, d_22) = (
+            edge_1.scalar_product(&edge_1),
+            edge_1.scalar_product(&edge_2),
+            edge_2.scalar_product(&edge_2),
+        );
+        let (d_p1, d_p2) = (from_vertex_1.scalar_product(&edge_1), from_vertex_1.scalar_product(&edge_2));
+        let denominator = d_11 * d_22 - d_12 * d_12;
+        // * degenerate triangle
+        if denominator.abs() < TOLERANCE {
+            return (0., 0.);
+        }
+        (
+            (d_22 * d_p1 - d_12 * d_p2) / denominator,
+            (d_11 * d_p2 - d_12 * d_p1) / denominator,
+        )
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let [vertex_1, vertex_2, vertex_3] = self.vertices;
+        Some(Aabb::new(&vertex_1, &vertex_2).union(&Aabb::new(&vertex_3, &vertex_3)))
+    }
+
+    /// Centroid of the vertices
+    fn position(&self) -> Point {
+        let [vertex_1, vertex_2, vertex_3] = self.vertices;
+        Point::new(
+            (vertex_1.x + vertex_2.x + vertex_3.x) / 3.,
+            (vertex_1.y + vertex_2.y + vertex_3.y) / 3.,
+            (vertex_1.z + vertex_2.z + vertex_3.z) / 3.,
+        )
+    }
+
+    fn translate(&mut self, offset: &Vector) {
+        for vertex in &mut self.vertices {
+            *vertex = &*vertex + offset;
+        }
+    }
+
+    /// Half the norme of the cross product of two edges
+    fn surface_area(&self) -> Scalar {
+        self.normal().norme_vec() / 2.
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use float_cmp::approx_eq;
+
+    #[test]
+    fn test_intersect() -> Result<(), RayTracingError> {
+        let triangle = Triangle::new(
+            Point::new(0., 0., 5.),
+            Point::new(0., 2., 5.),
+            Point::new(2., 0., 5.),
+        );
+        let towards_triangle = Vector::new_from_coordinates(0., 0., 2.);
+
+        let hit = triangle
+            .intersect(&Ray::new(Point::new(0.5, 0.5, 0.), towards_triangle)?)?
+            .expect("the ray goes through the triangle");
+
+        assert!(approx_eq!(
+            Scalar,
+            hit.hit_distance,
+            5.,
+            epsilon = TOLERANCE
+        ));
+        assert_eq!(hit.point_hit, Point::new(0.5, 0.5, 5.));
+        assert!(hit.normal.normalize()? == Vector::new_from_coordinates(0., 0., -1.));
+        // * the second vertex is along y and the third along x
+        assert!(approx_eq!(Scalar, hit.uv.0, 0.25, epsilon = TOLERANCE));
+        assert!(approx_eq!(Scalar, hit.uv.1, 0.25, epsilon = TOLERANCE));
+        let uv_at_hit = triangle.uv_at(&hit.point_hit);
+        assert!(approx_eq!(Scalar, uv_at_hit.0, hit.uv.0, epsilon = TOLERANCE));
+        assert!(approx_eq!(Scalar, uv_at_hit.1, hit.uv.1, epsilon = TOLERANCE));
+        // * beside the hypotenuse, behind the origin and parallel to the triangle
+        assert!(triangle
+            .intersect(&Ray::new(Point::new(1.5, 1.5, 0.), towards_triangle)?)?
+            .is_none());
+        assert!(triangle
+            .intersect(&Ray::new(Point::new(0.5, 0.5, 6.), towards_triangle)?)?
+            .is_none());
+        let along_triangle = Ray::new(
+            Point::new(-1., 0.5, 5.),
+            Vector::new_from_coordinates(1., 0., 0.),
+        )?;
+        assert!(triangle.intersect(&along_triangle)?.is_none());
+
+        Ok(())
+    }
+}
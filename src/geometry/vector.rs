@@ -1,39 +1,68 @@
 use float_cmp::approx_eq;
 
 use crate::error::RayTracingError;
-use crate::geometry::point::Point;
+use crate::geometry::{point::Point, Scalar};
 use std::ops::Add;
 use std::ops::Div;
 use std::ops::Mul;
+use std::ops::Neg;
 use std::ops::Sub;
 
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Vector {
-    pub x: f64,
-    pub y: f64,
-    pub z: f64,
+    pub x: Scalar,
+    pub y: Scalar,
+    pub z: Scalar,
 }
 
 impl Vector {
 
+    #[must_use]
     pub fn new_from_points(origin: &Point, destination: &Point) -> Self {
         let Point { x, y, z } = destination - origin;
         Vector{x, y, z}
     }
 
-    pub fn new_from_coordinates(x: f64, y: f64, z: f64) -> Self {
+    pub const fn new_from_coordinates(x: Scalar, y: Scalar, z: Scalar) -> Self {
         Vector{x,y,z}
     }
 
-    pub fn scalar_product(&self, other: &Self) -> f64 {
+    #[must_use]
+    pub fn scalar_product(&self, other: &Self) -> Scalar {
         self.x * other.x + self.y * other.y + self.z * other.z
     }
 
-    pub fn norme(x: f64, y: f64, z: f64) -> f64 {
+    /// `self × other`, orthogonal to both vectors, its norm is the area of the parallelogram they make
+    #[must_use]
+    pub fn cross_product(&self, other: &Self) -> Vector {
+        Vector {
+            x: self.y * other.z - self.z * other.y,
+            y: self.z * other.x - self.x * other.z,
+            z: self.x * other.y - self.y * other.x,
+        }
+    }
+
+    /// `a · (b × c)`, the signed volume of the parallelepiped made by the three vectors,
+    /// positive when they are in the same order as x, y and z
+    #[must_use]
+    pub fn triple_product(a: &Vector, b: &Vector, c: &Vector) -> Scalar {
+        a.scalar_product(&b.cross_product(c))
+    }
+
+    /// The three vectors are in a same plane, up to a parallelepiped volume of `epsilon`
+    #[must_use]
+    pub fn are_coplanar(a: &Vector, b: &Vector, c: &Vector, epsilon: Scalar) -> bool {
+        Self::triple_product(a, b, c).abs() <= epsilon
+    }
+
+    #[must_use]
+    pub fn norme(x: Scalar, y: Scalar, z: Scalar) -> Scalar {
         (x * x + y * y + z * z).sqrt()
     }
 
-    pub fn norme_vec(&self) -> f64 {
+    #[must_use]
+    pub fn norme_vec(&self) -> Scalar {
         let &Vector { x, y, z } = self;
         Self::norme(x,y,z)
     }
@@ -52,7 +81,29 @@ impl Vector {
         }
     }
 
-    pub fn angle_with(&self, other: &Self) -> f64 {
+    /// Mirror image of the vector on a surface of the given normal, the normal doesn't need to be of norme 1
+    pub fn reflect(&self, normal: &Vector) -> Result<Vector, RayTracingError> {
+        let unit_normal = normal.normalize()?;
+        Ok(self - &(2. * self.scalar_product(&unit_normal) * &unit_normal))
+    }
+
+    /// Two unit vectors of the plane orthogonal to the vector, orthogonal to each other,
+    /// their cross product gives the vector back once normalized
+    pub fn tangent_plane_vectors(&self) -> Result<[Vector; 2], RayTracingError> {
+        let axis = self.normalize()?;
+        // * any vector that isn't almost along the axis gives the first tangent
+        let helper = if axis.x.abs() > 0.9 {
+            Vector::new_from_coordinates(0., 1., 0.)
+        } else {
+            Vector::new_from_coordinates(1., 0., 0.)
+        };
+        let first_tangent = helper.cross_product(&axis).normalize()?;
+        let second_tangent = axis.cross_product(&first_tangent);
+        Ok([first_tangent, second_tangent])
+    }
+
+    #[must_use]
+    pub fn angle_with(&self, other: &Self) -> Scalar {
         let scalar_product = self.scalar_product(other);
         (scalar_product / (self.norme_vec() * other.norme_vec())).acos()
     }
@@ -60,7 +111,7 @@ impl Vector {
 
 impl PartialEq for Vector {
     fn eq(&self, other: &Self) -> bool {
-        approx_eq!(f64, self.x, other.x, ulps = 2) && approx_eq!(f64, self.y, other.y, ulps = 2) && approx_eq!(f64, self.z, other.z, ulps = 2)
+        approx_eq!(Scalar, self.x, other.x, ulps = 2) && approx_eq!(Scalar, self.y, other.y, ulps = 2) && approx_eq!(Scalar, self.z, other.z, ulps = 2)
     }
 }
 
@@ -86,9 +137,9 @@ impl Add for Vector {
     }
 }
 
-impl Mul<f64> for &Vector {
+impl Mul<Scalar> for &Vector {
     type Output = Vector;
-    fn mul(self, rhs: f64) -> Self::Output {
+    fn mul(self, rhs: Scalar) -> Self::Output {
         let x = self.x * rhs;
         let y = self.y * rhs;
         let z = self.z * rhs;
@@ -97,7 +148,7 @@ impl Mul<f64> for &Vector {
     }
 }
 
-impl Mul<&Vector> for f64 {
+impl Mul<&Vector> for Scalar {
     type Output = Vector;
     fn mul(self, rhs: &Vector) -> Self::Output {
         let x = rhs.x * self;
@@ -108,18 +159,34 @@ impl Mul<&Vector> for f64 {
     }
 }
 
+impl Neg for &Vector {
+    type Output = Vector;
+    fn neg(self) -> Self::Output {
+        Vector {
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+        }
+    }
+}
+
+impl Neg for Vector {
+    type Output = Vector;
+    fn neg(self) -> Self::Output {
+        -&self
+    }
+}
+
 impl Sub for &Vector {
     type Output = Vector;
     fn sub(self, rhs: Self) -> Self::Output {
-        let neg_vec = -1. * rhs;
-        // ? is there a way to oneline this with a closure maybe
-        self + &neg_vec
+        self + &-rhs
     }
 }
 
-impl Div<f64> for &Vector {
+impl Div<Scalar> for &Vector {
     type Output = Vector;
-    fn div(self, rhs: f64) -> Self::Output {
+    fn div(self, rhs: Scalar) -> Self::Output {
         (1. / rhs) * self
     }
 }
@@ -132,6 +199,17 @@ mod tests {
     use super::*;
     use float_cmp::approx_eq;
 
+    #[test]
+    fn test_const_vector() -> Result<(), RayTracingError> {
+        const UP: Vector = Vector::new_from_coordinates(0., -1., 0.);
+        const ORIGIN: Point = Point::new(0., 0., 0.);
+
+        assert_eq!(UP.norme_vec(), 1.);
+        assert!(Vector::new_from_points(&ORIGIN, &Point::new(0., -2., 0.)).normalize()? == UP);
+
+        Ok(())
+    }
+
     #[test]
     fn test_vector_new_from_points() {
         let origin = Point {
@@ -159,7 +237,7 @@ mod tests {
         let expected_value = -1.5 * 0.458 - 78. + 45. * 12.;
 
         assert!(approx_eq!(
-            f64,
+            Scalar,
             first_vector.scalar_product(&second_vector),
             expected_value,
             ulps = 2
@@ -167,14 +245,88 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_cross_product() {
+        let x = Vector::new_from_coordinates(1., 0., 0.);
+        let y = Vector::new_from_coordinates(0., 1., 0.);
+        let z = Vector::new_from_coordinates(0., 0., 1.);
+        let first_vector = Vector::new_from_coordinates(-1.5, 1., 45.);
+
+        assert_eq!(x.cross_product(&y), z);
+        assert_eq!(y.cross_product(&z), x);
+        assert_eq!(z.cross_product(&x), y);
+        assert_eq!(y.cross_product(&x), -z);
+        // * parallel vectors, pointing the same way or not, span no area
+        assert!(first_vector.cross_product(&(&first_vector * 2.5)).norme_vec() < 1e-4);
+        assert!(first_vector.cross_product(&(&first_vector * -0.3)).norme_vec() < 1e-4);
+        assert!(first_vector.cross_product(&x).scalar_product(&first_vector).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_neg() {
+        let vector = Vector::new_from_coordinates(-1.5, 1., 45.);
+
+        assert_eq!(-(-vector), vector);
+        assert_eq!(-&vector, Vector::new_from_coordinates(1.5, -1., -45.));
+        assert_eq!(vector + -vector, Vector::new_from_coordinates(0., 0., 0.));
+    }
+
+    #[test]
+    fn test_tangent_plane_vectors() -> Result<(), RayTracingError> {
+        for vector in [
+            Vector::new_from_coordinates(0., 0., -3.),
+            Vector::new_from_coordinates(5., 0.1, 0.),
+            Vector::new_from_coordinates(-1.5, 1., 45.),
+        ] {
+            let [first_tangent, second_tangent] = vector.tangent_plane_vectors()?;
+
+            assert!((first_tangent.norme_vec() - 1.).abs() < 1e-6);
+            assert!((second_tangent.norme_vec() - 1.).abs() < 1e-6);
+            assert!(first_tangent.scalar_product(&vector).abs() < 1e-5);
+            assert!(second_tangent.scalar_product(&vector).abs() < 1e-5);
+            assert!(first_tangent.scalar_product(&second_tangent).abs() < 1e-6);
+            assert!(first_tangent.cross_product(&second_tangent).angle_with(&vector) < 1e-3);
+        }
+        assert!(Vector::new_from_coordinates(0., 0., 0.).tangent_plane_vectors().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_triple_product() {
+        let a = Vector::new_from_coordinates(1., 2., 0.);
+        let b = Vector::new_from_coordinates(-3., 0.5, 0.);
+        let in_between = (&a * 0.3) + (&b * -1.7);
+        let box_x = Vector::new_from_coordinates(2., 0., 0.);
+        let box_y = Vector::new_from_coordinates(0., 3., 0.);
+        let slanted_z = Vector::new_from_coordinates(1., 1., 4.);
+
+        assert!(Vector::triple_product(&a, &b, &in_between).abs() < 1e-5);
+        assert!(Vector::are_coplanar(&a, &b, &in_between, 1e-5));
+        // * the volume of the parallelepiped is its base times its height, whatever the slant
+        assert!(approx_eq!(
+            Scalar,
+            Vector::triple_product(&box_x, &box_y, &slanted_z),
+            24.,
+            ulps = 2
+        ));
+        assert!(approx_eq!(
+            Scalar,
+            Vector::triple_product(&box_y, &box_x, &slanted_z),
+            -24.,
+            ulps = 2
+        ));
+        assert!(!Vector::are_coplanar(&box_x, &box_y, &slanted_z, 1e-5));
+    }
+
     #[test]
     fn test_norme() {
         let x = 541.4856;
         let y = 0.11457;
-        let z = f64::sqrt(42.);
+        let z = Scalar::sqrt(42.);
         let expected_value = (x * x + y * y + z * z).sqrt();
         assert!(approx_eq!(
-            f64,
+            Scalar,
             Vector::norme(x, y, z),
             expected_value,
             ulps = 2
@@ -184,10 +336,10 @@ mod tests {
     #[test]
     fn test_norme_vec() -> Result<(), RayTracingError> {
         let vector = Vector::new_from_coordinates(0.458, -78., 12.);
-        let expected_value = f64::sqrt(0.458 * 0.458 + 78. * 78. + 12. * 12.);
+        let expected_value = Scalar::sqrt(0.458 * 0.458 + 78. * 78. + 12. * 12.);
 
         assert!(approx_eq!(
-            f64,
+            Scalar,
             vector.norme_vec(),
             expected_value,
             ulps = 2
@@ -198,13 +350,13 @@ mod tests {
     #[test]
     fn test_angle_with() -> Result<(), RayTracingError> {
         let first_vector = Vector::new_from_coordinates(0., 12.5, 0.);
-        let a = f64::sqrt(2.) / 2.;
+        let a = Scalar::sqrt(2.) / 2.;
         let second_vector = Vector::new_from_coordinates(a, a, 0.);
 
         assert!(approx_eq!(
-            f64,
+            Scalar,
             first_vector.angle_with(&second_vector),
-            std::f64::consts::FRAC_PI_4,
+            crate::geometry::consts::FRAC_PI_4,
             ulps = 2
         ));
 
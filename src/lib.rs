@@ -1,9 +1,15 @@
+// * with the single-precision feature `Scalar` is f32, so the casts between it and f32 are only needed in double precision,
+// and the literals written in double precision are rounded
+#![cfg_attr(feature = "single-precision", allow(clippy::unnecessary_cast, clippy::excessive_precision))]
+
+pub mod accelerator;
 mod error;
 pub mod geometry;
 pub mod object;
 pub mod optic;
+pub mod scene;
 
-use std::path::PathBuf;
+use std::{path::PathBuf, time::Instant};
 
 // use float_cmp;
 
@@ -18,22 +24,76 @@ use std::path::PathBuf;
 pub fn ray_trace_image(
     number_of_points_per_pixel: usize,
     number_of_bounces: u64,
-    objects: &Vec<&object::Object>,
+    resolution: (usize, usize),
+    objects: &[&object::Object],
     export_path: &PathBuf,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let mut grid = optic::image::Grid::default();
-    grid.make_image(number_of_points_per_pixel, number_of_bounces, objects)?;
-    grid.export_image(export_path)
+    let (width, height) = resolution;
+    let mut grid = optic::image::Grid::new(width, height)?;
+    let start = Instant::now();
+    grid.make_image(
+        number_of_points_per_pixel,
+        optic::image::BounceLimit::Fixed(number_of_bounces),
+        objects,
+    )?;
+    log::debug!("image rendered in {:?}", start.elapsed());
+    export_rendered_grid(grid, export_path)
+}
+
+// the number of points per pixel is doubled at most this many times by `ray_trace_image_until_converged`
+const MAX_CONVERGENCE_PASSES: usize = 8;
+
+/// Same as `ray_trace_image` but the number of points per pixel is doubled until a pass changes the image
+/// by a root mean square difference below `rms_threshold`, returns the passes made after the first render
+pub fn ray_trace_image_until_converged(
+    number_of_points_per_pixel: usize,
+    number_of_bounces: u64,
+    resolution: (usize, usize),
+    objects: &[&object::Object],
+    rms_threshold: f32,
+    export_path: &PathBuf,
+) -> Result<Vec<optic::image::ConvergencePass>, Box<dyn std::error::Error>> {
+    let (width, height) = resolution;
+    let mut grid = optic::image::Grid::new(width, height)?;
+    let start = Instant::now();
+    let passes = grid.make_image_until_converged(
+        number_of_points_per_pixel,
+        optic::image::BounceLimit::Fixed(number_of_bounces),
+        objects,
+        rms_threshold,
+        MAX_CONVERGENCE_PASSES,
+    )?;
+    log::debug!("image rendered in {:?}", start.elapsed());
+    export_rendered_grid(grid, export_path)?;
+    Ok(passes)
+}
+
+fn export_rendered_grid(
+    mut grid: optic::image::Grid,
+    export_path: &PathBuf,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let scrubbed_pixels = grid.scrub()?;
+    if scrubbed_pixels > 0 {
+        log::warn!("{} pixels had an invalid color and were scrubbed", scrubbed_pixels);
+    }
+    let start = Instant::now();
+    grid.export_image(export_path)?;
+    log::debug!("image exported in {:?}", start.elapsed());
+    Ok(())
 }
 
 
 pub fn ray_trace_image_no_output(
     number_of_points_per_pixel: usize,
     number_of_bounces: u64,
-    objects: &Vec<&object::Object>,
+    objects: &[&object::Object],
 ) -> Result<(), Box<dyn std::error::Error>> {
     let mut grid = optic::image::Grid::default();
-    grid.make_image(number_of_points_per_pixel, number_of_bounces, objects)?;
+    grid.make_image(
+        number_of_points_per_pixel,
+        optic::image::BounceLimit::Fixed(number_of_bounces),
+        objects,
+    )?;
     
     Ok(())
 }
@@ -1,15 +1,20 @@
+// * the coordinates of the scene are written in double precision, they are rounded with the single-precision feature
+#![cfg_attr(feature = "single-precision", allow(clippy::excessive_precision))]
+
 use ray_tracing_3d::{
     geometry::{point::Point, shape::Sphere},
-    object::Object,
+    object::{Object, VisibilityFlags},
     optic::{
         color::{self, DiffusionCoefficient},
         material::Material,
     },
-    ray_trace_image,
+    ray_trace_image, ray_trace_image_until_converged,
+    scene::Scene,
 };
 
 use clap::{Args, Parser, Subcommand};
-use std::path::PathBuf;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::{path::PathBuf, process::ExitCode};
 
 #[derive(Parser)]
 #[command(version, about = "A 3D ray tracing tool", long_about = None)]
@@ -17,8 +22,51 @@ use std::path::PathBuf;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// only print errors
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// also print details such as the time taken by each stage
+    #[arg(short, long, global = true)]
+    verbose: bool,
+}
+
+impl Cli {
+    fn log_level(&self) -> LevelFilter {
+        if self.quiet {
+            LevelFilter::Error
+        } else if self.verbose {
+            LevelFilter::Debug
+        } else {
+            LevelFilter::Info
+        }
+    }
+}
+
+/// Prints the messages on stderr, warnings and errors are prefixed with their level
+struct StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        match record.level() {
+            Level::Error | Level::Warn => eprintln!("{}: {}", record.level(), record.args()),
+            _ => eprintln!("{}", record.args()),
+        }
+    }
+
+    fn flush(&self) {}
 }
 
+static LOGGER: StderrLogger = StderrLogger;
+
 #[derive(Subcommand)]
 enum Commands {
     /// Run the ray tracer
@@ -29,23 +77,76 @@ enum Commands {
 
 #[derive(Args, Debug)]
 struct RunArgs {
-    /// number of points per pixel
-    #[arg(short, long, default_value = "See default_param.txt")]
+    /// number of points per pixel [default: 5]
+    #[arg(short, long, value_parser = parse_points_per_pixel)]
     point_per_pixel: Option<usize>,
 
-    /// max number of bounces for a ray of light
-    #[arg(short, long, default_value = "See default_param.txt")]
+    /// max number of bounces for a ray of light [default: 3]
+    #[arg(short, long)]
     bounces: Option<usize>,
 
+    /// size of the image in pixels, as WIDTHxHEIGHT [default: 1920x1080]
+    #[arg(short, long, value_parser = parse_resolution)]
+    resolution: Option<(usize, usize)>,
+
+    /// width of the image in pixels, replaces the one given by --resolution
+    #[arg(long, value_parser = parse_dimension)]
+    width: Option<usize>,
+
+    /// height of the image in pixels, replaces the one given by --resolution
+    #[arg(long, value_parser = parse_dimension)]
+    height: Option<usize>,
+
+    /// keep doubling the points per pixel until a pass changes the image by a root mean square difference below RMS
+    #[arg(long, value_name = "RMS")]
+    converge: Option<f32>,
+
     /// where to export the completed image
     #[arg(short, long)]
     output: PathBuf,
 }
 
+impl RunArgs {
+    fn resolution(&self) -> (usize, usize) {
+        let (width, height) = self.resolution.unwrap_or((1920, 1080));
+        (self.width.unwrap_or(width), self.height.unwrap_or(height))
+    }
+}
+
+/// Reads a resolution written as WIDTHxHEIGHT, such as 1280x720
+fn parse_resolution(resolution: &str) -> Result<(usize, usize), String> {
+    let malformed = || format!("expected WIDTHxHEIGHT such as 1280x720, got \"{}\"", resolution);
+    let (width, height) = resolution.split_once(['x', 'X']).ok_or_else(malformed)?;
+    let width: usize = width.trim().parse().map_err(|_| malformed())?;
+    let height: usize = height.trim().parse().map_err(|_| malformed())?;
+    if width == 0 || height == 0 {
+        return Err(format!("the image can't be empty, got {}x{}", width, height));
+    }
+    Ok((width, height))
+}
+
+/// Reads a number of pixels along one side of the image
+fn parse_dimension(dimension: &str) -> Result<usize, String> {
+    match dimension.trim().parse() {
+        Ok(0) => Err("the image can't be empty".to_string()),
+        Ok(dimension) => Ok(dimension),
+        Err(_) => Err(format!("expected a number of pixels, got \"{}\"", dimension)),
+    }
+}
+
+/// Reads a number of primary rays traced through each pixel
+fn parse_points_per_pixel(points_per_pixel: &str) -> Result<usize, String> {
+    match points_per_pixel.trim().parse() {
+        Ok(0) => Err("a pixel needs at least one point".to_string()),
+        Ok(points_per_pixel) => Ok(points_per_pixel),
+        Err(_) => Err(format!("expected a number of points, got \"{}\"", points_per_pixel)),
+    }
+}
+
 #[derive(Args, Debug)]
 struct SetArgs {
     /// number of points per pixel
-    #[arg(short, long)]
+    #[arg(short, long, value_parser = parse_points_per_pixel)]
     point_per_pixel: Option<usize>,
 
     /// max number of bounces for a ray of light
@@ -53,21 +154,37 @@ struct SetArgs {
     bounces: Option<usize>,
 }
 
+#[allow(dead_code)] // kept for the `set` command, which doesn't read the default arguments yet
 fn read_default_args() {} // ? use clap::load_yaml
 
-fn main() {
+fn main() -> ExitCode {
     let cli = Cli::parse();
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(cli.log_level());
+    }
 
+    match run(&cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            log::error!("{}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
     match &cli.command {
         Commands::Run(run_args) => {
             let number_of_points_per_pixel= run_args.point_per_pixel.unwrap_or(5);
             let number_of_bounces = run_args.bounces.unwrap_or(3) as u64;
             let export_path= &run_args.output;
+            let resolution = run_args.resolution();
 
-            println!("Running with the following parameters:");
-            println!("number of points per pixel: {}", number_of_points_per_pixel);
-            println!("max number of bounces for a light ray: {}", number_of_bounces);
-            println!("file output at: {:?}", export_path);
+            log::info!("Running with the following parameters:");
+            log::info!("number of points per pixel: {}", number_of_points_per_pixel);
+            log::info!("max number of bounces for a light ray: {}", number_of_bounces);
+            log::info!("resolution: {}x{}", resolution.0, resolution.1);
+            log::info!("file output at: {:?}", export_path);
 
             // * need to define the objects in the scene
             let sphere_support_center = Point {
@@ -112,92 +229,206 @@ fn main() {
             let sphere_support_material = Material::new(
                 color::BLACK,
                 0.,
-                DiffusionCoefficient::new(186. / 255., 181. / 255., 120. / 255.).unwrap(),
+                DiffusionCoefficient::new(186. / 255., 181. / 255., 120. / 255.)?,
                 0.,
-            )
-            .unwrap();
+            )?;
             let object_support = Object {
-                shape: sphere_support,
+                shape: Box::new(sphere_support),
                 material: sphere_support_material,
+                visibility: VisibilityFlags::default(),
             };
 
             let small_sphere_1_material = Material::new(
                 color::BLACK,
                 0.,
-                color::GREEN.to_diffusion_coefficient().unwrap(),
+                color::GREEN.to_diffusion_coefficient()?,
                 0.,
-            )
-            .unwrap();
+            )?;
             let small_sphere_2_material = Material::new(
                 color::BLACK,
                 0.,
-                color::BLUE.to_diffusion_coefficient().unwrap(),
+                color::BLUE.to_diffusion_coefficient()?,
                 0.,
-            )
-            .unwrap();
+            )?;
             let small_sphere_3_material = Material::new(
                 color::BLACK,
                 0.,
-                color::RED.to_diffusion_coefficient().unwrap(),
+                color::RED.to_diffusion_coefficient()?,
                 0.,
-            )
-            .unwrap();
+            )?;
             let small_sphere_4_material = Material::new(
                 color::BLACK,
                 0.,
-                color::WHITE.to_diffusion_coefficient().unwrap(),
+                color::WHITE.to_diffusion_coefficient()?,
                 0.,
-            )
-            .unwrap();
+            )?;
             let object_small_sphere_1 = Object {
-                shape: small_sphere_1,
+                shape: Box::new(small_sphere_1),
                 material: small_sphere_1_material,
+                visibility: VisibilityFlags::default(),
             };
             let object_small_sphere_2 = Object {
-                shape: small_sphere_2,
+                shape: Box::new(small_sphere_2),
                 material: small_sphere_2_material,
+                visibility: VisibilityFlags::default(),
             };
             let object_small_sphere_3 = Object {
-                shape: small_sphere_3,
+                shape: Box::new(small_sphere_3),
                 material: small_sphere_3_material,
+                visibility: VisibilityFlags::default(),
             };
             let object_small_sphere_4 = Object {
-                shape: small_sphere_4,
+                shape: Box::new(small_sphere_4),
                 material: small_sphere_4_material,
+                visibility: VisibilityFlags::default(),
             };
 
             let light_source_material = Material::new(
                 color::WHITE,
                 1.,
-                color::BLACK.to_diffusion_coefficient().unwrap(),
+                color::BLACK.to_diffusion_coefficient()?,
                 0.,
-            )
-            .unwrap();
+            )?;
             let object_light_source = Object {
-                shape: light_source,
+                shape: Box::new(light_source),
                 material: light_source_material,
+                visibility: VisibilityFlags::default(),
             };
 
-            let objects = vec![
-                &object_support,
-                &object_small_sphere_1,
-                &object_small_sphere_2,
-                &object_small_sphere_3,
-                &object_small_sphere_4,
-                &object_light_source,
-            ];
+            let scene = Scene::new(vec![
+                object_support,
+                object_small_sphere_1,
+                object_small_sphere_2,
+                object_small_sphere_3,
+                object_small_sphere_4,
+                object_light_source,
+            ]);
+            let objects: Vec<&Object> = scene.objects.iter().collect();
+            let stats = scene.stats();
+            log::info!(
+                "scene: {} objects ({} emissive, {} triangles), about {} bytes",
+                stats.object_count,
+                stats.emissive_count,
+                stats.triangle_count,
+                stats.memory_bytes
+            );
+            log::debug!("scene bounding box: {:?}", stats.bounding_box);
             // println!("objects: {:?}", objects);
             // let objects = vec![];
             // println!("{}", number_of_points_per_pixel);
-            ray_trace_image(
-                number_of_points_per_pixel,
-                number_of_bounces,
-                &objects,
-                export_path,
-            )
-            .unwrap();
+            let could_not_render = |error| format!("could not render {:?}: {}", export_path, error);
+            match run_args.converge {
+                Some(rms_threshold) => {
+                    let passes = ray_trace_image_until_converged(
+                        number_of_points_per_pixel,
+                        number_of_bounces,
+                        resolution,
+                        &objects,
+                        rms_threshold,
+                        export_path,
+                    )
+                    .map_err(could_not_render)?;
+                    for pass in &passes {
+                        log::info!(
+                            "{} points per pixel: root mean square difference of {}",
+                            pass.number_of_points_per_pixel,
+                            pass.rms_difference
+                        );
+                    }
+                    if passes.last().map_or(true, |pass| pass.rms_difference >= rms_threshold) {
+                        log::warn!("the image didn't converge below {}", rms_threshold);
+                    }
+                }
+                None => ray_trace_image(
+                    number_of_points_per_pixel,
+                    number_of_bounces,
+                    resolution,
+                    &objects,
+                    export_path,
+                )
+                .map_err(could_not_render)?,
+            }
+        }
+        Commands::Set(_) => log::error!("Unimplemented"),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_level_flags() {
+        let parse = |args: &[&str]| Cli::try_parse_from(args.iter().copied());
+
+        let default_cli = parse(&["ray_tracing_3d", "run", "-o", "out.png"]).unwrap();
+        let quiet_cli = parse(&["ray_tracing_3d", "run", "-o", "out.png", "-q"]).unwrap();
+        let verbose_cli = parse(&["ray_tracing_3d", "--verbose", "run", "-o", "out.png"]).unwrap();
+
+        assert_eq!(default_cli.log_level(), LevelFilter::Info);
+        assert_eq!(quiet_cli.log_level(), LevelFilter::Error);
+        assert_eq!(verbose_cli.log_level(), LevelFilter::Debug);
+        assert!(parse(&["ray_tracing_3d", "run", "-o", "out.png", "-q", "-v"]).is_err());
+    }
+
+    #[test]
+    fn test_resolution_flag() {
+        let parse = |args: &[&str]| Cli::try_parse_from(args.iter().copied());
+        let resolution = |cli: Cli| match cli.command {
+            Commands::Run(run_args) => run_args.resolution,
+            Commands::Set(_) => None,
+        };
+
+        let default_cli = parse(&["ray_tracing_3d", "run", "-o", "out.png"]).unwrap();
+        let hd_cli = parse(&["ray_tracing_3d", "run", "-o", "out.png", "--resolution", "1280x720"]).unwrap();
+
+        assert_eq!(resolution(default_cli), None);
+        assert_eq!(resolution(hd_cli), Some((1280, 720)));
+        assert_eq!(parse_resolution("64X48"), Ok((64, 48)));
+        for malformed in ["1280x", "abc", "x720", "1280*720", "1280x720x2", "0x720", "-5x3"] {
+            assert!(parse_resolution(malformed).is_err(), "{} should be rejected", malformed);
+            assert!(parse(&["ray_tracing_3d", "run", "-o", "out.png", "-r", malformed]).is_err());
+        }
+    }
+
+    #[test]
+    fn test_width_and_height_flags() {
+        let parse = |args: &[&str]| Cli::try_parse_from(args.iter().copied());
+        let resolution = |args: &[&str]| match parse(args).unwrap().command {
+            Commands::Run(run_args) => Some(run_args.resolution()),
+            Commands::Set(_) => None,
+        };
+
+        assert_eq!(resolution(&["ray_tracing_3d", "run", "-o", "out.png"]), Some((1920, 1080)));
+        assert_eq!(
+            resolution(&["ray_tracing_3d", "run", "-o", "out.png", "--width", "321", "--height", "241"]),
+            Some((321, 241))
+        );
+        // * a single side replaces the same side of the resolution
+        assert_eq!(
+            resolution(&["ray_tracing_3d", "run", "-o", "out.png", "-r", "1280x720", "--height", "240"]),
+            Some((1280, 240))
+        );
+        for malformed in ["0", "-3", "abc", "12.5"] {
+            assert!(parse(&["ray_tracing_3d", "run", "-o", "out.png", "--width", malformed]).is_err());
+        }
+    }
+
+    #[test]
+    fn test_point_per_pixel_flag() {
+        let parse = |args: &[&str]| Cli::try_parse_from(args.iter().copied());
+        let point_per_pixel = |args: &[&str]| match parse(args).unwrap().command {
+            Commands::Run(run_args) => run_args.point_per_pixel,
+            Commands::Set(set_args) => set_args.point_per_pixel,
+        };
+
+        assert_eq!(point_per_pixel(&["ray_tracing_3d", "run", "-o", "out.png", "-p", "16"]), Some(16));
+        assert_eq!(point_per_pixel(&["ray_tracing_3d", "set", "-p", "4"]), Some(4));
+        for malformed in ["0", "-3", "abc", "2.5"] {
+            assert!(parse_points_per_pixel(malformed).is_err(), "{} should be rejected", malformed);
+            assert!(parse(&["ray_tracing_3d", "run", "-o", "out.png", "-p", malformed]).is_err());
+            assert!(parse(&["ray_tracing_3d", "set", "-p", malformed]).is_err());
         }
-        Commands::Set(_) => println!("Unimplemented"),
     }
-    
 }
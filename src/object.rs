@@ -1,7 +1,98 @@
-use crate::{geometry::shape::Sphere, optic::material::Material};
+use crate::{
+    geometry::{ray::RayKind, shape::Shape},
+    optic::material::Material,
+    scene::MaterialId,
+};
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Object {
-    pub shape: Sphere, // * should be changed to use a trait like Shape for example
+    #[cfg_attr(feature = "serde", serde(with = "scene_shape"))]
+    pub shape: Box<dyn Shape>,
     pub material: Material,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub visibility: VisibilityFlags,
+}
+
+impl PartialEq for Object {
+    fn eq(&self, other: &Self) -> bool {
+        *self.shape == *other.shape && self.material == other.material && self.visibility == other.visibility
+    }
+}
+
+/// Kinds of rays that can hit the object, the others go through it as if it wasn't there
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
+pub struct VisibilityFlags {
+    pub camera: bool,     // rays from the eye
+    pub shadow: bool,     // rays from a surface to a sampled emitter
+    pub reflection: bool, // rays bouncing off a surface
+}
+
+impl VisibilityFlags {
+    pub fn is_visible_to(&self, ray_kind: RayKind) -> bool {
+        match ray_kind {
+            RayKind::Camera => self.camera,
+            RayKind::Shadow => self.shadow,
+            RayKind::Reflection => self.reflection,
+        }
+    }
+}
+
+impl Default for VisibilityFlags {
+    fn default() -> Self {
+        VisibilityFlags {
+            camera: true,
+            shadow: true,
+            reflection: true,
+        }
+    }
+}
+
+/// Same as `Object` but the material is shared through the material table of a `Scene`
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SharedObject {
+    #[cfg_attr(feature = "serde", serde(with = "scene_shape"))]
+    pub shape: Box<dyn Shape>,
+    pub material_id: MaterialId,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub visibility: VisibilityFlags,
+}
+
+/// Scene files only hold spheres and planes for now, the other shapes can't be written
+#[cfg(feature = "serde")]
+mod scene_shape {
+    use crate::geometry::shape::{Plane, Shape, Sphere};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    /// The fields tell the shapes apart, so the spheres of the older scene files are still read
+    #[derive(Serialize, Deserialize)]
+    #[serde(untagged)]
+    enum ShapeDescription {
+        Sphere(Sphere),
+        Plane(Plane),
+    }
+
+    #[allow(clippy::borrowed_box)] // serde gives the field as it is
+    pub fn serialize<S: Serializer>(shape: &Box<dyn Shape>, serializer: S) -> Result<S::Ok, S::Error> {
+        let description = if let Some(sphere) = shape.as_sphere() {
+            ShapeDescription::Sphere(*sphere)
+        } else if let Some(plane) = shape.as_any().downcast_ref::<Plane>() {
+            ShapeDescription::Plane(*plane)
+        } else {
+            return Err(serde::ser::Error::custom(format!(
+                "only spheres and planes can be written in scene files, got {:?}",
+                shape
+            )));
+        };
+        description.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Box<dyn Shape>, D::Error> {
+        Ok(match ShapeDescription::deserialize(deserializer)? {
+            ShapeDescription::Sphere(sphere) => Box::new(sphere),
+            ShapeDescription::Plane(plane) => Box::new(plane),
+        })
+    }
 }
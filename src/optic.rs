@@ -1,3 +1,63 @@
+pub mod camera;
 pub mod color;
 pub mod image;
+pub mod light;
 pub mod material;
+pub mod normal_map;
+pub mod sampling;
+pub mod shading;
+pub mod spectrum;
+pub mod tonemap;
+
+use crate::{
+    error::RayTracingError,
+    geometry::{vector::Vector, Scalar},
+};
+
+/// Lambert cosine term between the normal of a surface and the direction from the surface to the light,
+/// 0 for light coming from below the surface
+pub fn lambert_cosine(normal: &Vector, light_direction: &Vector) -> Result<Scalar, RayTracingError> {
+    Ok(normal
+        .normalize()?
+        .scalar_product(&light_direction.normalize()?)
+        .max(0.))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        geometry::{point::Point, ray::Ray, shape::{Shape, Sphere}},
+        optic::sampling::BounceSampler,
+    };
+    use rand::SeedableRng;
+    use rand_distr::{DistIter, Distribution, UnitSphere};
+    use rand_xorshift::XorShiftRng;
+
+    #[test]
+    fn test_lambert_cosine_is_the_same_everywhere() -> Result<(), RayTracingError> {
+        let sphere = Sphere::new_from_radius(&Point::new(0., 0., 0.), 2.);
+        let surface_point = Point::new(0., 2., 0.);
+        let source = Point::new(3., 7., -1.);
+        let normal = sphere.normal_at(&surface_point);
+        let light_direction = Vector::new_from_points(&surface_point, &source);
+        let cos_theta = lambert_cosine(&normal, &light_direction)?;
+
+        // * a mirror keeps the angle with the normal
+        let reflected_ray = Ray::reflected_ray(&source, &sphere, &surface_point)?;
+        assert!((lambert_cosine(&normal, &reflected_ray.direction())? - cos_theta).abs() < 1e-12);
+        assert!((cos_theta - light_direction.angle_with(&normal).cos()).abs() < 1e-12);
+
+        // * the weight of a uniform bounce is twice the cosine term of its direction
+        let rng = XorShiftRng::seed_from_u64(2);
+        let mut unit_sphere_iter: DistIter<UnitSphere, XorShiftRng, [Scalar; 3]> = UnitSphere.sample_iter(rng);
+        for _ in 0..10 {
+            let (bounce_ray, weight) =
+                BounceSampler::Uniform.sample_bounce(&surface_point, &normal, &mut unit_sphere_iter)?;
+            assert!((weight - 2. * lambert_cosine(&normal, &bounce_ray.direction())?).abs() < 1e-12);
+        }
+        assert_eq!(lambert_cosine(&normal, &-light_direction)?, 0.);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,346 @@
+use crate::{
+    error::RayTracingError,
+    geometry::{point::Point, ray::Ray, shape::Sphere, vector::Vector, Scalar},
+};
+
+use super::image::{EYE_POINT, GRID_CENTER_POINT, GRID_HEIGHT, GRID_WIDTH, PIXEL_SIZE};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    pub position: Point,
+    pub look_at: Point,
+    pub up: Vector,
+    fov_degrees: Scalar, // horizontal field of view
+    aspect_ratio: Scalar, // width / height of the image
+    near: Scalar, // primary rays start at this distance from the eye, anything closer is clipped
+}
+
+impl Camera {
+    pub fn new(
+        position: Point,
+        look_at: Point,
+        up: Vector,
+        fov_degrees: Scalar,
+        aspect_ratio: Scalar,
+    ) -> Result<Self, RayTracingError> {
+        if fov_degrees <= 0. || fov_degrees >= 180. || fov_degrees.is_nan() {
+            Err(RayTracingError::CoefficientOOB(fov_degrees, 0., 180.))
+        } else if aspect_ratio <= 0. || !aspect_ratio.is_finite() {
+            Err(RayTracingError::CoefficientOOB(aspect_ratio, 0., Scalar::INFINITY))
+        } else if position == look_at {
+            Err(RayTracingError::VectorHasNormeZero)
+        } else {
+            Ok(Camera {
+                position,
+                look_at,
+                up,
+                fov_degrees,
+                aspect_ratio,
+                near: 0.,
+            })
+        }
+    }
+
+    /// Same as `new` but with the field of view of a lens of focal length `focal_mm` on a sensor of width `sensor_mm`
+    pub fn from_focal_length(
+        position: Point,
+        look_at: Point,
+        up: Vector,
+        focal_mm: Scalar,
+        sensor_mm: Scalar,
+        aspect_ratio: Scalar,
+    ) -> Result<Self, RayTracingError> {
+        if focal_mm <= 0. || focal_mm.is_nan() {
+            return Err(RayTracingError::CoefficientOOB(focal_mm, 0., Scalar::INFINITY));
+        }
+        let fov_degrees = (2. * (sensor_mm / (2. * focal_mm)).atan()).to_degrees();
+        Camera::new(position, look_at, up, fov_degrees, aspect_ratio)
+    }
+
+    /// Camera looking at the center of `bounding` along `direction`, placed so that the sphere fills the whole view,
+    /// corners included, with a horizontal field of view of `fov_degrees` and the aspect ratio of the default grid
+    pub fn frame(
+        bounding: &Sphere,
+        direction: Vector,
+        fov_degrees: Scalar,
+    ) -> Result<Self, RayTracingError> {
+        let unit_direction = direction.normalize()?;
+        let aspect_ratio = GRID_WIDTH as Scalar / GRID_HEIGHT as Scalar;
+        // y goes down, looking straight up or down the up vector is taken along z instead
+        let up = if unit_direction
+            .cross_product(&Vector::new_from_coordinates(0., -1., 0.))
+            .norme_vec()
+            < 1e-6
+        {
+            Vector::new_from_coordinates(0., 0., 1.)
+        } else {
+            Vector::new_from_coordinates(0., -1., 0.)
+        };
+        let tan_half_fov = (fov_degrees.to_radians() / 2.).tan();
+        let corner_half_angle = (tan_half_fov * (1. + 1. / aspect_ratio.powi(2)).sqrt()).atan();
+        let distance = bounding.radius() / corner_half_angle.sin();
+        let position = &bounding.center + &(-distance * &unit_direction);
+        Camera::new(position, bounding.center, up, fov_degrees, aspect_ratio)
+    }
+
+    /// Clips everything closer than `near` to the eye
+    pub fn with_near(mut self, near: Scalar) -> Result<Self, RayTracingError> {
+        if near < 0. || !near.is_finite() {
+            Err(RayTracingError::CoefficientOOB(near, 0., Scalar::INFINITY))
+        } else {
+            self.near = near;
+            Ok(self)
+        }
+    }
+
+    /// Unit vectors going forward, to the right and down in the image, in that order
+    pub fn basis(&self) -> Result<(Vector, Vector, Vector), RayTracingError> {
+        let forward = Vector::new_from_points(&self.position, &self.look_at).normalize()?;
+        let right = forward.cross_product(&self.up).normalize().map_err(|_| {
+            RayTracingError::UnitVectorFromZeroVector(format!(
+                "the up vector {:?} of the camera is parallel to its viewing direction",
+                self.up
+            ))
+        })?;
+        let down = forward.cross_product(&right);
+        Ok((forward, right, down))
+    }
+
+    /// Center of a pixel of an image of `width` x `height` pixels, the image plane goes through `look_at`
+    pub fn pixel_center_point(
+        &self,
+        pixel_width_index: usize,
+        pixel_height_index: usize,
+        width: usize,
+        height: usize,
+    ) -> Result<Point, RayTracingError> {
+        let (_, right, down) = self.basis()?;
+        let image_plane_width =
+            2. * self.position.distance(&self.look_at) * (self.fov_degrees.to_radians() / 2.).tan();
+        let pixel_width = image_plane_width / width as Scalar;
+        let pixel_height = image_plane_width / self.aspect_ratio / height as Scalar;
+        let right_offset = (0.5 + pixel_width_index as Scalar - width as Scalar / 2.) * pixel_width;
+        let down_offset = (0.5 + pixel_height_index as Scalar - height as Scalar / 2.) * pixel_height;
+        Ok(&(&self.look_at + &(right_offset * &right)) + &(down_offset * &down))
+    }
+
+    /// Inverse of `pixel_center_point`: where a point of the scene lands in the image, as fractional pixel indices
+    /// (width index then height index), `None` if the point is not in front of the eye
+    pub fn project_to_pixel(
+        &self,
+        point: &Point,
+        width: usize,
+        height: usize,
+    ) -> Result<Option<(Scalar, Scalar)>, RayTracingError> {
+        let (forward, right, down) = self.basis()?;
+        let eye_to_point = Vector::new_from_points(&self.position, point);
+        let depth = eye_to_point.scalar_product(&forward);
+        if depth <= 0. {
+            return Ok(None);
+        }
+        let image_plane_distance = self.position.distance(&self.look_at);
+        let image_plane_width =
+            2. * image_plane_distance * (self.fov_degrees.to_radians() / 2.).tan();
+        let pixel_width = image_plane_width / width as Scalar;
+        let pixel_height = image_plane_width / self.aspect_ratio / height as Scalar;
+        // * the point is brought back to the image plane along the line going through the eye
+        let scale = image_plane_distance / depth;
+        let right_offset = eye_to_point.scalar_product(&right) * scale;
+        let down_offset = eye_to_point.scalar_product(&down) * scale;
+        Ok(Some((
+            right_offset / pixel_width + width as Scalar / 2. - 0.5,
+            down_offset / pixel_height + height as Scalar / 2. - 0.5,
+        )))
+    }
+
+    /// Ray from the eye through a point of the image plane, starting at the near distance
+    pub fn primary_ray(&self, image_point: &Point) -> Result<Ray, RayTracingError> {
+        let direction = Vector::new_from_points(&self.position, image_point);
+        let origin = &self.position + &(self.near * &direction.normalize()?);
+        Ray::new(origin, direction)
+    }
+
+    pub fn near(&self) -> Scalar {
+        self.near
+    }
+
+    pub fn fov_degrees(&self) -> Scalar {
+        self.fov_degrees
+    }
+
+    pub fn aspect_ratio(&self) -> Scalar {
+        self.aspect_ratio
+    }
+}
+
+impl Default for Camera {
+    /// Camera matching the historical fixed eye, looking at the center of a 1920x1080 image plane of square pixels
+    fn default() -> Self {
+        let half_width = PIXEL_SIZE * GRID_WIDTH as Scalar / 2.;
+        Camera {
+            position: EYE_POINT,
+            look_at: GRID_CENTER_POINT,
+            up: Vector::new_from_coordinates(0., -1., 0.), // y axis goes down
+            fov_degrees: (2. * (half_width / EYE_POINT.distance(&GRID_CENTER_POINT)).atan()).to_degrees(),
+            aspect_ratio: GRID_WIDTH as Scalar / GRID_HEIGHT as Scalar,
+            near: 0.,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{geometry::TOLERANCE, object::{Object, VisibilityFlags}, optic::material::Material, scene::Scene};
+
+    use super::*;
+
+    const POSITION: Point = Point {
+        x: 0.,
+        y: 0.,
+        z: -10.,
+    };
+    const LOOK_AT: Point = Point {
+        x: 0.,
+        y: 0.,
+        z: 0.,
+    };
+
+    #[test]
+    fn test_from_focal_length() -> Result<(), RayTracingError> {
+        let up = Vector::new_from_coordinates(0., -1., 0.);
+        let camera = Camera::from_focal_length(POSITION, LOOK_AT, up, 50., 36., 1.5)?;
+
+        assert!(
+            (camera.fov_degrees() - 39.6).abs() < 0.05,
+            "fov is {}",
+            camera.fov_degrees()
+        );
+        assert_eq!(camera.aspect_ratio(), 1.5);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_new_invalid() {
+        let up = Vector::new_from_coordinates(0., -1., 0.);
+
+        assert!(Camera::new(POSITION, LOOK_AT, up, 0., 1.).is_err());
+        assert!(Camera::new(POSITION, LOOK_AT, up, 180., 1.).is_err());
+        assert!(Camera::new(POSITION, LOOK_AT, up, 60., -1.).is_err());
+        assert!(Camera::new(POSITION, POSITION, up, 60., 1.).is_err());
+        assert!(Camera::from_focal_length(POSITION, LOOK_AT, up, 0., 36., 1.).is_err());
+    }
+
+    #[test]
+    fn test_default_matches_fixed_eye() -> Result<(), RayTracingError> {
+        let camera = Camera::default();
+
+        let center_pixel = camera.pixel_center_point(GRID_WIDTH / 2, GRID_HEIGHT / 2, GRID_WIDTH, GRID_HEIGHT)?;
+        let corner_pixel = camera.pixel_center_point(0, 0, GRID_WIDTH, GRID_HEIGHT)?;
+
+        assert_eq!(center_pixel, Point::new(PIXEL_SIZE / 2., PIXEL_SIZE / 2., 0.));
+        assert_eq!(
+            corner_pixel,
+            Point::new(
+                (0.5 - GRID_WIDTH as Scalar / 2.) * PIXEL_SIZE,
+                (0.5 - GRID_HEIGHT as Scalar / 2.) * PIXEL_SIZE,
+                0.
+            )
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_project_to_pixel() -> Result<(), RayTracingError> {
+        let camera = Camera::default();
+        let pixel_point = camera.pixel_center_point(100, 700, GRID_WIDTH, GRID_HEIGHT)?;
+        // * further away on the same primary ray
+        let scene_point = &pixel_point + &(2. * &Vector::new_from_points(&POSITION, &pixel_point));
+
+        let (pixel_width_index, pixel_height_index) = camera
+            .project_to_pixel(&scene_point, GRID_WIDTH, GRID_HEIGHT)?
+            .expect("the point is in front of the eye");
+
+        assert!((pixel_width_index - 100.).abs() < 1e3 * TOLERANCE);
+        assert!((pixel_height_index - 700.).abs() < 1e3 * TOLERANCE);
+        assert!(camera
+            .project_to_pixel(&Point::new(0., 0., -20.), GRID_WIDTH, GRID_HEIGHT)?
+            .is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_near_clips_sphere_around_eye() -> Result<(), RayTracingError> {
+        let around_eye = Object {
+            shape: Box::new(Sphere::new_from_radius(&POSITION, 1.)),
+            material: Material::default(),
+            visibility: VisibilityFlags::default(),
+        };
+        let in_front = Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(0., 0., 10.), 1.)),
+            material: Material::default(),
+            visibility: VisibilityFlags::default(),
+        };
+        let objects = vec![&around_eye, &in_front];
+        let camera = Camera::default();
+        let clipping_camera = camera.with_near(1.5)?;
+
+        let hit = camera.primary_ray(&LOOK_AT)?.first_point_hit_by_ray(&objects, None)?;
+        let clipped_hit = clipping_camera.primary_ray(&LOOK_AT)?.first_point_hit_by_ray(&objects, None)?;
+
+        assert!(std::ptr::eq(hit.expect("the eye is inside the sphere").object, &around_eye));
+        assert!(std::ptr::eq(clipped_hit.expect("the sphere in front is visible").object, &in_front));
+        assert!(camera.with_near(-1.).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_frame_bounding_sphere() -> Result<(), RayTracingError> {
+        let make_object = |center: Point, radius: Scalar| Object {
+            shape: Box::new(Sphere::new_from_radius(&center, radius)),
+            material: Material::default(),
+            visibility: VisibilityFlags::default(),
+        };
+        let scene = Scene::new(vec![
+            make_object(Point::new(0., 0., 10.), 1.),
+            make_object(Point::new(5., -3., 14.), 2.),
+            make_object(Point::new(-4., 1., 8.), 0.5),
+            make_object(Point::new(1., 6., 12.), 3.),
+        ]);
+
+        let bounding = scene.bounding_sphere().expect("the scene is not empty");
+        let camera = Camera::frame(&bounding, Vector::new_from_coordinates(1., 0., 1.), 60.)?;
+
+        for object in &scene.objects {
+            let sphere = object.shape.as_sphere().expect("the scene only holds spheres");
+            assert!(
+                sphere.center.distance(&bounding.center) + sphere.radius()
+                    <= bounding.radius() + 1e3 * TOLERANCE
+            );
+        }
+        let bounding_object = make_object(bounding.center, bounding.radius());
+        for (pixel_width_index, pixel_height_index) in [
+            (0, 0),
+            (GRID_WIDTH - 1, 0),
+            (0, GRID_HEIGHT - 1),
+            (GRID_WIDTH - 1, GRID_HEIGHT - 1),
+        ] {
+            let corner_point = camera.pixel_center_point(
+                pixel_width_index,
+                pixel_height_index,
+                GRID_WIDTH,
+                GRID_HEIGHT,
+            )?;
+            assert!(camera
+                .primary_ray(&corner_point)?
+                .intersect(&bounding_object)?
+                .is_some());
+        }
+        assert!(Scene::default().bounding_sphere().is_none());
+
+        Ok(())
+    }
+}
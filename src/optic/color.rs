@@ -1,16 +1,59 @@
 use std::ops::{Add, Mul};
 
-use crate::error::RayTracingError;
+use crate::{error::RayTracingError, geometry::Scalar};
 
 #[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "ColorDescription", into = "ColorDescription")
+)]
 pub struct Color {
     r: f32,
     g: f32,
     b: f32,
 }
+
+/// What is written in scene files for a color, it is checked by `Color::new` when it is read
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct ColorDescription {
+    r: f32,
+    g: f32,
+    b: f32,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<ColorDescription> for Color {
+    type Error = RayTracingError;
+    fn try_from(description: ColorDescription) -> Result<Self, Self::Error> {
+        Color::new(description.r, description.g, description.b)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<Color> for ColorDescription {
+    fn from(color: Color) -> Self {
+        ColorDescription {
+            r: color.r,
+            g: color.g,
+            b: color.b,
+        }
+    }
+}
 // * note that the creation is made to bound it to 0..=1 but subsequent operations can bring the number higher than 1
 // this allows for color summation and averaging, but we must be careful when converting back to an actual color format such as triple u8
 
+/// How a color with channels above 1 is brought back to something that can be written in 8 bits
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum GamutMapping {
+    /// each channel is clamped on its own, over-bright saturated colors drift towards yellow, cyan, magenta or white
+    #[default]
+    Clip,
+    /// the color is moved towards the gray of same luminance until its largest channel is 1, which keeps the hue
+    DesaturateToFit,
+}
+
 impl Color {
     pub fn new(r: f32, g: f32, b: f32) -> Result<Self, RayTracingError> {
         if !(0. ..=1.).contains(&r) || !(0. ..=1.).contains(&g) || !(0. ..=1.).contains(&b) {
@@ -34,38 +77,168 @@ impl Color {
         }
     }
 
+    /// Color of a pipeline working with double precision floats, the channels outside of 0..=1 are rejected
+    /// as with `new` rather than clamped, so that a wrong scale isn't hidden
+    pub fn from_f64(r: f64, g: f64, b: f64) -> Result<Color, RayTracingError> {
+        Color::new(r as f32, g as f32, b as f32)
+    }
+
+    /// Approximate color of a black body at the given temperature, using the fit by Tanner Helland
+    /// (valid from 1000K to 40000K, temperatures outside are clamped)
+    pub fn from_temperature(kelvin: f64) -> Color {
+        let temperature = kelvin.clamp(1000., 40000.) / 100.;
+        let red = if temperature <= 66. {
+            255.
+        } else {
+            329.698727446 * (temperature - 60.).powf(-0.1332047592)
+        };
+        let green = if temperature <= 66. {
+            99.4708025861 * temperature.ln() - 161.1195681661
+        } else {
+            288.1221695283 * (temperature - 60.).powf(-0.0755148492)
+        };
+        let blue = if temperature >= 66. {
+            255.
+        } else if temperature <= 19. {
+            0.
+        } else {
+            138.5177312231 * (temperature - 10.).ln() - 305.0447927307
+        };
+        let to_coefficient = |channel: f64| (channel.clamp(0., 255.) / 255.) as f32;
+        Color {
+            r: to_coefficient(red),
+            g: to_coefficient(green),
+            b: to_coefficient(blue),
+        }
+    }
+
+    /// The channels are taken as they are, as linear reflectances
     pub fn to_diffusion_coefficient(&self) -> Result<DiffusionCoefficient, RayTracingError> {
         let Color { r, g, b } = *self;
         DiffusionCoefficient::new(r, g, b)
     }
 
+    /// The channels are taken as sRGB encoded, like the colors picked in an image editor, and decoded to linear reflectances
+    /// a mid gray of 188 in sRGB reflects half of the light, where `to_diffusion_coefficient` would make it reflect 74%
+    pub fn to_diffusion_coefficient_srgb(&self) -> Result<DiffusionCoefficient, RayTracingError> {
+        let decode = |channel: f32| {
+            if channel <= 0.04045 {
+                channel / 12.92
+            } else {
+                ((channel + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        let Color { r, g, b } = *self;
+        DiffusionCoefficient::new(decode(r), decode(g), decode(b))
+    }
+
     pub fn get_components(&self) -> (f32, f32, f32) {
         (self.r, self.g, self.b)
     }
 
+    pub fn max_channel(&self) -> f32 {
+        self.r.max(self.g).max(self.b)
+    }
+
+    pub fn is_finite(&self) -> bool {
+        self.r.is_finite() && self.g.is_finite() && self.b.is_finite()
+    }
+
+    /// Relative luminance with the Rec. 709 weights
+    pub fn luminance(&self) -> f32 {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
+
+    /// True if the color would be written as pure black in 8 bits
+    pub fn is_black(&self) -> bool {
+        self.max_channel() * (u8::MAX as f32) < 0.5
+    }
+
+    /// True if the luminance of the color is at most `epsilon`
+    pub fn is_negligible(&self, epsilon: Scalar) -> bool {
+        (self.luminance() as Scalar) <= epsilon
+    }
+
+    /// Color with every channel in 0..=1, negative channels are set to 0 whatever the mapping
+    pub fn map_gamut(&self, mapping: GamutMapping) -> Color {
+        let clipped = Color {
+            r: self.r.clamp(0., 1.),
+            g: self.g.clamp(0., 1.),
+            b: self.b.clamp(0., 1.),
+        };
+        match mapping {
+            GamutMapping::Clip => clipped,
+            GamutMapping::DesaturateToFit => {
+                let max_channel = self.max_channel();
+                let luminance = self.luminance();
+                if max_channel <= 1. {
+                    clipped
+                } else if luminance >= 1. {
+                    // * too bright to keep any saturation
+                    WHITE
+                } else {
+                    let saturation_kept = (1. - luminance) / (max_channel - luminance);
+                    let desaturate = |channel: f32| (luminance + saturation_kept * (channel - luminance)).clamp(0., 1.);
+                    Color {
+                        r: desaturate(self.r),
+                        g: desaturate(self.g),
+                        b: desaturate(self.b),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Color with any channels, for the unbounded values of the renderer that were written to a file
+    pub(crate) fn new_unbounded(r: f32, g: f32, b: f32) -> Color {
+        Color { r, g, b }
+    }
+
+    /// Porter-Duff "over": the color laid on top of `background` with an opacity of `alpha`, mixed in linear space
+    pub fn blend_over(&self, background: &Color, alpha: Scalar) -> Color {
+        &(self * alpha) + &(background * (1. - alpha))
+    }
+
+    /// Color with `transform` applied to each channel, the result isn't bounded to 0..=1
+    pub fn map_channels(&self, transform: impl Fn(f32) -> f32) -> Color {
+        Color {
+            r: transform(self.r),
+            g: transform(self.g),
+            b: transform(self.b),
+        }
+    }
+
+    /// Same as `into_rgb` but colors out of range are mapped first instead of being an error
+    pub fn into_rgb_mapped(self, mapping: GamutMapping) -> Result<(u8, u8, u8), RayTracingError> {
+        self.map_gamut(mapping).into_rgb()
+    }
+
+    /// Channels rounded to the nearest 8 bit value, truncating would darken the whole image by half a step on average
     pub fn into_rgb(self) -> Result<(u8, u8, u8), RayTracingError> {
         let Color { r, g, b } = self.new_from_color()?;
-        let r = (r * u8::MAX as f32) as u8;
-        let g = (g * u8::MAX as f32) as u8;
-        let b = (b * u8::MAX as f32) as u8;
-        Ok((r, g, b))
+        Ok((to_8_bits(r), to_8_bits(g), to_8_bits(b)))
     }
 }
 
-impl Mul<f64> for &Color {
+/// Nearest 8 bit value of a channel in 0..=1
+pub(crate) fn to_8_bits(channel: f32) -> u8 {
+    (channel * u8::MAX as f32).round() as u8
+}
+
+impl Mul<Scalar> for &Color {
     type Output = Color;
-    fn mul(self, rhs: f64) -> Self::Output {
+    fn mul(self, rhs: Scalar) -> Self::Output {
         // Rust 1.45 introduced overflow control with the as keyword
         // setting the value to the crossed bound
-        let r = (self.r as f64 * rhs) as f32;
-        let g = (self.g as f64 * rhs) as f32;
-        let b = (self.b as f64 * rhs) as f32;
+        let r = (self.r as Scalar * rhs) as f32;
+        let g = (self.g as Scalar * rhs) as f32;
+        let b = (self.b as Scalar * rhs) as f32;
 
         Color { r, g, b }
     }
 }
 
-impl Mul<&Color> for f64 {
+impl Mul<&Color> for Scalar {
     type Output = Color;
     fn mul(self, rhs: &Color) -> Self::Output {
         rhs * self
@@ -123,13 +296,46 @@ pub const BLUE: Color = Color {
     b: 1.,
 };
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "DiffusionCoefficientDescription", into = "DiffusionCoefficientDescription")
+)]
 pub struct DiffusionCoefficient {
     dr: f32, // should be between 0 and 1
     dg: f32,
     db: f32,
 }
 
+/// What is written in scene files for diffusion coefficients, they are checked by `DiffusionCoefficient::new` when read
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct DiffusionCoefficientDescription {
+    dr: f32,
+    dg: f32,
+    db: f32,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<DiffusionCoefficientDescription> for DiffusionCoefficient {
+    type Error = RayTracingError;
+    fn try_from(description: DiffusionCoefficientDescription) -> Result<Self, Self::Error> {
+        DiffusionCoefficient::new(description.dr, description.dg, description.db)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<DiffusionCoefficient> for DiffusionCoefficientDescription {
+    fn from(coefficient: DiffusionCoefficient) -> Self {
+        DiffusionCoefficientDescription {
+            dr: coefficient.dr,
+            dg: coefficient.dg,
+            db: coefficient.db,
+        }
+    }
+}
+
 impl DiffusionCoefficient {
     pub fn new(dr: f32, dg: f32, db: f32) -> Result<Self, RayTracingError> {
         if !(0. ..=1.).contains(&dr) || !(0. ..=1.).contains(&dg) || !(0. ..=1.).contains(&db) {
@@ -138,8 +344,29 @@ impl DiffusionCoefficient {
             Ok(DiffusionCoefficient { dr, dg, db })
         }
     }
+
+    /// Same as `new` but the coefficients are clamped to 0..=1 instead of being an error, NaN is taken as 0
+    pub fn new_clamped(dr: f32, dg: f32, db: f32) -> Self {
+        let clamp = |coefficient: f32| if coefficient.is_nan() { 0. } else { coefficient.clamp(0., 1.) };
+        DiffusionCoefficient {
+            dr: clamp(dr),
+            dg: clamp(dg),
+            db: clamp(db),
+        }
+    }
+
+    pub fn get_components(&self) -> (f32, f32, f32) {
+        (self.dr, self.dg, self.db)
+    }
 }
 
+/// Diffuses all the light it receives, known to be valid so usable where no error can be returned
+pub const FULL_DIFFUSION: DiffusionCoefficient = DiffusionCoefficient {
+    dr: 1.,
+    dg: 1.,
+    db: 1.,
+};
+
 impl Mul<&Color> for &DiffusionCoefficient {
     type Output = Color;
     fn mul(self, rhs: &Color) -> Self::Output {
@@ -180,3 +407,133 @@ impl Mul<&DiffusionCoefficient> for &Color {
 //         Ok(&source_color * &object_diffusion_coefficient)
 //     }
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_deserialize_checks_coefficients() -> Result<(), serde_json::Error> {
+        let color: Color = serde_json::from_str(r#"{ "r": 1.0, "g": 0.5, "b": 0.0 }"#)?;
+        let coefficient: DiffusionCoefficient = serde_json::from_str(r#"{ "dr": 0.25, "dg": 1.0, "db": 0.0 }"#)?;
+
+        assert_eq!(color.get_components(), (1., 0.5, 0.));
+        assert_eq!(coefficient.get_components(), (0.25, 1., 0.));
+        assert!(serde_json::from_str::<Color>(r#"{ "r": 1.5, "g": 0.5, "b": 0.0 }"#).is_err());
+        assert!(serde_json::from_str::<DiffusionCoefficient>(r#"{ "dr": 0.25, "dg": -1.0, "db": 0.0 }"#).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_negligible() -> Result<(), RayTracingError> {
+        let tiny_color = Color::new(1e-7, 0., 2e-7)?;
+
+        assert!(tiny_color.is_negligible(1e-6));
+        assert!(!tiny_color.is_negligible(1e-9));
+        assert!(BLACK.is_negligible(0.));
+        assert!(BLACK.is_negligible(1e-6));
+        assert!(!WHITE.is_negligible(1e-6));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_blend_over() -> Result<(), RayTracingError> {
+        let foreground = Color::new(1., 0.5, 0.)?;
+        let background = Color::new(0., 0.25, 1.)?;
+
+        assert_eq!(foreground.blend_over(&background, 1.), foreground);
+        assert_eq!(foreground.blend_over(&background, 0.), background);
+        assert_eq!(foreground.blend_over(&background, 0.5), Color::new(0.5, 0.375, 0.5)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_into_rgb_rounds() -> Result<(), RayTracingError> {
+        assert_eq!((&WHITE * 0.999).into_rgb()?, (255, 255, 255));
+        assert_eq!((&WHITE * 0.5).into_rgb()?, (128, 128, 128));
+        assert_eq!((&WHITE * (0.49 / 255.)).into_rgb()?, (0, 0, 0));
+        assert_eq!(Color::new(1. / 255., 0.2, 1.)?.into_rgb()?, (1, 51, 255));
+        assert_eq!(BLACK.into_rgb()?, (0, 0, 0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_f64() -> Result<(), RayTracingError> {
+        assert_eq!(Color::from_f64(1.0, 0.5, 0.0)?.into_rgb()?, (255, 128, 0));
+        assert!(Color::from_f64(1.5, 0.5, 0.0).is_err());
+        assert!(Color::from_f64(0.5, -0.1, 0.0).is_err());
+        assert!(Color::from_f64(0.5, 0.5, f64::NAN).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_black() -> Result<(), RayTracingError> {
+        assert!(BLACK.is_black());
+        assert!(Color::new(0.4 / 255., 0., 0.)?.is_black());
+        // * rounded up to 1 when written
+        assert!(!Color::new(0.6 / 255., 0., 0.)?.is_black());
+        assert!(!Color::new(0., 1. / 255., 0.)?.is_black());
+        assert!(!WHITE.is_black());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_map_gamut() -> Result<(), RayTracingError> {
+        let bright_red = &Color::new(1., 0.2, 0.1)? * 2.;
+
+        let clipped = bright_red.map_gamut(GamutMapping::Clip);
+        let desaturated = bright_red.map_gamut(GamutMapping::DesaturateToFit);
+        let (clipped_r, clipped_g, clipped_b) = clipped.get_components();
+        let (desaturated_r, desaturated_g, desaturated_b) = desaturated.get_components();
+
+        assert_eq!(clipped, Color::new(1., 0.4, 0.2)?);
+        assert_ne!(clipped, desaturated);
+        assert!((desaturated_r - 1.).abs() < 1e-6);
+        // * both are in range but only the desaturated one keeps the hue: its channels stay ordered with the same ratios of differences
+        let hue_ratio = |r: f32, g: f32, b: f32| (g - b) / (r - b);
+        assert!((hue_ratio(desaturated_r, desaturated_g, desaturated_b) - hue_ratio(2., 0.4, 0.2)).abs() < 1e-5);
+        assert!((hue_ratio(clipped_r, clipped_g, clipped_b) - hue_ratio(2., 0.4, 0.2)).abs() > 0.1);
+        assert_eq!(WHITE.map_gamut(GamutMapping::DesaturateToFit), WHITE);
+        assert_eq!((&WHITE * 3.).map_gamut(GamutMapping::DesaturateToFit), WHITE);
+        assert!((&WHITE * 3.).into_rgb().is_err());
+        assert_eq!((&WHITE * 3.).into_rgb_mapped(GamutMapping::Clip)?, (255, 255, 255));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_diffusion_coefficient_srgb() -> Result<(), RayTracingError> {
+        let mid_gray = Color::new(188. / 255., 188. / 255., 188. / 255.)?;
+
+        let (linear_dr, _, _) = mid_gray.to_diffusion_coefficient()?.get_components();
+        let (srgb_dr, srgb_dg, srgb_db) = mid_gray.to_diffusion_coefficient_srgb()?.get_components();
+
+        assert!((linear_dr - 0.737).abs() < 1e-3);
+        assert!((srgb_dr - 0.5).abs() < 5e-3);
+        assert!(srgb_dr == srgb_dg && srgb_dg == srgb_db);
+        // * black and white are the same in both encodings
+        assert_eq!(BLACK.to_diffusion_coefficient_srgb()?, BLACK.to_diffusion_coefficient()?);
+        assert_eq!(WHITE.to_diffusion_coefficient_srgb()?, WHITE.to_diffusion_coefficient()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_temperature() {
+        let (daylight_r, daylight_g, daylight_b) = Color::from_temperature(6500.).get_components();
+        let (candle_r, candle_g, candle_b) = Color::from_temperature(2000.).get_components();
+        let (sky_r, sky_g, sky_b) = Color::from_temperature(10000.).get_components();
+
+        assert!(daylight_r > 0.95 && daylight_g > 0.9 && daylight_b > 0.9);
+        assert!(candle_r == 1. && candle_g < 0.6 && candle_b < 0.2 && candle_g > candle_b);
+        assert!(sky_b == 1. && sky_r < 0.8 && sky_r < sky_g);
+        assert_eq!(Color::from_temperature(100.), Color::from_temperature(1000.));
+    }
+}
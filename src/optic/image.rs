@@ -1,34 +1,58 @@
 use crate::{
+    accelerator::Accelerator,
     error::RayTracingError,
-    geometry::{point::Point, ray::Ray, vector::Vector},
+    geometry::{
+        aabb::Aabb,
+        consts,
+        point::Point,
+        ray::{HitInfo, Ray, RayKind},
+        vector::Vector,
+        Scalar,
+    },
     object::Object,
 };
 
-use image::{Rgb, RgbImage};
+use image::{Rgb, RgbImage, Rgba, RgbaImage};
 use rand::SeedableRng;
 use rand_xorshift::{self, XorShiftRng};
 use rand_distr::{self, DistIter, Distribution, UnitSphere};
 
-use super::color::{self, Color};
+use super::{
+    camera::Camera,
+    lambert_cosine,
+    light::{Falloff, Light},
+    sampling::{uniform_sample, BounceSampler},
+    shading::ShadingContext,
+    spectrum::ColorMode,
+    tonemap::{self, ToneMap},
+    color::{self, Color, GamutMapping},
+};
 
-use std::path::PathBuf;
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::PathBuf,
+};
 
-const GRID_WIDTH: usize = 1920; // ! should be even
-const GRID_HEIGHT: usize = 1080; // ! should be even
-const PIXEL_SIZE: f64 = 1e-2;
-const EYE_POINT: Point = Point {
+pub(crate) const GRID_WIDTH: usize = 1920;
+pub(crate) const GRID_HEIGHT: usize = 1080;
+pub(crate) const PIXEL_SIZE: Scalar = 1e-2;
+pub(crate) const EYE_POINT: Point = Point {
     x: 0.,
     y: 0.,
     z: -10.,
-}; // should change functions to take the position as an argument and not refer to this
-const GRID_CENTER_POINT: Point = Point {
+}; // only used for the default camera
+pub(crate) const GRID_CENTER_POINT: Point = Point {
     x: 0.,
     y: 0.,
     z: 0.,
 };
+const RENDER_SEED: u64 = 51468412518;
+// start of the files written by `save_state`, the last byte is the version of the format
+const RENDER_STATE_MAGIC: [u8; 8] = *b"RTGRID\0\x01";
+// bytes of each pixel in these files: the color, the coverage and the number of samples
+const RENDER_STATE_PIXEL_SIZE: usize = 4 * std::mem::size_of::<f32>() + std::mem::size_of::<u64>();
 
-const GRID_CENTER_WIDTH_INDEX: usize = GRID_WIDTH / 2; 
-const GRID_CENTER_HEIGHT_INDEX: usize = GRID_HEIGHT / 2;
 
 /*----------------------------
 Axis orientation
@@ -45,209 +69,1561 @@ pub fn get_background_color() -> Result<Color, RayTracingError> {
     Color::new(0., 0., 0.0)
 }
 
-#[derive(Debug)]
+/// Same as `Ray::first_visible_hit`, through the accelerator when there is one, it then holds the objects
+fn first_visible_hit<'a>(
+    ray: &Ray,
+    objects: &[&'a Object],
+    accelerator: Option<&'a dyn Accelerator>,
+    ignore_object: Option<&Object>,
+    ray_kind: RayKind,
+) -> Result<Option<HitInfo<'a>>, RayTracingError> {
+    match accelerator {
+        Some(accelerator) => accelerator.first_visible_hit(ray, ignore_object, ray_kind),
+        None => ray.first_visible_hit(objects, ignore_object, ray_kind),
+    }
+}
+
+/// How `trace_ray` shades the hits, taken from the grid being rendered
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct TraceSettings<'a> {
+    pub sampler: BounceSampler,
+    pub ambient: Option<Color>,
+    pub emitters: &'a [Object], // sampled at each diffuse hit, empty for pure path tracing
+    pub lights: &'a [Light], // every one of them lights each diffuse hit
+    pub light_falloff: Falloff,
+    pub color_mode: ColorMode,
+    pub accelerator: Option<&'a dyn Accelerator>, // looks for the hits instead of trying every object when there is one
+}
+
+/// When to stop making a ray bounce around the scene
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BounceLimit {
+    /// a fixed maximum number of bounces
+    Fixed(u64),
+    /// bounce until the brightest channel of the ray color goes below the threshold
+    UntilThreshold(Scalar),
+}
+
+// a ray trapped between bright objects could bounce for a long time before going below the threshold
+const MAX_BOUNCES_UNTIL_THRESHOLD: u64 = 1000;
+const NEGLIGIBLE_THROUGHPUT: Scalar = 1e-6; // below this luminance a ray can no longer carry visible light
+
+impl BounceLimit {
+    fn max_bounces(&self) -> u64 {
+        match self {
+            BounceLimit::Fixed(number_of_bounces) => *number_of_bounces,
+            BounceLimit::UntilThreshold(_) => MAX_BOUNCES_UNTIL_THRESHOLD,
+        }
+    }
+
+    fn ray_is_exhausted(&self, ray_color: &Color) -> bool {
+        match self {
+            BounceLimit::Fixed(_) => ray_color.is_negligible(NEGLIGIBLE_THROUGHPUT),
+            BounceLimit::UntilThreshold(threshold) => (ray_color.max_channel() as Scalar) < *threshold,
+        }
+    }
+}
+
+impl From<u64> for BounceLimit {
+    fn from(number_of_bounces: u64) -> Self {
+        BounceLimit::Fixed(number_of_bounces)
+    }
+}
+
+/// What `make_image` computes for each pixel
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum RenderMode {
+    /// path traced colors
+    #[default]
+    Shaded,
+    /// white pixels where the primary rays of the pixel don't all hit the same object (or all miss), black elsewhere
+    Edges,
+}
+
+/// Pixels rendered by a pass of `Grid::make_image_checkerboard`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CheckerboardPass {
+    /// the pixels whose indices have an even sum, the others are filled from a neighbor for a quick preview
+    First,
+    /// the remaining pixels, which completes the image
+    Second,
+}
+
+impl CheckerboardPass {
+    fn renders(&self, pixel_height_index: usize, pixel_width_index: usize) -> bool {
+        let is_even = (pixel_height_index + pixel_width_index) % 2 == 0;
+        match self {
+            CheckerboardPass::First => is_even,
+            CheckerboardPass::Second => !is_even,
+        }
+    }
+}
+
+/// How the samples of a pixel are weighted depending on their position in the pixel
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum PixelFilter {
+    /// every sample has the same weight
+    #[default]
+    Box,
+    /// the weight goes linearly from 1 at the center of the pixel to 0 at its edges
+    Tent,
+}
+
+impl PixelFilter {
+    /// Weight of a sample at the given offsets from the pixel center, in half pixels (so between -1 and 1)
+    fn weight(&self, right_offset: Scalar, down_offset: Scalar) -> Scalar {
+        match self {
+            PixelFilter::Box => 1.,
+            PixelFilter::Tent => (1. - right_offset.abs()).max(0.) * (1. - down_offset.abs()).max(0.),
+        }
+    }
+}
+
+/// Middle gray targeted by `Grid::auto_exposure` when there is no reason to pick another key
+pub const DEFAULT_EXPOSURE_KEY: Scalar = 0.18;
+// keeps the black pixels from pulling the log average down to 0
+const LOG_AVERAGE_DELTA: Scalar = 1e-4;
+
+/// Decoding gamma of usual displays, the colors are encoded with it on export
+pub const DEFAULT_GAMMA: Scalar = 2.2;
+
+// pixels differing by less than one 8 bit step on every channel are considered identical
+const DIFF_TOLERANCE: f32 = 1. / 255.;
+
+/// Per channel (r, g, b) absolute differences between two grids
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DiffReport {
+    pub max_difference: (f32, f32, f32),
+    pub mean_difference: (f32, f32, f32),
+    pub number_of_differing_pixels: usize, // pixels with a channel differing by more than DIFF_TOLERANCE
+    pub rms_difference: f32, // root mean square of the differences of every channel of every pixel
+}
+
+/// Change brought by a pass of `Grid::make_image_until_converged`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ConvergencePass {
+    pub number_of_points_per_pixel: usize, // rays averaged in each pixel once the pass is done
+    pub rms_difference: f32, // between the image before and after the pass
+}
+
+#[derive(Debug, Clone)]
 pub struct Grid {
     width: usize,
     height: usize,
     pub colors: Vec<Vec<Color>>,
+    pub sampler: BounceSampler,
+    pub render_mode: RenderMode,
+    pub camera: Camera,
+    pub coverage: Vec<Vec<f32>>, // part of the primary rays of each pixel that hit an object
+    samples: Vec<Vec<usize>>, // primary rays averaged in each pixel, `make_image` adds its rays to them
+    pub transparent_background: bool, // export with an alpha channel, the background being transparent
+    pub filter: PixelFilter,
+    pub gamut_mapping: GamutMapping, // how colors above 1 are written on export
+    pub exposure: Scalar, // multiplies the colors on export, before the gamut mapping
+    pub tone_map: ToneMap, // brings the exposed colors to 0..=1 on export, before the gamut mapping
+    pub gamma: Scalar, // encodes the colors on export, after the gamut mapping, 1 writes the linear values
+    pub ambient: Option<Color>, // fill light added at every diffuse hit, None for pure path tracing
+    pub emitters: Vec<Object>, // emissive objects whose light is sampled at each diffuse hit, see `Scene::prepare`
+    pub lights: Vec<Light>, // lights that are not objects, they light the diffuse hits but the rays never hit them
+    pub light_falloff: Falloff, // how the light of `lights` decreases with distance, the emissive objects have none
+    pub color_mode: ColorMode, // how the lights are filtered by the surfaces, RGB unless set to spectral
+    // ! finds the hits in place of the objects given to `make_image`, it must be built from the same objects
+    pub accelerator: Option<Box<dyn Accelerator>>,
 }
 
 impl Grid {
+    /// Black grid of `width` x `height` pixels, seen by the default camera with its aspect ratio matched to the grid
+    pub fn new(width: usize, height: usize) -> Result<Self, RayTracingError> {
+        let default_camera = Camera::default();
+        let camera = Camera::new(
+            default_camera.position,
+            default_camera.look_at,
+            default_camera.up,
+            default_camera.fov_degrees(),
+            width as Scalar / height as Scalar,
+        )?;
+        Ok(Grid::with_camera(width, height, camera))
+    }
+
+    /// Black grid of `width` x `height` pixels seen by `camera`, with the default export settings
+    fn with_camera(width: usize, height: usize, camera: Camera) -> Self {
+        Grid {
+            width,
+            height,
+            colors: vec![vec![color::BLACK; width]; height],
+            sampler: BounceSampler::default(),
+            render_mode: RenderMode::default(),
+            camera,
+            coverage: vec![vec![1.; width]; height],
+            samples: vec![vec![0; width]; height],
+            transparent_background: false,
+            filter: PixelFilter::default(),
+            gamut_mapping: GamutMapping::default(),
+            exposure: 1.,
+            tone_map: ToneMap::default(),
+            gamma: DEFAULT_GAMMA,
+            ambient: None,
+            emitters: Vec::new(),
+            lights: Vec::new(),
+            light_falloff: Falloff::default(),
+            color_mode: ColorMode::default(),
+            accelerator: None,
+        }
+    }
+
+    /// Center of the pixel along with half the steps to the next pixel to the right and to the next pixel down
+    fn pixel_center_and_half_steps(
+        &self,
+        pixel_width_index: usize,
+        pixel_height_index: usize,
+    ) -> Result<(Point, Vector, Vector), RayTracingError> {
+        let pixel_center_point = |width_index, height_index| {
+            self.camera
+                .pixel_center_point(width_index, height_index, self.width, self.height)
+        };
+        let center = pixel_center_point(pixel_width_index, pixel_height_index)?;
+        let half_right_step = 0.5
+            * &Vector::new_from_points(&center, &pixel_center_point(pixel_width_index + 1, pixel_height_index)?);
+        let half_down_step = 0.5
+            * &Vector::new_from_points(&center, &pixel_center_point(pixel_width_index, pixel_height_index + 1)?);
+        Ok((center, half_right_step, half_down_step))
+    }
+
     fn pixel_point_selection(
+        &self,
         pixel_width_index: usize,
         pixel_height_index: usize,
         number_of_points_per_pixel: usize,
-    ) -> Vec<Point> {
-        let pixel_center_point_x =
-            (0.5 + pixel_width_index as f64 - GRID_CENTER_WIDTH_INDEX as f64) * PIXEL_SIZE
-                + GRID_CENTER_POINT.x;
-        let pixel_center_point_y =
-            (0.5 + pixel_height_index as f64 - GRID_CENTER_HEIGHT_INDEX as f64) * PIXEL_SIZE
-                + GRID_CENTER_POINT.y;
-        let pixel_center_point = Point::new(pixel_center_point_x, pixel_center_point_y, 0.);
-        // default implementation for now, just return the center point of the pixel
-        // * will try to return a random distribution of points in the pixel for anti-aliasing later
-        vec![pixel_center_point; number_of_points_per_pixel]
+        unit_disc_iter: &mut DistIter<UnitSphere, XorShiftRng, [Scalar; 3]>,
+    ) -> Result<Vec<(Point, Scalar)>, RayTracingError> {
+        let (pixel_center_point, half_right_step, half_down_step) =
+            self.pixel_center_and_half_steps(pixel_width_index, pixel_height_index)?;
+        // the pixel is cut in the largest square number of cells with at most one point each, each point is random in its cell,
+        // the points left over are random in the whole pixel, and a single point is the pixel center
+        let cells_per_side = ((number_of_points_per_pixel as Scalar).sqrt().floor() as usize).max(1);
+        let number_of_cells = cells_per_side * cells_per_side;
+        // offset in half pixels, between -1 and 1, of a random point in the cell
+        let cell_offset =
+            |cell_index: usize, sample: Scalar| 2. * (cell_index as Scalar + sample) / cells_per_side as Scalar - 1.;
+        let mut pixel_points = Vec::with_capacity(number_of_points_per_pixel);
+        for point_index in 0..number_of_points_per_pixel {
+            let (right_offset, down_offset) = if number_of_points_per_pixel == 1 {
+                (0., 0.)
+            } else if point_index < number_of_cells {
+                (
+                    cell_offset(point_index % cells_per_side, uniform_sample(unit_disc_iter)?),
+                    cell_offset(point_index / cells_per_side, uniform_sample(unit_disc_iter)?),
+                )
+            } else {
+                (
+                    2. * uniform_sample(unit_disc_iter)? - 1.,
+                    2. * uniform_sample(unit_disc_iter)? - 1.,
+                )
+            };
+            let point = &(&pixel_center_point + &(right_offset * &half_right_step))
+                + &(down_offset * &half_down_step);
+            pixel_points.push((point, self.filter.weight(right_offset, down_offset)));
+        }
+        Ok(pixel_points)
     }
 
+    /// Primary rays of the pixel along with their filter weights
     fn ray_eye_pixel_point(
+        &self,
         pixel_width_index: usize,
         pixel_height_index: usize,
         number_of_points_per_pixel: usize,
-    ) -> Result<Vec<Vector>, RayTracingError> {
-        let pixel_points = Self::pixel_point_selection(
+        unit_disc_iter: &mut DistIter<UnitSphere, XorShiftRng, [Scalar; 3]>,
+    ) -> Result<Vec<(Ray, Scalar)>, RayTracingError> {
+        let pixel_points = self.pixel_point_selection(
             pixel_width_index,
             pixel_height_index,
             number_of_points_per_pixel,
-        );
-        let mut unit_vector_list: Vec<Vector> = Vec::new();
-        for point in pixel_points {
-            let u_vec = Vector::new_from_points(&EYE_POINT, &point);
-            unit_vector_list.push(u_vec);
+            unit_disc_iter,
+        )?;
+        pixel_points
+            .iter()
+            .map(|(point, weight)| Ok((self.camera.primary_ray(point)?, *weight)))
+            .collect()
+    }
+
+    fn trace_settings(&self) -> TraceSettings<'_> {
+        TraceSettings {
+            sampler: self.sampler,
+            ambient: self.ambient,
+            emitters: &self.emitters,
+            lights: &self.lights,
+            light_falloff: self.light_falloff,
+            color_mode: self.color_mode,
+            accelerator: self.accelerator.as_deref(),
+        }
+    }
+
+    /// Light coming straight from one of the emitters picked at random to a diffuse hit, reflected towards the ray
+    /// by a white surface, a point of the emitter is picked uniformly in the cone it fills seen from the hit point,
+    /// only spheres are sampled
+    fn sample_emitters(
+        hit_info: &HitInfo,
+        objects: &[&Object],
+        accelerator: Option<&dyn Accelerator>,
+        emitters: &[Object],
+        unit_disc_iter: &mut DistIter<UnitSphere, XorShiftRng, [Scalar; 3]>,
+    ) -> Result<Color, RayTracingError> {
+        let number_of_emitters = emitters.len();
+        let emitter_index = ((uniform_sample(unit_disc_iter)? * number_of_emitters as Scalar) as usize)
+            .min(number_of_emitters - 1);
+        let emitter = &emitters[emitter_index];
+        let (cone_sample, rotation_sample) = (uniform_sample(unit_disc_iter)?, uniform_sample(unit_disc_iter)?);
+        // * a sphere can't light itself, and a point inside an emitter has no cone to sample
+        let Some(emitter_sphere) = emitter.shape.as_sphere() else {
+            return Ok(color::BLACK);
+        };
+        if *emitter.shape == *hit_info.object.shape {
+            return Ok(color::BLACK);
         }
-        Ok(unit_vector_list)
+        let to_center = Vector::new_from_points(&hit_info.point_hit, &emitter_sphere.center);
+        let distance = to_center.norme_vec();
+        let radius = emitter_sphere.radius();
+        if distance <= radius {
+            return Ok(color::BLACK);
+        }
+        let cos_max = (1. - (radius / distance).powi(2)).sqrt();
+        let axis = to_center.normalize()?;
+        let [first_side, second_side] = axis.tangent_plane_vectors()?;
+        let cos_theta = 1. - cone_sample * (1. - cos_max);
+        let sin_theta = (1. - cos_theta * cos_theta).max(0.).sqrt();
+        let phi = 2. * consts::PI * rotation_sample;
+        let direction = (sin_theta * phi.cos() * &first_side)
+            + (sin_theta * phi.sin() * &second_side)
+            + (cos_theta * &axis);
+        let shading_normal = hit_info.object.material.shading_normal(&hit_info.normal, hit_info.uv)?;
+        let cos_surface = lambert_cosine(&shading_normal, &direction)?;
+        // * a normal map can tilt the shading normal towards a light that is below the surface
+        if cos_surface == 0. || lambert_cosine(&hit_info.normal, &direction)? == 0. {
+            return Ok(color::BLACK);
+        }
+        let shadow_ray = Ray::spawn(&hit_info.point_hit, direction, &hit_info.normal);
+        // like the bounces, the shadow ray can't hit the outward sphere it starts from again
+        let ignored_object = if hit_info.object.shape.is_concave() { None } else { Some(hit_info.object) };
+        match first_visible_hit(&shadow_ray, objects, accelerator, ignored_object, RayKind::Shadow)? {
+            Some(shadow_hit) if *shadow_hit.object.shape == *emitter.shape => {}
+            _ => return Ok(color::BLACK),
+        }
+        let emitted_light =
+            &emitter.material.emission_color * emitter.material.emission_strength_towards(&-direction)?;
+        // the lambertian brdf is albedo / pi and the cone is sampled with a pdf of 1 / its solid angle,
+        // the emitter itself is picked with a probability of 1 / number of emitters
+        let solid_angle = 2. * consts::PI * (1. - cos_max);
+        let weight = cos_surface * solid_angle / consts::PI * number_of_emitters as Scalar;
+        Ok(&emitted_light * weight)
+    }
+
+    /// Light coming straight from each of the lights to a diffuse hit, reflected towards the ray by a white surface,
+    /// a light of intensity 1 facing the surface makes it white, the area lights are seen from a point of their surface
+    /// picked at random
+    fn sample_lights(
+        hit_info: &HitInfo,
+        objects: &[&Object],
+        accelerator: Option<&dyn Accelerator>,
+        lights: &[Light],
+        falloff: Falloff,
+        unit_disc_iter: &mut DistIter<UnitSphere, XorShiftRng, [Scalar; 3]>,
+    ) -> Result<Color, RayTracingError> {
+        let shading_normal = hit_info.object.material.shading_normal(&hit_info.normal, hit_info.uv)?;
+        // like the bounces, the shadow rays can't hit the outward sphere they start from again
+        let ignored_object = if hit_info.object.shape.is_concave() { None } else { Some(hit_info.object) };
+        let mut direct_light = color::BLACK;
+        for light in lights {
+            let (direction, distance) = match light {
+                Light::Point { position, .. } => {
+                    let to_light = Vector::new_from_points(&hit_info.point_hit, position);
+                    let distance = to_light.norme_vec();
+                    (to_light, distance)
+                }
+                Light::Area { center, radius, .. } => {
+                    let [x, y, z] = unit_disc_iter.next().ok_or(RayTracingError::IteratorDepleted())?;
+                    let surface_point = center + &(*radius * &Vector::new_from_coordinates(x, y, z));
+                    let to_light = Vector::new_from_points(&hit_info.point_hit, &surface_point);
+                    let distance = to_light.norme_vec();
+                    (to_light, distance)
+                }
+                Light::Directional { direction, .. } => (-direction, Scalar::INFINITY),
+            };
+            // * the point is inside of an area light or right on a point light
+            if distance <= 0. || lambert_cosine(&hit_info.normal, &direction)? == 0. {
+                continue;
+            }
+            let illumination = light.illumination_at(&hit_info.point_hit, &shading_normal, falloff)?;
+            if illumination.is_black() {
+                continue;
+            }
+            let shadow_ray = Ray::spawn(&hit_info.point_hit, direction, &hit_info.normal);
+            let is_shadowed = first_visible_hit(&shadow_ray, objects, accelerator, ignored_object, RayKind::Shadow)?
+                .is_some_and(|shadow_hit| shadow_hit.hit_distance < distance);
+            if !is_shadowed {
+                direct_light = &direct_light + &illumination;
+            }
+        }
+        Ok(direct_light)
+    }
+
+    /// Makes a ray bounce around the scene, returns the light it brings back and the number of objects it hit,
+    /// every hit is also pushed to `path` if one is given
+    pub(crate) fn trace_ray<'a>(
+        mut ray: Ray,
+        bounce_limit: BounceLimit,
+        objects: &[&'a Object],
+        settings: &TraceSettings<'a>,
+        unit_disc_iter: &mut DistIter<UnitSphere, XorShiftRng, [Scalar; 3]>,
+        mut path: Option<&mut Vec<HitInfo<'a>>>,
+    ) -> Result<(Color, u64), RayTracingError> {
+        // * the colors are only turned back to RGB once the ray is done
+        let mut ray_color = settings.color_mode.path_color(&color::WHITE);
+        let mut ray_light = settings.color_mode.path_color(&color::BLACK);
+        let mut number_of_hits = 0;
+        // make the vector bounce around the scene on objects
+        // we get a color if we hit a light source, or else we get the background color
+        let mut last_hit_sphere = None;
+        // * the emitters already sampled at the previous hit don't add their light again when the bounce hits them
+        let mut emitters_were_sampled = false;
+        for bounce_index in 0..=bounce_limit.max_bounces() {
+            // the first ray comes from the eye, the others bounced off the previous hit
+            let ray_kind = if bounce_index == 0 { RayKind::Camera } else { RayKind::Reflection };
+            let hit_info = match first_visible_hit(&ray, objects, settings.accelerator, last_hit_sphere, ray_kind)? {
+                Some(point) => point,
+                None => {
+                    // only a ray escaping right from the eye sees the background, a bounced ray keeps the light it gathered
+                    if number_of_hits == 0 {
+                        ray_light = settings.color_mode.path_color(&self::get_background_color()?);
+                    }
+                    break;
+                }
+            };
+            number_of_hits += 1;
+            if let Some(path) = path.as_mut() {
+                path.push(hit_info);
+            }
+
+            // a ray can hit a concave shape again right after bouncing on it, like the inside of an inward sphere,
+            // and a ray refracted into an object leaves it through its other side
+            last_hit_sphere = if hit_info.object.shape.is_concave() || hit_info.object.material.ior().is_some() {
+                None
+            } else {
+                Some(hit_info.object)
+            };
+            // the light emitted towards the viewer goes back along the ray
+            let light_emitted_by_hit_object = &hit_info.object.material.emission_color
+                * hit_info
+                    .object
+                    .material
+                    .emission_strength_towards(&-ray.direction())?;
+            let hit_is_sampled_emitter = emitters_were_sampled
+                && settings.emitters.iter().any(|emitter| *emitter.shape == *hit_info.object.shape);
+            if !hit_is_sampled_emitter {
+                ray_light = &ray_light + &ray_color.filter(&light_emitted_by_hit_object);
+            }
+            // the ambient light is a cheap stand-in for the light the path tracer would take many bounces to find
+            if let Some(ambient) = settings.ambient {
+                let ambient_light = hit_info.object.material.ambient_light(&ambient);
+                ray_light = &ray_light + &ray_color.filter(&ambient_light);
+            }
+            // the direct light of the emitters and of the lights is reflected by the diffuse surface towards the ray
+            let albedo = &color::WHITE * &hit_info.object.material.diffusion_coefficients;
+            // only when the ray can bounce again, so that the emitters bring the same light as the bounce would
+            emitters_were_sampled = !settings.emitters.is_empty()
+                && hit_info.object.material.is_purely_diffuse()
+                && bounce_index < bounce_limit.max_bounces();
+            if emitters_were_sampled {
+                let direct_light = Grid::sample_emitters(
+                    &hit_info,
+                    objects,
+                    settings.accelerator,
+                    settings.emitters,
+                    unit_disc_iter,
+                )?;
+                ray_light = &ray_light + &ray_color.filter(&albedo).filter(&direct_light);
+            }
+            // * the rays never hit the lights, they are added at every diffuse hit without counting them twice
+            if !settings.lights.is_empty() && hit_info.object.material.is_purely_diffuse() {
+                let direct_light = Grid::sample_lights(
+                    &hit_info,
+                    objects,
+                    settings.accelerator,
+                    settings.lights,
+                    settings.light_falloff,
+                    unit_disc_iter,
+                )?;
+                ray_light = &ray_light + &ray_color.filter(&albedo).filter(&direct_light);
+            }
+            let shading_context = ShadingContext::new(&hit_info, &ray)?;
+            match hit_info
+                .object
+                .material
+                .scatter(&shading_context, settings.sampler, unit_disc_iter)?
+            {
+                Some((scattered_ray, attenuation)) => {
+                    ray = scattered_ray;
+                    ray_color = ray_color.filter(&attenuation);
+                }
+                // the material absorbs everything, no more light can come back
+                None => break,
+            }
+            log::trace!("hit info : {:?}", hit_info);
+            log::trace!("ray after bounce : {:?}", ray);
+            log::trace!(
+                "ligth emitted by hit object : {:?}",
+                light_emitted_by_hit_object
+            );
+            log::trace!("ray light : {:?}", ray_light);
+            log::trace!("ray color : {:?}", ray_color);
+            // once the ray color is too dark, subsequent bounces won't bring back any light, meaning we can exit early
+            if bounce_limit.ray_is_exhausted(&ray_color.to_rgb()) {
+                break;
+            }
+        }
+        Ok((ray_light.to_rgb(), number_of_hits))
     }
 
+    /// Returns the average light brought back by the primary rays of the pixel, and the part of them that hit an object
     fn trace_pixel_color(
+        &self,
         pixel_height_index: usize,
         pixel_width_index: usize,
         number_of_points_per_pixel: usize,
-        number_of_bounces: u64,
-        objects: &Vec<&Object>,
-        unit_disc_iter: &mut DistIter<UnitSphere, XorShiftRng, [f64; 3]>,
-    ) -> Result<Color, RayTracingError> {
-        let vector_eye_pixel = Grid::ray_eye_pixel_point(
+        bounce_limit: BounceLimit,
+        objects: &[&Object],
+        unit_disc_iter: &mut DistIter<UnitSphere, XorShiftRng, [Scalar; 3]>,
+    ) -> Result<(Color, f32), RayTracingError> {
+        let primary_rays = self.ray_eye_pixel_point(
             pixel_width_index,
             pixel_height_index,
             number_of_points_per_pixel,
+            unit_disc_iter,
         )?;
+        // the sums are normalized by the total weight and not the number of rays, so that other filters than the box don't darken the image
         let mut total_ray_light = color::BLACK;
-        let ray_has_hit = false;
-        for vector in vector_eye_pixel {
-            let mut ray_color = color::WHITE;
-            let mut ray_light = color::BLACK;
-            // make the vector bounce around the scene on objects
-            // we get a color if we hit a light source, or else we get the background color
-            let mut ray = Ray {
-                origin: EYE_POINT,
-                direction: vector,
-            };
-            let mut last_hit_sphere = None;
-            for _ in 0..=number_of_bounces {
-                let hit_info = match ray.first_point_hit_by_ray(objects, last_hit_sphere)? {
-                    Some(point) => point,
-                    None => {
-                        if ray_has_hit {
-                            break;
-                        } else {
-                            ray_light = self::get_background_color()?;
-                            break;
-                        }
+        let mut total_weight = 0.;
+        let mut covering_weight = 0.;
+        for (ray, weight) in primary_rays {
+            let (ray_light, number_of_hits) = Grid::trace_ray(
+                ray,
+                bounce_limit,
+                objects,
+                &self.trace_settings(),
+                unit_disc_iter,
+                None,
+            )?;
+            total_weight += weight;
+            if number_of_hits > 0 {
+                covering_weight += weight;
+            } else if self.transparent_background {
+                // the background is left out, the color of the pixel only comes from the objects
+                continue;
+            }
+            total_ray_light = &total_ray_light + &(&ray_light * weight);
+        }
+        // * without any weight there is no mean, the pixel is left to the background like a pixel with no budget
+        if total_weight == 0. {
+            return Ok((get_background_color()?, 0.));
+        }
+        let coverage = (covering_weight / total_weight) as f32;
+        // * the mean is kept linear and unbounded, a pixel brighter than 1 is left to the tone mapping of the export
+        let pixel_color = &total_ray_light * (1. / total_weight);
+        Ok((pixel_color, coverage))
+    }
+
+    /// Shoots primary rays through the four corners of the pixel and checks whether they all hit the same object
+    /// corners are shared with the neighboring pixels, so a silhouette can't go between the samples of two pixels
+    fn pixel_is_on_edge(
+        &self,
+        pixel_height_index: usize,
+        pixel_width_index: usize,
+        objects: &[&Object],
+    ) -> Result<bool, RayTracingError> {
+        let (pixel_center_point, half_right_step, half_down_step) =
+            self.pixel_center_and_half_steps(pixel_width_index, pixel_height_index)?;
+        let mut first_hit_object: Option<Option<&Object>> = None;
+        for (right_offset, down_offset) in [(-1., -1.), (1., -1.), (-1., 1.), (1., 1.)] {
+            let sample_point = &(&pixel_center_point + &(right_offset * &half_right_step))
+                + &(down_offset * &half_down_step);
+            let ray = self.camera.primary_ray(&sample_point)?;
+            let hit_object = first_visible_hit(&ray, objects, self.accelerator.as_deref(), None, RayKind::Camera)?
+                .map(|hit_info| hit_info.object);
+            match first_hit_object {
+                None => first_hit_object = Some(hit_object),
+                Some(first_object) => {
+                    let same_object = match (first_object, hit_object) {
+                        (Some(first_object), Some(object)) => std::ptr::eq(first_object, object),
+                        (None, None) => true,
+                        _ => false,
+                    };
+                    if !same_object {
+                        return Ok(true);
                     }
-                };
-                
-                last_hit_sphere = Some(hit_info.object);
-                // make the ray bounce on the hit object randomly, cos weighted to take into account the Lambert reflectance law
-                ray = Ray::cos_weighted_random_ray_unit_sphere(
-                    &hit_info.point_hit,
-                    &hit_info.normal,
-                    unit_disc_iter,
-                )?;
-                let light_emitted_by_hit_object = &hit_info.object.material.emission_color
-                    * hit_info.object.material.emission_strength();
-                ray_light = &ray_light + &(&light_emitted_by_hit_object * &ray_color);
-                ray_color = &ray_color * &hit_info.object.material.diffusion_coefficients;
-                #[cfg(test)]
-                {
-                    println!("hit info : {:?}", hit_info);
-                    println!("ray after bounce : {:?}", ray);
-                    println!(
-                        "ligth emitted by hit object : {:?}",
-                        light_emitted_by_hit_object
-                    );
-                    println!("ray light : {:?}", ray_light);
-                    println!("ray color : {:?}", ray_color);
-                }
-                // if the object hit is black, all subsequent bounces of the ray will be black, meaning we can exit early
-                if ray_color == color::BLACK {
-                    break;
                 }
             }
-            total_ray_light = &total_ray_light + &ray_light;
         }
-        (&total_ray_light * (1. / number_of_points_per_pixel as f64)).new_from_color()
+        Ok(false)
+    }
+
+    /// Colors and coverages of the pixels of a row, each row has its own random stream seeded from its index
+    /// so that the rows give the same result whatever order they are rendered in
+    fn render_row(
+        &self,
+        pixel_height_index: usize,
+        number_of_points_per_pixel: usize,
+        bounce_limit: BounceLimit,
+        objects: &[&Object],
+        seed: u64,
+    ) -> Result<(Vec<Color>, Vec<f32>), RayTracingError> {
+        let rng = XorShiftRng::seed_from_u64(seed.wrapping_add(pixel_height_index as u64));
+        let mut unit_disc_iter: DistIter<UnitSphere, XorShiftRng, [Scalar; 3]> =
+            UnitSphere.sample_iter(rng);
+        let mut row_colors = Vec::with_capacity(self.width);
+        let mut row_coverage = Vec::with_capacity(self.width);
+        for pixel_width_index in 0..self.width {
+            if self.render_mode == RenderMode::Edges {
+                let is_on_edge =
+                    self.pixel_is_on_edge(pixel_height_index, pixel_width_index, objects)?;
+                row_colors.push(if is_on_edge { color::WHITE } else { color::BLACK });
+                row_coverage.push(1.);
+                continue;
+            }
+            let (pixel_color, pixel_coverage) = self
+                .trace_pixel_color(
+                    pixel_height_index,
+                    pixel_width_index,
+                    number_of_points_per_pixel,
+                    bounce_limit,
+                    objects,
+                    &mut unit_disc_iter,
+                )
+                .map_err(|error| {
+                    error.context(&format!(
+                        "tracing pixel (height {}, width {})",
+                        pixel_height_index, pixel_width_index
+                    ))
+                })?;
+            row_colors.push(pixel_color);
+            row_coverage.push(pixel_coverage);
+        }
+        Ok((row_colors, row_coverage))
     }
 
+    /// Renders every pixel of the grid, rows are rendered in parallel with the `parallel` feature, with the same result
+    /// the new rays are averaged with the ones the pixels already had, so that a render can be continued,
+    /// except in the edges mode where the pixels are drawn again
     pub fn make_image(
         &mut self,
         number_of_points_per_pixel: usize,
-        number_of_bounces: u64,
-        objects: &Vec<&Object>,
+        bounce_limit: BounceLimit,
+        objects: &[&Object],
     ) -> Result<(), RayTracingError> {
-        let seed: u64 = 51468412518;
-        let rng = XorShiftRng::seed_from_u64(seed);
-        let mut unit_disc_iter: DistIter<UnitSphere, XorShiftRng, [f64; 3]> =
-            UnitSphere.sample_iter(rng);
+        // * no new ray to average, the pixels keep what they had, the edges don't depend on the number of rays
+        if number_of_points_per_pixel == 0 && self.render_mode != RenderMode::Edges {
+            return Ok(());
+        }
+        // * the random streams depend on the rays already traced, each call adds new samples instead of the same ones again
+        let previous_samples: usize = self.samples.iter().flatten().sum();
+        let seed = RENDER_SEED.wrapping_add((previous_samples as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        let render_row = |pixel_height_index| {
+            self.render_row(
+                pixel_height_index,
+                number_of_points_per_pixel,
+                bounce_limit,
+                objects,
+                seed,
+            )
+        };
+        #[cfg(feature = "parallel")]
+        let rows = {
+            use rayon::prelude::*;
+            (0..self.height)
+                .into_par_iter()
+                .map(render_row)
+                .collect::<Result<Vec<_>, RayTracingError>>()?
+        };
+        #[cfg(not(feature = "parallel"))]
+        let rows = (0..self.height)
+            .map(render_row)
+            .collect::<Result<Vec<_>, RayTracingError>>()?;
+        for (pixel_height_index, (row_colors, row_coverage)) in rows.into_iter().enumerate() {
+            if self.render_mode == RenderMode::Edges {
+                self.colors[pixel_height_index] = row_colors;
+                self.coverage[pixel_height_index] = row_coverage;
+                self.samples[pixel_height_index] = vec![0; self.width];
+                continue;
+            }
+            for (pixel_width_index, (new_color, new_coverage)) in row_colors.into_iter().zip(row_coverage).enumerate() {
+                self.accumulate_pixel(
+                    pixel_height_index,
+                    pixel_width_index,
+                    &new_color,
+                    new_coverage,
+                    number_of_points_per_pixel,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Averages the color and coverage of `number_of_points_per_pixel` new rays with the ones the pixel already had,
+    /// each side weighted by its number of rays
+    fn accumulate_pixel(
+        &mut self,
+        pixel_height_index: usize,
+        pixel_width_index: usize,
+        new_color: &Color,
+        new_coverage: f32,
+        number_of_points_per_pixel: usize,
+    ) {
+        let samples = &mut self.samples[pixel_height_index][pixel_width_index];
+        let total_samples = *samples + number_of_points_per_pixel;
+        if total_samples == 0 {
+            return;
+        }
+        let previous_weight = *samples as Scalar / total_samples as Scalar;
+        let new_weight = number_of_points_per_pixel as Scalar / total_samples as Scalar;
+        let pixel_color = &mut self.colors[pixel_height_index][pixel_width_index];
+        *pixel_color = &(&*pixel_color * previous_weight) + &(new_color * new_weight);
+        let pixel_coverage = &mut self.coverage[pixel_height_index][pixel_width_index];
+        let blended_coverage = *pixel_coverage * previous_weight as f32 + new_coverage * new_weight as f32;
+        *pixel_coverage = blended_coverage;
+        *samples = total_samples;
+    }
+
+    /// Renders the image with `number_of_points_per_pixel` rays, then keeps doubling them with `make_image`
+    /// until the root mean square difference brought by a pass is below `rms_threshold`, or `max_passes` passes were made,
+    /// returns the passes made after the first render
+    pub fn make_image_until_converged(
+        &mut self,
+        number_of_points_per_pixel: usize,
+        bounce_limit: BounceLimit,
+        objects: &[&Object],
+        rms_threshold: f32,
+        max_passes: usize,
+    ) -> Result<Vec<ConvergencePass>, RayTracingError> {
+        self.make_image(number_of_points_per_pixel, bounce_limit, objects)?;
+        let mut total_points_per_pixel = number_of_points_per_pixel;
+        let mut passes = Vec::new();
+        while passes.len() < max_passes {
+            let previous_grid = self.clone();
+            // * as many new rays as there already are, which doubles the rays averaged in each pixel
+            self.make_image(total_points_per_pixel, bounce_limit, objects)?;
+            total_points_per_pixel *= 2;
+            let rms_difference = previous_grid.diff(self)?.rms_difference;
+            passes.push(ConvergencePass {
+                number_of_points_per_pixel: total_points_per_pixel,
+                rms_difference,
+            });
+            if rms_difference < rms_threshold {
+                break;
+            }
+        }
+        Ok(passes)
+    }
+
+    /// Renders half of the pixels in a checkerboard pattern, so that a preview of the whole frame comes at half the cost,
+    /// after the first pass the pixels left without any ray are copies of a rendered neighbor, the second pass renders them,
+    /// like `make_image` the new rays are averaged with the ones the pixels already had, except in the edges mode
+    pub fn make_image_checkerboard(
+        &mut self,
+        pass: CheckerboardPass,
+        number_of_points_per_pixel: usize,
+        bounce_limit: BounceLimit,
+        objects: &[&Object],
+        seed: u64,
+    ) -> Result<(), RayTracingError> {
+        // * the passes don't share their random streams
+        let pass_seed = match pass {
+            CheckerboardPass::First => seed,
+            CheckerboardPass::Second => seed.wrapping_add(self.height as u64),
+        };
         for pixel_height_index in 0..self.height {
+            let rng = XorShiftRng::seed_from_u64(pass_seed.wrapping_add(pixel_height_index as u64));
+            let mut unit_disc_iter: DistIter<UnitSphere, XorShiftRng, [Scalar; 3]> = UnitSphere.sample_iter(rng);
             for pixel_width_index in 0..self.width {
-                let pixel_color = Grid::trace_pixel_color(
+                if !pass.renders(pixel_height_index, pixel_width_index) {
+                    continue;
+                }
+                if self.render_mode == RenderMode::Edges {
+                    let is_on_edge = self.pixel_is_on_edge(pixel_height_index, pixel_width_index, objects)?;
+                    self.colors[pixel_height_index][pixel_width_index] =
+                        if is_on_edge { color::WHITE } else { color::BLACK };
+                    self.coverage[pixel_height_index][pixel_width_index] = 1.;
+                    self.samples[pixel_height_index][pixel_width_index] = 0;
+                    continue;
+                }
+                let (pixel_color, pixel_coverage) = self
+                    .trace_pixel_color(
+                        pixel_height_index,
+                        pixel_width_index,
+                        number_of_points_per_pixel,
+                        bounce_limit,
+                        objects,
+                        &mut unit_disc_iter,
+                    )
+                    .map_err(|error| {
+                        error.context(&format!(
+                            "tracing pixel (height {}, width {})",
+                            pixel_height_index, pixel_width_index
+                        ))
+                    })?;
+                self.accumulate_pixel(
                     pixel_height_index,
                     pixel_width_index,
+                    &pixel_color,
+                    pixel_coverage,
                     number_of_points_per_pixel,
-                    number_of_bounces,
-                    objects,
-                    &mut unit_disc_iter,
-                )?;
-                self.colors[pixel_height_index][pixel_width_index] = pixel_color;
+                );
             }
         }
+        if pass == CheckerboardPass::First {
+            self.fill_checkerboard_gaps();
+        }
         Ok(())
     }
 
-    pub fn export_image(self, path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
-        let mut image = RgbImage::new(self.width as u32, self.height as u32);
-        for (width_index, height_index, pixel) in image.enumerate_pixels_mut() {
-            let (r, g, b) = self.colors[height_index as usize][width_index as usize].into_rgb()?;
-            *pixel = Rgb([r, g, b])
+    /// Copies a rendered neighbor into each pixel left by the first checkerboard pass, the left one when there is one,
+    /// the pixels that already have rays from an earlier render keep them
+    fn fill_checkerboard_gaps(&mut self) {
+        for pixel_height_index in 0..self.height {
+            for pixel_width_index in 0..self.width {
+                if CheckerboardPass::First.renders(pixel_height_index, pixel_width_index)
+                    || self.samples[pixel_height_index][pixel_width_index] > 0
+                {
+                    continue;
+                }
+                // * the 4 neighbors of a gap are rendered, a gap always has one as the grid has at least 2 pixels
+                let neighbor = if pixel_width_index > 0 {
+                    (pixel_height_index, pixel_width_index - 1)
+                } else if pixel_width_index + 1 < self.width {
+                    (pixel_height_index, pixel_width_index + 1)
+                } else {
+                    (pixel_height_index - 1, pixel_width_index)
+                };
+                self.colors[pixel_height_index][pixel_width_index] = self.colors[neighbor.0][neighbor.1];
+                self.coverage[pixel_height_index][pixel_width_index] = self.coverage[neighbor.0][neighbor.1];
+                self.samples[pixel_height_index][pixel_width_index] = 0; // * a copy, no ray went through the pixel
+            }
         }
+    }
 
-        image.save(path)?;
+    /// Same as `make_image` but each pixel gets its own number of primary rays, `budget[height_index][width_index]`,
+    /// pixels with a budget of 0 are not traced and keep the background color
+    pub fn make_image_with_budget(
+        &mut self,
+        budget: &[Vec<usize>],
+        bounce_limit: BounceLimit,
+        objects: &[&Object],
+        seed: u64,
+    ) -> Result<(), RayTracingError> {
+        let budget_width = budget.first().map_or(0, |row| row.len());
+        if budget.len() != self.height || budget.iter().any(|row| row.len() != self.width) {
+            return Err(RayTracingError::GridDimensionsMismatch(
+                self.width,
+                self.height,
+                budget_width,
+                budget.len(),
+            ));
+        }
+        let background_color = get_background_color()?;
+        let rng = XorShiftRng::seed_from_u64(seed);
+        let mut unit_disc_iter: DistIter<UnitSphere, XorShiftRng, [Scalar; 3]> =
+            UnitSphere.sample_iter(rng);
+        for (pixel_height_index, budget_row) in budget.iter().enumerate() {
+            for (pixel_width_index, number_of_points_per_pixel) in budget_row.iter().enumerate() {
+                let (pixel_color, pixel_coverage) = if *number_of_points_per_pixel == 0 {
+                    (background_color, 0.)
+                } else {
+                    self.trace_pixel_color(
+                        pixel_height_index,
+                        pixel_width_index,
+                        *number_of_points_per_pixel,
+                        bounce_limit,
+                        objects,
+                        &mut unit_disc_iter,
+                    )
+                    .map_err(|error| {
+                        error.context(&format!(
+                            "tracing pixel (height {}, width {})",
+                            pixel_height_index, pixel_width_index
+                        ))
+                    })?
+                };
+                self.colors[pixel_height_index][pixel_width_index] = pixel_color;
+                self.coverage[pixel_height_index][pixel_width_index] = pixel_coverage;
+                self.samples[pixel_height_index][pixel_width_index] = *number_of_points_per_pixel;
+            }
+        }
         Ok(())
     }
-}
 
-impl Default for Grid {
-    fn default() -> Self {
-        Grid {
-            width: GRID_WIDTH,
-            height: GRID_HEIGHT,
-            colors: vec![vec![color::BLACK; GRID_WIDTH]; GRID_HEIGHT],
+    /// Darkens the pixels towards the corners, by a factor going from 1 at the center to 1 - `strength` at the corner pixels
+    /// with the square of the distance to the center, `strength` is between 0 (no effect) and 1 (black corners)
+    pub fn apply_vignette(&mut self, strength: Scalar) -> Result<(), RayTracingError> {
+        if !(0. ..=1.).contains(&strength) {
+            return Err(RayTracingError::CoefficientOOB(strength, 0., 1.));
         }
+        let (center_width, center_height) = (self.width as Scalar / 2., self.height as Scalar / 2.);
+        // distances are taken between pixel centers, in pixels
+        let squared_distance_to_center = |pixel_width_index: usize, pixel_height_index: usize| {
+            (pixel_width_index as Scalar + 0.5 - center_width).powi(2)
+                + (pixel_height_index as Scalar + 0.5 - center_height).powi(2)
+        };
+        let corner_squared_distance = squared_distance_to_center(0, 0);
+        if corner_squared_distance == 0. {
+            // a single pixel, there are no corners to darken
+            return Ok(());
+        }
+        for (pixel_height_index, row) in self.colors.iter_mut().enumerate() {
+            for (pixel_width_index, pixel_color) in row.iter_mut().enumerate() {
+                let factor = 1.
+                    - strength * squared_distance_to_center(pixel_width_index, pixel_height_index)
+                        / corner_squared_distance;
+                *pixel_color = &*pixel_color * factor;
+            }
+        }
+        Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use crate::{
-        error::RayTracingError,
-        geometry::shape::Sphere,
-        optic::{color, material::Material}, 
-    };
+    /// Draws the 12 edges of the box as one pixel wide lines of `color` as seen by `camera`,
+    /// edges going behind the eye are left out
+    pub fn draw_aabb(&mut self, bb: &Aabb, camera: &Camera, color: Color) -> Result<(), RayTracingError> {
+        for (start, end) in bb.edges() {
+            let projected_start = camera.project_to_pixel(&start, self.width, self.height)?;
+            let projected_end = camera.project_to_pixel(&end, self.width, self.height)?;
+            if let (Some(projected_start), Some(projected_end)) = (projected_start, projected_end) {
+                self.draw_line(projected_start, projected_end, color);
+            }
+        }
+        Ok(())
+    }
 
-    use super::*;
+    /// Bresenham line between two points given as fractional pixel indices (width index then height index),
+    /// the line is first clipped to the image so that points projected far away don't make it walk over pixels that aren't drawn
+    fn draw_line(&mut self, start: (Scalar, Scalar), end: (Scalar, Scalar), color: Color) {
+        let Some(((start_width, start_height), (end_width, end_height))) =
+            clip_segment(start, end, self.width as Scalar, self.height as Scalar)
+        else {
+            return;
+        };
+        let (mut width_index, mut height_index) = (start_width.round() as i64, start_height.round() as i64);
+        let (end_width_index, end_height_index) = (end_width.round() as i64, end_height.round() as i64);
+        let width_distance = (end_width_index - width_index).abs();
+        let height_distance = -(end_height_index - height_index).abs();
+        let width_step = if width_index < end_width_index { 1 } else { -1 };
+        let height_step = if height_index < end_height_index { 1 } else { -1 };
+        let mut error = width_distance + height_distance;
+        loop {
+            if (0..self.width as i64).contains(&width_index) && (0..self.height as i64).contains(&height_index) {
+                self.colors[height_index as usize][width_index as usize] = color;
+            }
+            if width_index == end_width_index && height_index == end_height_index {
+                break;
+            }
+            let doubled_error = 2 * error;
+            if doubled_error >= height_distance {
+                error += height_distance;
+                width_index += width_step;
+            }
+            if doubled_error <= width_distance {
+                error += width_distance;
+                height_index += height_step;
+            }
+        }
+    }
 
-    #[test]
-    fn test_pixel_point_selection() {
-        let pixel_center_point = Grid::pixel_point_selection(GRID_WIDTH / 2, GRID_HEIGHT / 2, 1);
-        let expected_point = Point::new(PIXEL_SIZE / 2., PIXEL_SIZE / 2., 0.);
-        let pixel_center_point_2 =
-            Grid::pixel_point_selection(GRID_WIDTH / 2 + 20, GRID_HEIGHT / 2 - 25, 1);
-        let expected_point_2 = Point::new((0.5 + 20.) * PIXEL_SIZE, (0.5 - 25.) * PIXEL_SIZE, 0.);
+    /// Multiplier bringing the log-average luminance of the image to the middle gray `key` (see `DEFAULT_EXPOSURE_KEY`),
+    /// the colors are to be multiplied by it before tone mapping
+    pub fn auto_exposure(&self, key: Scalar) -> Scalar {
+        let number_of_pixels = self.width * self.height;
+        if number_of_pixels == 0 {
+            return 1.;
+        }
+        let log_luminance_sum: Scalar = self
+            .colors
+            .iter()
+            .flatten()
+            .map(|pixel_color| (LOG_AVERAGE_DELTA + pixel_color.luminance() as Scalar).ln())
+            .sum();
+        let log_average_luminance = (log_luminance_sum / number_of_pixels as Scalar).exp();
+        key / log_average_luminance
+    }
+
+    /// Number of pixels in each of `bins` equal ranges of linear luminance going from 0 to the brightest pixel,
+    /// the NaN and infinite pixels are left out and the negative ones counted as 0
+    pub fn luminance_histogram(&self, bins: usize) -> Vec<u64> {
+        let mut histogram = vec![0; bins];
+        if bins == 0 {
+            return histogram;
+        }
+        let luminances: Vec<f32> = self
+            .colors
+            .iter()
+            .flatten()
+            .filter(|pixel_color| pixel_color.is_finite())
+            .map(|pixel_color| pixel_color.luminance().max(0.))
+            .collect();
+        let max_luminance = luminances.iter().copied().fold(0., f32::max);
+        for luminance in luminances {
+            // * the brightest pixels go in the last bin, and every pixel in the first one for a black image
+            let bin = if max_luminance > 0. {
+                ((luminance / max_luminance * bins as f32) as usize).min(bins - 1)
+            } else {
+                0
+            };
+            histogram[bin] += 1;
+        }
+        histogram
+    }
 
-        assert_eq!(pixel_center_point[0], expected_point);
-        assert_eq!(pixel_center_point_2[0], expected_point_2);
+    /// Luminance below which `percentile` percent of the finite pixels are, with the nearest rank method, 0 for an empty grid
+    pub fn percentile_luminance(&self, percentile: Scalar) -> Scalar {
+        let mut luminances: Vec<f32> = self
+            .colors
+            .iter()
+            .flatten()
+            .filter(|pixel_color| pixel_color.is_finite())
+            .map(|pixel_color| pixel_color.luminance())
+            .collect();
+        if luminances.is_empty() {
+            return 0.;
+        }
+        luminances.sort_by(f32::total_cmp);
+        let rank = (percentile.clamp(0., 100.) / 100. * luminances.len() as Scalar).ceil() as usize;
+        luminances[rank.saturating_sub(1).min(luminances.len() - 1)] as Scalar
     }
 
-    #[test]
-    fn test_ray_eye_pixel_point() -> Result<(), RayTracingError> {
-        let unit_vector_list =
-            Grid::ray_eye_pixel_point(GRID_WIDTH / 2 + 20, GRID_HEIGHT / 2 - 25, 1)?;
+    /// Replaces every NaN or infinite pixel with the average of its valid neighbors (background if there is none)
+    /// so that a single bad sample doesn't leave a speck in the exported image, returns the number of pixels replaced
+    pub fn scrub(&mut self) -> Result<usize, RayTracingError> {
+        let mut scrubbed_pixels = vec![];
+        for (pixel_height_index, row) in self.colors.iter().enumerate() {
+            for (pixel_width_index, pixel_color) in row.iter().enumerate() {
+                if pixel_color.is_finite() {
+                    continue;
+                }
+                let mut neighbors_sum = color::BLACK;
+                let mut valid_neighbors = 0;
+                for neighbor_height_index in
+                    pixel_height_index.saturating_sub(1)..=(pixel_height_index + 1).min(self.height - 1)
+                {
+                    for neighbor_width_index in pixel_width_index.saturating_sub(1)
+                        ..=(pixel_width_index + 1).min(self.width - 1)
+                    {
+                        let neighbor_color = &self.colors[neighbor_height_index][neighbor_width_index];
+                        // the pixel itself is not finite so it is skipped here too
+                        if neighbor_color.is_finite() {
+                            neighbors_sum = &neighbors_sum + neighbor_color;
+                            valid_neighbors += 1;
+                        }
+                    }
+                }
+                let scrubbed_color = if valid_neighbors == 0 {
+                    get_background_color()?
+                } else {
+                    &neighbors_sum * (1. / valid_neighbors as Scalar)
+                };
+                scrubbed_pixels.push((pixel_height_index, pixel_width_index, scrubbed_color));
+            }
+        }
+        // colors are replaced after the pass so that a scrubbed pixel is never used as the neighbor of another one
+        for (pixel_height_index, pixel_width_index, scrubbed_color) in &scrubbed_pixels {
+            self.colors[*pixel_height_index][*pixel_width_index] = *scrubbed_color;
+        }
+        Ok(scrubbed_pixels.len())
+    }
 
-        let expected_point =
-            Grid::pixel_point_selection(GRID_WIDTH / 2 + 20, GRID_HEIGHT / 2 - 25, 1)[0];
-        let expected_unit_vector = Vector::new_from_points(&EYE_POINT, &expected_point);
+    pub fn diff(&self, other: &Grid) -> Result<DiffReport, RayTracingError> {
+        if self.width != other.width || self.height != other.height {
+            return Err(RayTracingError::GridDimensionsMismatch(
+                self.width,
+                self.height,
+                other.width,
+                other.height,
+            ));
+        }
+        let mut max_difference = (0_f32, 0_f32, 0_f32);
+        let mut total_difference: (Scalar, Scalar, Scalar) = (0., 0., 0.);
+        let mut number_of_differing_pixels = 0;
+        let mut squared_difference_sum: Scalar = 0.;
+        for (color, other_color) in self.colors.iter().flatten().zip(other.colors.iter().flatten()) {
+            let (r, g, b) = color.get_components();
+            let (other_r, other_g, other_b) = other_color.get_components();
+            let difference = ((r - other_r).abs(), (g - other_g).abs(), (b - other_b).abs());
+            max_difference = (
+                max_difference.0.max(difference.0),
+                max_difference.1.max(difference.1),
+                max_difference.2.max(difference.2),
+            );
+            total_difference = (
+                total_difference.0 + difference.0 as Scalar,
+                total_difference.1 + difference.1 as Scalar,
+                total_difference.2 + difference.2 as Scalar,
+            );
+            squared_difference_sum += [difference.0, difference.1, difference.2]
+                .iter()
+                .map(|channel_difference| (*channel_difference as Scalar).powi(2))
+                .sum::<Scalar>();
+            if difference.0 > DIFF_TOLERANCE
+                || difference.1 > DIFF_TOLERANCE
+                || difference.2 > DIFF_TOLERANCE
+            {
+                number_of_differing_pixels += 1;
+            }
+        }
+        let number_of_pixels = (self.width * self.height) as Scalar;
+        Ok(DiffReport {
+            max_difference,
+            mean_difference: (
+                (total_difference.0 / number_of_pixels) as f32,
+                (total_difference.1 / number_of_pixels) as f32,
+                (total_difference.2 / number_of_pixels) as f32,
+            ),
+            number_of_differing_pixels,
+            rms_difference: (squared_difference_sum / (3. * number_of_pixels)).sqrt() as f32,
+        })
+    }
 
-        assert_eq!(unit_vector_list[0], expected_unit_vector);
+    /// Writes the 8 bit image, in the format given by the extension of `path`, a PNG is written in a single tile
+    /// of `export_tiled` so both give the same file
+    pub fn export_image(self, path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+        if path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("png")) {
+            return self.export_tiled(path, self.height);
+        }
+        if self.transparent_background {
+            return self.export_image_with_alpha(path);
+        }
+        let mut image = RgbImage::new(self.width as u32, self.height as u32);
+        for (width_index, height_index, pixel) in image.enumerate_pixels_mut() {
+            let (r, g, b) = self.display_color(&self.colors[height_index as usize][width_index as usize])?;
+            *pixel = Rgb([r, g, b])
+        }
 
+        image.save(path)?;
         Ok(())
     }
 
-    #[test]
-    fn test_trace_pixel_color() -> Result<(), RayTracingError> {
-        // * Define RNG
-        let seed: u64 = 25;
+    /// 8 bit value written for the linear color of a pixel
+    fn display_color(&self, pixel_color: &Color) -> Result<(u8, u8, u8), RayTracingError> {
+        let (exposure, gamma) = (self.exposure as f32, self.gamma as f32);
+        let exposed_color = tonemap::tone_map_color(pixel_color, |channel| {
+            self.tone_map.apply(tonemap::exposure(channel, exposure))
+        });
+        tonemap::tone_map_color(&exposed_color.map_gamut(self.gamut_mapping), |channel| {
+            tonemap::gamma(channel, gamma)
+        })
+        .into_rgb()
+    }
+
+    fn export_image_with_alpha(self, path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+        let mut image = RgbaImage::new(self.width as u32, self.height as u32);
+        for (width_index, height_index, pixel) in image.enumerate_pixels_mut() {
+            *pixel = Rgba(self.display_color_with_alpha(width_index as usize, height_index as usize)?)
+        }
+
+        image.save(path)?;
+        Ok(())
+    }
+
+    fn display_color_with_alpha(&self, width_index: usize, height_index: usize) -> Result<[u8; 4], RayTracingError> {
+        let coverage = self.coverage[height_index][width_index];
+        let pixel_color = &self.colors[height_index][width_index];
+        // the colors only have the light of the covering rays, they are divided by the coverage to get straight alpha
+        let (r, g, b) = if coverage > 0. {
+            // float errors could make the division go slightly above 1
+            self.display_color(&(pixel_color * (1. / coverage as Scalar)))?
+        } else {
+            (0, 0, 0)
+        };
+        Ok([r, g, b, color::to_8_bits(coverage)])
+    }
+
+    /// Same PNG file as `export_image`, but the 8 bit pixels are made `tile_height` rows at a time and streamed to the png
+    /// encoder instead of filling a whole image first
+    pub fn export_tiled(&self, path: &PathBuf, tile_height: usize) -> Result<(), Box<dyn std::error::Error>> {
+        let (bytes_per_pixel, tile_height) = (self.bytes_per_pixel(), tile_height.max(1));
+        let row_length = self.width * bytes_per_pixel;
+        let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), self.width as u32, self.height as u32);
+        encoder.set_color(if self.transparent_background { png::ColorType::Rgba } else { png::ColorType::Rgb });
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_compression(png::Compression::Fast);
+        encoder.set_adaptive_filter(png::AdaptiveFilterType::Adaptive);
+        let mut writer = encoder.write_header()?;
+        let mut stream_writer = writer.stream_writer()?;
+
+        let mut tile = vec![0; tile_height * row_length];
+        for tile_start in (0..self.height).step_by(tile_height) {
+            let tile_rows = (self.height - tile_start).min(tile_height);
+            for (row_index, row) in tile.chunks_mut(row_length).take(tile_rows).enumerate() {
+                for (width_index, pixel) in row.chunks_mut(bytes_per_pixel).enumerate() {
+                    if self.transparent_background {
+                        pixel.copy_from_slice(&self.display_color_with_alpha(width_index, tile_start + row_index)?);
+                    } else {
+                        let (r, g, b) = self.display_color(&self.colors[tile_start + row_index][width_index])?;
+                        pixel.copy_from_slice(&[r, g, b]);
+                    }
+                }
+            }
+            stream_writer.write_all(&tile[..tile_rows * row_length])?;
+        }
+        stream_writer.finish()?;
+        writer.finish()?;
+        Ok(())
+    }
+
+    fn bytes_per_pixel(&self) -> usize {
+        if self.transparent_background {
+            4
+        } else {
+            3
+        }
+    }
+
+    /// Writes the unclamped colors as little endian f32 triplets, row by row from the top of the image,
+    /// the dimensions are written next to it in a `.hdr.txt` file
+    pub fn export_raw_f32(&self, path: &PathBuf) -> Result<(), RayTracingError> {
+        let mut bytes = Vec::with_capacity(self.width * self.height * 3 * std::mem::size_of::<f32>());
+        for color in self.colors.iter().flatten() {
+            let (r, g, b) = color.get_components();
+            for channel in [r, g, b] {
+                bytes.extend_from_slice(&channel.to_le_bytes());
+            }
+        }
+        std::fs::write(path, bytes)?;
+
+        let mut header_path = path.clone().into_os_string();
+        header_path.push(".hdr.txt");
+        std::fs::write(
+            header_path,
+            format!(
+                "width {}\nheight {}\nchannels 3 (rgb)\nformat f32 little endian, rows from top to bottom\n",
+                self.width, self.height
+            ),
+        )?;
+        Ok(())
+    }
+
+    /// Writes what is needed to continue the render later with `load_state`: the dimensions, then for each pixel
+    /// its unclamped color, coverage and number of samples, as little endian numbers, row by row from the top
+    pub fn save_state(&self, path: &PathBuf) -> Result<(), RayTracingError> {
+        let mut bytes =
+            Vec::with_capacity(RENDER_STATE_MAGIC.len() + 16 + self.width * self.height * RENDER_STATE_PIXEL_SIZE);
+        bytes.extend_from_slice(&RENDER_STATE_MAGIC);
+        bytes.extend_from_slice(&(self.width as u64).to_le_bytes());
+        bytes.extend_from_slice(&(self.height as u64).to_le_bytes());
+        for pixel_height_index in 0..self.height {
+            for pixel_width_index in 0..self.width {
+                let (r, g, b) = self.colors[pixel_height_index][pixel_width_index].get_components();
+                for value in [r, g, b, self.coverage[pixel_height_index][pixel_width_index]] {
+                    bytes.extend_from_slice(&value.to_le_bytes());
+                }
+                bytes.extend_from_slice(&(self.samples[pixel_height_index][pixel_width_index] as u64).to_le_bytes());
+            }
+        }
+        std::fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    /// Grid saved by `save_state`, its render settings are the ones of `Grid::new` and should be set again before resuming
+    pub fn load_state(path: &PathBuf) -> Result<Self, RayTracingError> {
+        let bytes = std::fs::read(path)?;
+        if !bytes.starts_with(&RENDER_STATE_MAGIC) {
+            return Err(RayTracingError::InvalidRenderState(
+                "not a render state file, or written by another version".to_string(),
+            ));
+        }
+        let mut position = RENDER_STATE_MAGIC.len();
+        let mut next_bytes = |length: usize| -> Result<&[u8], RayTracingError> {
+            let read = bytes
+                .get(position..position + length)
+                .ok_or_else(|| RayTracingError::InvalidRenderState("the file is truncated".to_string()))?;
+            position += length;
+            Ok(read)
+        };
+        let mut next_u64 = || -> Result<u64, RayTracingError> {
+            Ok(u64::from_le_bytes(next_bytes(8)?.try_into().expect("8 bytes were read")))
+        };
+        let width = next_u64()? as usize;
+        let height = next_u64()? as usize;
+        // * the size is checked before the grid is made, a damaged header could ask for any amount of memory
+        let expected_length = width
+            .checked_mul(height)
+            .and_then(|pixels| pixels.checked_mul(RENDER_STATE_PIXEL_SIZE))
+            .and_then(|pixels_length| pixels_length.checked_add(RENDER_STATE_MAGIC.len() + 16));
+        if expected_length != Some(bytes.len()) {
+            return Err(RayTracingError::InvalidRenderState(format!(
+                "a {}x{} grid doesn't fit in {} bytes",
+                width,
+                height,
+                bytes.len()
+            )));
+        }
+        let mut grid = Grid::new(width, height)?;
+        for pixel_height_index in 0..height {
+            for pixel_width_index in 0..width {
+                let mut channels = [0.; 4];
+                for channel in &mut channels {
+                    *channel = f32::from_le_bytes(next_bytes(4)?.try_into().expect("4 bytes were read"));
+                }
+                let [r, g, b, coverage] = channels;
+                // * the colors aren't checked, they can go above 1 before the export
+                grid.colors[pixel_height_index][pixel_width_index] = Color::new_unbounded(r, g, b);
+                grid.coverage[pixel_height_index][pixel_width_index] = coverage;
+                grid.samples[pixel_height_index][pixel_width_index] =
+                    u64::from_le_bytes(next_bytes(8)?.try_into().expect("8 bytes were read")) as usize;
+            }
+        }
+        Ok(grid)
+    }
+}
+
+/// Part of the segment inside the image, pixel centers going from 0 to `width` - 1 (Liang-Barsky clipping)
+fn clip_segment(
+    start: (Scalar, Scalar),
+    end: (Scalar, Scalar),
+    width: Scalar,
+    height: Scalar,
+) -> Option<((Scalar, Scalar), (Scalar, Scalar))> {
+    let (width_delta, height_delta) = (end.0 - start.0, end.1 - start.1);
+    let (mut entering, mut leaving): (Scalar, Scalar) = (0., 1.);
+    for (delta, distance_to_border) in [
+        (-width_delta, start.0 + 0.5),
+        (width_delta, width - 0.5 - start.0),
+        (-height_delta, start.1 + 0.5),
+        (height_delta, height - 0.5 - start.1),
+    ] {
+        if delta == 0. {
+            if distance_to_border < 0. {
+                return None;
+            }
+        } else {
+            let crossing = distance_to_border / delta;
+            if delta < 0. {
+                entering = entering.max(crossing);
+            } else {
+                leaving = leaving.min(crossing);
+            }
+        }
+    }
+    if entering > leaving {
+        return None;
+    }
+    Some((
+        (start.0 + entering * width_delta, start.1 + entering * height_delta),
+        (start.0 + leaving * width_delta, start.1 + leaving * height_delta),
+    ))
+}
+
+impl Default for Grid {
+    fn default() -> Self {
+        Grid::with_camera(GRID_WIDTH, GRID_HEIGHT, Camera::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        error::RayTracingError,
+        geometry::{shape::{Plane, Sphere}, triangle::Triangle},
+        object::VisibilityFlags,
+        optic::{color::{self, DiffusionCoefficient}, material::Material, normal_map::NormalMap},
+    };
+
+    use super::*;
+
+    fn make_uniform_grid(width: usize, height: usize, color: Color) -> Grid {
+        Grid {
+            colors: vec![vec![color; width]; height],
+            ..Grid::with_camera(width, height, Camera::default())
+        }
+    }
+
+    #[test]
+    fn test_new() -> Result<(), RayTracingError> {
+        let grid = Grid::new(1280, 720)?;
+        let square_grid = Grid::new(64, 64)?;
+
+        assert_eq!((grid.width, grid.height), (1280, 720));
+        assert_eq!(grid.colors.len(), 720);
+        assert_eq!(grid.coverage[0].len(), 1280);
+        assert_eq!(square_grid.camera.aspect_ratio(), 1.);
+        assert_eq!(square_grid.camera.fov_degrees(), Camera::default().fov_degrees());
+        // * an empty grid has no aspect ratio
+        assert!(Grid::new(0, 720).is_err());
+        assert!(Grid::new(1280, 0).is_err());
+        // * with odd sides, the middle pixel is centered on the point the camera looks at
+        let mut odd_grid = Grid::new(5, 3)?;
+        let (middle_pixel_center, _, _) = odd_grid.pixel_center_and_half_steps(2, 1)?;
+        assert_eq!(middle_pixel_center, odd_grid.camera.look_at);
+        odd_grid.make_image(1, BounceLimit::Fixed(1), &[])?;
+        assert_eq!(odd_grid.colors[2][4], get_background_color()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pixel_point_selection() -> Result<(), RayTracingError> {
+        let mut unit_disc_iter: DistIter<UnitSphere, XorShiftRng, [Scalar; 3]> =
+            UnitSphere.sample_iter(XorShiftRng::seed_from_u64(1));
+        let grid = Grid::default();
+        let pixel_center_point =
+            grid.pixel_point_selection(GRID_WIDTH / 2, GRID_HEIGHT / 2, 1, &mut unit_disc_iter)?;
+        let expected_point = Point::new(PIXEL_SIZE / 2., PIXEL_SIZE / 2., 0.);
+        let pixel_center_point_2 =
+            grid.pixel_point_selection(GRID_WIDTH / 2 + 20, GRID_HEIGHT / 2 - 25, 1, &mut unit_disc_iter)?;
+        let expected_point_2 = Point::new((0.5 + 20.) * PIXEL_SIZE, (0.5 - 25.) * PIXEL_SIZE, 0.);
+
+        assert_eq!(pixel_center_point[0].0, expected_point);
+        assert_eq!(pixel_center_point_2[0].0, expected_point_2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pixel_point_selection_is_jittered() -> Result<(), RayTracingError> {
+        let mut unit_disc_iter: DistIter<UnitSphere, XorShiftRng, [Scalar; 3]> =
+            UnitSphere.sample_iter(XorShiftRng::seed_from_u64(1));
+        let grid = Grid::default();
+        // * pixel (0, 0) of the default grid covers [0, PIXEL_SIZE] on both axes of the image plane
+        let cell_of = |point: &Point| {
+            assert!((0. ..=PIXEL_SIZE).contains(&point.x) && (0. ..=PIXEL_SIZE).contains(&point.y));
+            assert_eq!(point.z, 0.);
+            ((point.x / PIXEL_SIZE * 4.) as usize, (point.y / PIXEL_SIZE * 4.) as usize)
+        };
+
+        let points = grid.pixel_point_selection(GRID_WIDTH / 2, GRID_HEIGHT / 2, 16, &mut unit_disc_iter)?;
+        let mut cells: Vec<(usize, usize)> = points.iter().map(|(point, _)| cell_of(point)).collect();
+        cells.sort();
+        cells.dedup();
+        // * one point in each cell of the 4x4 subdivision, not at the cell centers
+        assert_eq!(cells.len(), 16);
+        let cell_center = Point::new(PIXEL_SIZE / 8., PIXEL_SIZE / 8., 0.);
+        assert!(points.iter().all(|(point, _)| *point != cell_center));
+        let other_points = grid.pixel_point_selection(GRID_WIDTH / 2, GRID_HEIGHT / 2, 16, &mut unit_disc_iter)?;
+        assert!(other_points[0].0 != points[0].0);
+        // * the 5th point of 5 is anywhere in the pixel
+        let points = grid.pixel_point_selection(GRID_WIDTH / 2, GRID_HEIGHT / 2, 5, &mut unit_disc_iter)?;
+        assert_eq!(points.len(), 5);
+        points.iter().for_each(|(point, _)| {
+            cell_of(point);
+        });
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ray_eye_pixel_point() -> Result<(), RayTracingError> {
+        let mut unit_disc_iter: DistIter<UnitSphere, XorShiftRng, [Scalar; 3]> =
+            UnitSphere.sample_iter(XorShiftRng::seed_from_u64(1));
+        let grid = Grid::default();
+        let ray_list = grid.ray_eye_pixel_point(GRID_WIDTH / 2 + 20, GRID_HEIGHT / 2 - 25, 1, &mut unit_disc_iter)?;
+
+        let expected_point =
+            grid.pixel_point_selection(GRID_WIDTH / 2 + 20, GRID_HEIGHT / 2 - 25, 1, &mut unit_disc_iter)?[0].0;
+        let expected_unit_vector = Vector::new_from_points(&EYE_POINT, &expected_point);
+
+        assert_eq!(ray_list[0].0.direction(), expected_unit_vector);
+        assert_eq!(ray_list[0].0.origin, EYE_POINT);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_trace_pixel_color_on_silhouette() -> Result<(), RayTracingError> {
+        let rng = XorShiftRng::seed_from_u64(3);
+        let mut unit_disc_iter: DistIter<UnitSphere, XorShiftRng, [Scalar; 3]> = UnitSphere.sample_iter(rng);
+        // * the bounces off the glowing sphere escape the scene, they must not replace its light by the background
+        let glowing_sphere = Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(0., 0., 10.), 5.)),
+            material: Material::new(color::RED, 1., color::WHITE.to_diffusion_coefficient()?, 0.)?,
+            visibility: VisibilityFlags::default(),
+        };
+        let objects = vec![&glowing_sphere];
+        let grid = Grid::default();
+        let pixel_height_index = GRID_HEIGHT / 2;
+        let edge_pixel_width_index = (GRID_WIDTH / 2..GRID_WIDTH)
+            .find(|pixel_width_index| {
+                grid.pixel_is_on_edge(pixel_height_index, *pixel_width_index, &objects)
+                    .unwrap_or(false)
+            })
+            .expect("the sphere is smaller than the image");
+
+        let (sphere_color, sphere_coverage) = grid.trace_pixel_color(
+            pixel_height_index,
+            GRID_WIDTH / 2,
+            16,
+            BounceLimit::Fixed(1),
+            &objects,
+            &mut unit_disc_iter,
+        )?;
+        let (edge_color, edge_coverage) = grid.trace_pixel_color(
+            pixel_height_index,
+            edge_pixel_width_index,
+            16,
+            BounceLimit::Fixed(1),
+            &objects,
+            &mut unit_disc_iter,
+        )?;
+        let background_color = get_background_color()?;
+
+        assert_eq!(sphere_color, color::RED);
+        assert_eq!(sphere_coverage, 1.);
+        assert!(0. < edge_coverage && edge_coverage < 1.);
+        // * the edge is a blend of the sphere and the background, the weights being the parts of the samples each got
+        let blended_luminance =
+            edge_coverage * sphere_color.luminance() + (1. - edge_coverage) * background_color.luminance();
+        assert!((edge_color.luminance() - blended_luminance).abs() < 1e-6);
+        assert!(edge_color != sphere_color && edge_color != background_color);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_trace_pixel_color_above_one() -> Result<(), RayTracingError> {
+        let rng = XorShiftRng::seed_from_u64(5);
+        let mut unit_disc_iter: DistIter<UnitSphere, XorShiftRng, [Scalar; 3]> = UnitSphere.sample_iter(rng);
+        // * a white glowing sphere in a white glowing room: every sample brings back its own light plus the light of the room
+        let glowing_sphere = Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(0., 0., 10.), 5.)),
+            material: Material::new(color::WHITE, 1., color::WHITE.to_diffusion_coefficient()?, 0.)?,
+            visibility: VisibilityFlags::default(),
+        };
+        let glowing_room = Object {
+            shape: Box::new(Sphere::new_inward_from_radius(&Point::new(0., 0., 0.), 100.)),
+            material: Material::new(color::WHITE, 1., color::BLACK.to_diffusion_coefficient()?, 0.)?,
+            visibility: VisibilityFlags::default(),
+        };
+        let objects = vec![&glowing_sphere, &glowing_room];
+
+        let (pixel_color, _) = Grid::default().trace_pixel_color(
+            GRID_HEIGHT / 2,
+            GRID_WIDTH / 2,
+            10,
+            BounceLimit::Fixed(1),
+            &objects,
+            &mut unit_disc_iter,
+        )?;
+
+        // * the mean of samples of 2 is 2, it is neither clipped to 1 nor an error
+        let (r, g, b) = pixel_color.get_components();
+        for channel in [r, g, b] {
+            assert!((channel - 2.).abs() < 1e-5, "the pixel is {:?}", pixel_color);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg_attr(feature = "single-precision", ignore = "the chosen ray depends on the double precision random stream")]
+    fn test_trace_pixel_color() -> Result<(), RayTracingError> {
+        // * Define RNG
+        // the seed is chosen so that the bounce on the diffuse sphere goes towards the light source, see below,
+        // with the seed 25 used before the bounce misses the light and the pixel is black, even on the baseline renderer
+        let seed: u64 = 2;
         let rng = XorShiftRng::seed_from_u64(seed);
-        let mut unit_disc_iter: DistIter<UnitSphere, XorShiftRng, [f64; 3]> =
+        let mut unit_disc_iter: DistIter<UnitSphere, XorShiftRng, [Scalar; 3]> =
             UnitSphere.sample_iter(rng);
 
         // * define parameters
@@ -271,8 +1647,9 @@ mod tests {
         )
         .unwrap();
         let object_support = Object {
-            shape: sphere_support,
+            shape: Box::new(sphere_support),
             material: sphere_support_material,
+            visibility: VisibilityFlags::default(),
         };
 
         // * define the light source of the scene
@@ -290,8 +1667,9 @@ mod tests {
         )
         .unwrap();
         let object_light_source = Object {
-            shape: light_source,
+            shape: Box::new(light_source),
             material: light_source_material,
+            visibility: VisibilityFlags::default(),
         };
 
         // sphere positions are chosen so that the light source surface is very close to point defined as the ray going from the eye to the pixel, intersected with the sphere
@@ -302,11 +1680,11 @@ mod tests {
         // * object vector
         let objects = vec![&object_support, &object_light_source];
 
-        let actual_color = Grid::trace_pixel_color(
+        let (actual_color, _) = Grid::default().trace_pixel_color(
             pixel_height_index,
             pixel_width_index,
             number_of_points_per_pixel,
-            number_of_bounces,
+            BounceLimit::Fixed(number_of_bounces),
             &objects,
             &mut unit_disc_iter,
         )?;
@@ -316,4 +1694,1115 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_color_bleeding() -> Result<(), RayTracingError> {
+        // * a white floor next to a red wall, lit from above, both are large spheres so that they look flat
+        let floor = Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(0., 10001., 10.), 10000.)),
+            material: Material::new(color::BLACK, 0., color::WHITE.to_diffusion_coefficient()?, 0.)?,
+            visibility: VisibilityFlags::default(),
+        };
+        let red_wall = Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(10002., 0., 10.), 10000.)),
+            material: Material::new(color::BLACK, 0., DiffusionCoefficient::new(0.9, 0.05, 0.05)?, 0.)?,
+            visibility: VisibilityFlags::default(),
+        };
+        let light = Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(-5., -20., 10.), 10.)),
+            material: Material::new(color::WHITE, 1., color::BLACK.to_diffusion_coefficient()?, 0.)?,
+            visibility: VisibilityFlags::default(),
+        };
+        let objects = vec![&floor, &red_wall, &light];
+        let rng = XorShiftRng::seed_from_u64(10);
+        let mut unit_disc_iter: DistIter<UnitSphere, XorShiftRng, [Scalar; 3]> = UnitSphere.sample_iter(rng);
+
+        // all the rays hit the floor right next to the wall
+        let number_of_rays = 2000;
+        let mut total_light = color::BLACK;
+        for _ in 0..number_of_rays {
+            let ray = Ray::new_from_points(&Point::new(0., 0., 0.), &Point::new(1.8, 1., 10.))?;
+            let (ray_light, _) = Grid::trace_ray(
+                ray,
+                BounceLimit::Fixed(4),
+                &objects,
+                &TraceSettings::default(),
+                &mut unit_disc_iter,
+                None,
+            )?;
+            total_light = &total_light + &ray_light;
+        }
+        let (r, g, b) = (&total_light * (1. / number_of_rays as Scalar)).get_components();
+
+        // the light reaching the floor directly is white, the light coming from the wall is red
+        assert!(g > 0.01, "the floor should be lit, got {:?}", (r, g, b));
+        assert!(r > 1.2 * g, "the floor should be tinted red, got {:?}", (r, g, b));
+        assert!((g - b).abs() < 1e-6);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bounce_samplers_converge() -> Result<(), RayTracingError> {
+        // * same scene as test_trace_pixel_color, but with enough samples for the Monte Carlo estimation to converge
+        let number_of_points_per_pixel = 20000;
+        let number_of_bounces = 1;
+        let pixel_height_index = 1080 / 2;
+        let pixel_width_index = 1920 / 2;
+
+        let sphere_support = Sphere::new_from_radius(&Point::new(0., -3.9, 10.), 4.);
+        let object_support = Object {
+            shape: Box::new(sphere_support),
+            material: Material::new(
+                color::BLACK,
+                0.,
+                color::RED.to_diffusion_coefficient()?,
+                0.,
+            )?,
+            visibility: VisibilityFlags::default(),
+        };
+        let light_source = Sphere::new_from_radius(&Point::new(4.8, 6.2, 8.37), 3.18);
+        let object_light_source = Object {
+            shape: Box::new(light_source),
+            material: Material::new(
+                color::WHITE,
+                1.,
+                color::BLACK.to_diffusion_coefficient()?,
+                0.,
+            )?,
+            visibility: VisibilityFlags::default(),
+        };
+        let objects = vec![&object_support, &object_light_source];
+
+        let mut radiances = vec![];
+        for sampler in [BounceSampler::CosineWeighted, BounceSampler::Uniform] {
+            let rng = XorShiftRng::seed_from_u64(7);
+            let mut unit_disc_iter: DistIter<UnitSphere, XorShiftRng, [Scalar; 3]> =
+                UnitSphere.sample_iter(rng);
+            let grid = Grid {
+                sampler,
+                ..Grid::default()
+            };
+            let (color, _) = grid.trace_pixel_color(
+                pixel_height_index,
+                pixel_width_index,
+                number_of_points_per_pixel,
+                BounceLimit::Fixed(number_of_bounces),
+                &objects,
+                &mut unit_disc_iter,
+            )?;
+            radiances.push(color.get_components());
+        }
+        let (cos_r, cos_g, cos_b) = radiances[0];
+        let (uniform_r, uniform_g, uniform_b) = radiances[1];
+
+        // the light is seen from the hit point under a fair solid angle, so both estimations are well above 0
+        assert!(cos_r > 0.05, "cosine weighted radiance is {}", cos_r);
+        assert!(
+            (cos_r - uniform_r).abs() < 0.015,
+            "cosine weighted radiance {} and uniform radiance {} should converge to the same value",
+            cos_r,
+            uniform_r
+        );
+        assert_eq!((cos_g, cos_b), (0., 0.));
+        assert_eq!((uniform_g, uniform_b), (0., 0.));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scrub() -> Result<(), RayTracingError> {
+        let gray = Color::new(0.5, 0.5, 0.5)?;
+        let mut grid = make_uniform_grid(3, 3, gray);
+        grid.colors[0][0] = color::WHITE;
+        grid.colors[1][1] = &color::WHITE * Scalar::NAN;
+        grid.colors[2][2] = &color::WHITE * Scalar::INFINITY;
+
+        let scrubbed_pixels = grid.scrub()?;
+
+        assert_eq!(scrubbed_pixels, 2);
+        assert!(grid.colors.iter().flatten().all(|color| color.is_finite()));
+        // the center pixel has 7 valid neighbors: one white and six gray
+        let (r, g, b) = grid.colors[1][1].get_components();
+        let expected_value = (1. + 6. * 0.5) / 7.;
+        assert!((r - expected_value).abs() < 1e-6);
+        assert!((g - expected_value).abs() < 1e-6);
+        assert!((b - expected_value).abs() < 1e-6);
+        // the corner pixel only has gray valid neighbors, the infinite center is left out
+        assert_eq!(grid.colors[2][2], gray);
+        assert_eq!(grid.scrub()?, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_vignette() -> Result<(), RayTracingError> {
+        let gray = Color::new(0.5, 0.5, 0.5)?;
+        let mut grid = make_uniform_grid(5, 3, gray);
+        let mut unchanged_grid = make_uniform_grid(5, 3, gray);
+
+        grid.apply_vignette(0.4)?;
+        unchanged_grid.apply_vignette(0.)?;
+
+        assert_eq!(grid.colors[1][2], gray);
+        for (pixel_height_index, pixel_width_index) in [(0, 0), (0, 4), (2, 0), (2, 4)] {
+            let (r, _, _) = grid.colors[pixel_height_index][pixel_width_index].get_components();
+            assert!((r - 0.5 * 0.6).abs() < 1e-6);
+        }
+        let (edge_r, _, _) = grid.colors[1][0].get_components();
+        assert!(edge_r < 0.5 && edge_r > 0.3);
+        assert!(unchanged_grid.colors.iter().flatten().all(|color| *color == gray));
+        assert!(grid.apply_vignette(1.5).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bounce_limit_until_threshold() -> Result<(), RayTracingError> {
+        // * two large spheres facing each other, rays starting in between bounce back and forth from one to the other
+        let make_walls = |diffusion: f32| -> Result<[Object; 2], RayTracingError> {
+            let material = Material::new(
+                color::BLACK,
+                0.,
+                DiffusionCoefficient::new(diffusion, diffusion, diffusion)?,
+                0.,
+            )?;
+            Ok([
+                Object {
+                    shape: Box::new(Sphere::new_from_radius(&Point::new(0., -10001., 0.), 10000.)),
+                    material,
+                    visibility: VisibilityFlags::default(),
+                },
+                Object {
+                    shape: Box::new(Sphere::new_from_radius(&Point::new(0., 10001., 0.), 10000.)),
+                    material,
+                    visibility: VisibilityFlags::default(),
+                },
+            ])
+        };
+        let bright_walls = make_walls(0.9)?;
+        let dark_walls = make_walls(0.1)?;
+        let bounce_limit = BounceLimit::UntilThreshold(0.01);
+
+        let mut number_of_hits = vec![];
+        for walls in [&bright_walls, &dark_walls] {
+            let objects = vec![&walls[0], &walls[1]];
+            let rng = XorShiftRng::seed_from_u64(3);
+            let mut unit_disc_iter: DistIter<UnitSphere, XorShiftRng, [Scalar; 3]> =
+                UnitSphere.sample_iter(rng);
+            let mut total_number_of_hits = 0;
+            for _ in 0..20 {
+                let ray = Ray::new(Point::new(0., 0., 0.), Vector::new_from_coordinates(0., 1., 0.))?;
+                let (_, ray_number_of_hits) = Grid::trace_ray(
+                    ray,
+                    bounce_limit,
+                    &objects,
+                    &TraceSettings::default(),
+                    &mut unit_disc_iter,
+                    None,
+                )?;
+                total_number_of_hits += ray_number_of_hits;
+            }
+            number_of_hits.push(total_number_of_hits);
+        }
+
+        // 0.1^2 is not below the threshold yet, so the dark rays stop at the third hit at the latest
+        assert!(number_of_hits[1] <= 20 * 3);
+        assert!(
+            number_of_hits[0] > 5 * number_of_hits[1],
+            "bright scene made {} hits, dark scene made {} hits",
+            number_of_hits[0],
+            number_of_hits[1]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_inside_inward_sphere() -> Result<(), RayTracingError> {
+        // every direction hits the walls of the room, so the light brought back doesn't depend on the random bounces
+        let room = Object {
+            shape: Box::new(Sphere::new_inward_from_radius(&Point::new(0., 0., 0.), 20.)),
+            material: Material::new(color::WHITE, 0.5, DiffusionCoefficient::new(0.5, 0.5, 0.5)?, 0.)?,
+            visibility: VisibilityFlags::default(),
+        };
+        let objects = vec![&room];
+        let rng = XorShiftRng::seed_from_u64(4);
+        let mut unit_disc_iter: DistIter<UnitSphere, XorShiftRng, [Scalar; 3]> = UnitSphere.sample_iter(rng);
+        let ray = Ray::new(Point::new(0., 0., -10.), Vector::new_from_coordinates(0.3, -0.2, 1.))?;
+
+        let (ray_light, number_of_hits) = Grid::trace_ray(
+            ray,
+            BounceLimit::Fixed(2),
+            &objects,
+            &TraceSettings::default(),
+            &mut unit_disc_iter,
+            None,
+        )?;
+
+        // 0.5 + 0.5 * 0.5 + 0.5 * 0.5^2
+        assert_eq!(number_of_hits, 3);
+        let (r, g, b) = ray_light.get_components();
+        assert!((r - 0.875).abs() < 1e-6 && (g - 0.875).abs() < 1e-6 && (b - 0.875).abs() < 1e-6);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_raw_f32() -> Result<(), RayTracingError> {
+        let mut grid = make_uniform_grid(2, 2, color::BLACK);
+        grid.colors[0][1] = Color::new(0.25, 0.5, 1.)?;
+        grid.colors[1][0] = &color::WHITE * 3.;
+        // * the process id keeps the name unique between concurrent test runs
+        let path = std::env::temp_dir().join(format!("ray_tracing_3d_test_export_raw_f32_{}.raw", std::process::id()));
+        let mut header_path = path.clone().into_os_string();
+        header_path.push(".hdr.txt");
+
+        grid.export_raw_f32(&path)?;
+        let bytes = std::fs::read(&path)?;
+        let header = std::fs::read_to_string(&header_path)?;
+        std::fs::remove_file(&path)?;
+        std::fs::remove_file(&header_path)?;
+        let value_at = |index: usize| f32::from_le_bytes(bytes[4 * index..4 * index + 4].try_into().unwrap());
+
+        assert_eq!(bytes.len(), 2 * 2 * 3 * 4);
+        assert_eq!(value_at(4), 0.5); // green of the pixel at height 0, width 1
+        assert_eq!(value_at(6), 3.); // red of the pixel at height 1, width 0, not clamped
+        assert!(header.contains("width 2") && header.contains("height 2"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_tiled() -> Result<(), Box<dyn std::error::Error>> {
+        use rand::Rng;
+
+        let mut gradient = make_uniform_grid(37, 23, color::BLACK);
+        for (height_index, row) in gradient.colors.iter_mut().enumerate() {
+            for (width_index, pixel_color) in row.iter_mut().enumerate() {
+                *pixel_color = Color::new(width_index as f32 / 37., height_index as f32 / 23., 0.5)?;
+            }
+        }
+        gradient.coverage[4][7] = 0.25;
+        // * noise doesn't compress
+        let mut noise = gradient.clone();
+        let mut rng = XorShiftRng::seed_from_u64(3);
+        for pixel_color in noise.colors.iter_mut().flatten() {
+            *pixel_color = Color::new(rng.gen(), rng.gen(), rng.gen())?;
+        }
+
+        for (grid_index, grid) in [gradient, noise].into_iter().enumerate() {
+            for transparent_background in [false, true] {
+                let mut grid = grid.clone();
+                grid.transparent_background = transparent_background;
+                let name = format!("ray_tracing_3d_test_export_tiled_{}_{}", grid_index, transparent_background);
+                let (tiled_path, whole_path) = (
+                    std::env::temp_dir().join(format!("{}_tiled.png", name)),
+                    std::env::temp_dir().join(format!("{}_whole.png", name)),
+                );
+
+                // the tiles don't divide the height
+                grid.export_tiled(&tiled_path, 5)?;
+                grid.export_image(&whole_path)?;
+
+                let (tiled_bytes, whole_bytes) = (std::fs::read(&tiled_path)?, std::fs::read(&whole_path)?);
+                let tiled_image = image::open(&tiled_path)?;
+                std::fs::remove_file(&tiled_path)?;
+                std::fs::remove_file(&whole_path)?;
+                assert!(tiled_bytes == whole_bytes, "the tiled file differs from the whole one");
+                assert_eq!((tiled_image.width(), tiled_image.height()), (37, 23));
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_and_load_state() -> Result<(), RayTracingError> {
+        let light = Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(0., -3., 5.), 2.5)),
+            material: Material::new(color::WHITE, 1., color::BLACK.to_diffusion_coefficient()?, 0.)?,
+            visibility: VisibilityFlags::default(),
+        };
+        let ground = Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(0., 1003., 5.), 1000.)),
+            material: Material::new(color::BLACK, 0., color::WHITE.to_diffusion_coefficient()?, 0.)?,
+            visibility: VisibilityFlags::default(),
+        };
+        let objects = vec![&light, &ground];
+        let path = std::env::temp_dir().join("ray_tracing_3d_test_save_and_load_state.bin");
+        let mut uninterrupted = Grid::new(16, 9)?;
+        let mut interrupted = Grid::new(16, 9)?;
+
+        uninterrupted.make_image(2, BounceLimit::Fixed(2), &objects)?;
+        let halfway_colors = uninterrupted.colors.clone();
+        uninterrupted.make_image(2, BounceLimit::Fixed(2), &objects)?;
+        interrupted.make_image(2, BounceLimit::Fixed(2), &objects)?;
+        interrupted.save_state(&path)?;
+        let mut resumed = Grid::load_state(&path)?;
+        resumed.make_image(2, BounceLimit::Fixed(2), &objects)?;
+
+        assert_eq!(resumed.colors, uninterrupted.colors);
+        assert_eq!(resumed.coverage, uninterrupted.coverage);
+        assert!(resumed.samples.iter().flatten().all(|samples| *samples == 4));
+        // * the second half brought new samples, not the same ones again
+        assert_ne!(uninterrupted.colors, halfway_colors);
+        std::fs::write(&path, b"not a render")?;
+        assert!(matches!(Grid::load_state(&path), Err(RayTracingError::InvalidRenderState(_))));
+        // * a header asking for a huge grid, or a file with missing or extra pixels
+        let mut huge_header = RENDER_STATE_MAGIC.to_vec();
+        huge_header.extend_from_slice(&u64::MAX.to_le_bytes());
+        huge_header.extend_from_slice(&u64::MAX.to_le_bytes());
+        std::fs::write(&path, huge_header)?;
+        assert!(matches!(Grid::load_state(&path), Err(RayTracingError::InvalidRenderState(_))));
+        interrupted.save_state(&path)?;
+        let mut bytes = std::fs::read(&path)?;
+        bytes.push(0);
+        std::fs::write(&path, &bytes)?;
+        assert!(matches!(Grid::load_state(&path), Err(RayTracingError::InvalidRenderState(_))));
+        bytes.truncate(bytes.len() - 2);
+        std::fs::write(&path, &bytes)?;
+        assert!(matches!(Grid::load_state(&path), Err(RayTracingError::InvalidRenderState(_))));
+        std::fs::remove_file(&path)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_specular_tint() -> Result<(), RayTracingError> {
+        let light = Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(0., 0., -10.), 1.)),
+            material: Material::new(color::WHITE, 1., color::BLACK.to_diffusion_coefficient()?, 0.)?,
+            visibility: VisibilityFlags::default(),
+        };
+        let clear_mirror_material = Material::new(color::BLACK, 0., color::BLUE.to_diffusion_coefficient()?, 1.)?;
+        let red_mirror_material = clear_mirror_material.with_specular_tint(color::RED.to_diffusion_coefficient()?);
+
+        let mut reflected_lights = vec![];
+        for material in [clear_mirror_material, red_mirror_material] {
+            let mirror = Object {
+                shape: Box::new(Sphere::new_from_radius(&Point::new(0., 0., 10.), 1.)),
+                material,
+                visibility: VisibilityFlags::default(),
+            };
+            let objects = vec![&mirror, &light];
+            let rng = XorShiftRng::seed_from_u64(8);
+            let mut unit_disc_iter: DistIter<UnitSphere, XorShiftRng, [Scalar; 3]> = UnitSphere.sample_iter(rng);
+            // the mirror sends the ray straight back to the light behind its origin
+            let ray = Ray::new(Point::new(0., 0., 0.), Vector::new_from_coordinates(0., 0., 1.))?;
+            let (ray_light, _) = Grid::trace_ray(
+                ray,
+                BounceLimit::Fixed(2),
+                &objects,
+                &TraceSettings::default(),
+                &mut unit_disc_iter,
+                None,
+            )?;
+            reflected_lights.push(ray_light);
+        }
+
+        // the blue diffusion coefficients have no effect on the mirror bounce
+        assert_eq!(reflected_lights[0], color::WHITE);
+        assert_eq!(reflected_lights[1], color::RED);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_spectral_mode() -> Result<(), RayTracingError> {
+        // * a yellow light seen in a magenta mirror, a metameric failure of the RGB renderer
+        let light = Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(0., 0., -10.), 1.)),
+            material: Material::new(Color::new(1., 1., 0.)?, 1., color::BLACK.to_diffusion_coefficient()?, 0.)?,
+            visibility: VisibilityFlags::default(),
+        };
+        let mirror = Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(0., 0., 10.), 1.)),
+            material: Material::new(color::BLACK, 0., color::BLACK.to_diffusion_coefficient()?, 1.)?
+                .with_specular_tint(Color::new(1., 0., 1.)?.to_diffusion_coefficient()?),
+            visibility: VisibilityFlags::default(),
+        };
+        let objects = vec![&mirror, &light];
+
+        let reflected_lights = [ColorMode::Rgb, ColorMode::Spectral]
+            .iter()
+            .map(|color_mode| {
+                let rng = XorShiftRng::seed_from_u64(8);
+                let mut unit_disc_iter: DistIter<UnitSphere, XorShiftRng, [Scalar; 3]> = UnitSphere.sample_iter(rng);
+                let ray = Ray::new(Point::new(0., 0., 0.), Vector::new_from_coordinates(0., 0., 1.))?;
+                let settings = TraceSettings {
+                    color_mode: *color_mode,
+                    ..TraceSettings::default()
+                };
+                let (ray_light, _) =
+                    Grid::trace_ray(ray, BounceLimit::Fixed(2), &objects, &settings, &mut unit_disc_iter, None)?;
+                Ok(ray_light)
+            })
+            .collect::<Result<Vec<Color>, RayTracingError>>()?;
+
+        assert_eq!(reflected_lights[0], color::RED);
+        // * the smooth spectra of yellow and magenta overlap in the green and a little in the blue
+        let (r, g, b) = reflected_lights[1].get_components();
+        assert!((r - 0.9376).abs() < 1e-3 && (g - 0.2582).abs() < 1e-3 && (b - 0.0994).abs() < 1e-3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_normal_map_shading() -> Result<(), RayTracingError> {
+        // * a small light in front of a flat wall facing it, only the normals of the wall can change how much it gets
+        let light = Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(0., 0., -5.), 0.1)),
+            material: Material::new(color::WHITE, 1., color::BLACK.to_diffusion_coefficient()?, 0.)?,
+            visibility: VisibilityFlags::default(),
+        };
+        let flat_material = Material::default();
+        let bumpy_material = flat_material.with_normal_map(NormalMap::Bumps {
+            frequency: 3.,
+            strength: 1.,
+        })?;
+        let null_map_material = flat_material.with_normal_map(NormalMap::Bumps {
+            frequency: 3.,
+            strength: 0.,
+        })?;
+
+        let direct_lights = [flat_material, bumpy_material, null_map_material]
+            .iter()
+            .map(|material| {
+                let wall = Object {
+                    shape: Box::new(Triangle::new(
+                        Point::new(-10., -10., 10.),
+                        Point::new(-10., 10., 10.),
+                        Point::new(10., -10., 10.),
+                    )),
+                    material: *material,
+                    visibility: VisibilityFlags::default(),
+                };
+                let objects = vec![&wall, &light];
+                (0..20)
+                    .map(|point_index| {
+                        let ray = Ray::new(
+                            Point::new(-3. + 0.3 * point_index as Scalar, -4.2, 0.),
+                            Vector::new_from_coordinates(0., 0., 1.),
+                        )?;
+                        let hit_info = ray.intersect(&wall)?.expect("the wall is in front of the ray");
+                        let rng = XorShiftRng::seed_from_u64(point_index);
+                        let mut unit_disc_iter: DistIter<UnitSphere, XorShiftRng, [Scalar; 3]> =
+                            UnitSphere.sample_iter(rng);
+                        let direct_light = Grid::sample_emitters(
+                            &hit_info,
+                            &objects,
+                            None,
+                            &[light.clone()],
+                            &mut unit_disc_iter,
+                        )?;
+                        Ok(direct_light.luminance())
+                    })
+                    .collect::<Result<Vec<f32>, RayTracingError>>()
+            })
+            .collect::<Result<Vec<Vec<f32>>, RayTracingError>>()?;
+
+        let spread = |lights: &[f32]| {
+            let (min, max) = lights.iter().fold((f32::MAX, f32::MIN), |(min, max), light| (min.min(*light), max.max(*light)));
+            (max - min) / max
+        };
+        assert!(spread(&direct_lights[0]) < 0.1);
+        assert!(spread(&direct_lights[1]) > 0.3);
+        for (flat_light, null_map_light) in direct_lights[0].iter().zip(&direct_lights[2]) {
+            assert!((flat_light - null_map_light).abs() < 1e-5 * flat_light);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_light_through_glass() -> Result<(), RayTracingError> {
+        let light = Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(0., 0., 20.), 5.)),
+            material: Material::new(color::WHITE, 1., color::BLACK.to_diffusion_coefficient()?, 0.)?,
+            visibility: VisibilityFlags::default(),
+        };
+        let glass_material = Material::default().with_ior(1.5)?;
+        let opaque_material = Material::new(color::BLACK, 0., color::BLACK.to_diffusion_coefficient()?, 0.)?;
+
+        let mut lights_through_ball = vec![];
+        for material in [glass_material, opaque_material] {
+            let ball = Object {
+                shape: Box::new(Sphere::new_from_radius(&Point::new(0., 0., 10.), 1.)),
+                material,
+                visibility: VisibilityFlags::default(),
+            };
+            let objects = vec![&ball, &light];
+            let rng = XorShiftRng::seed_from_u64(8);
+            let mut unit_disc_iter: DistIter<UnitSphere, XorShiftRng, [Scalar; 3]> = UnitSphere.sample_iter(rng);
+            // the ray goes through the center of the ball towards the light behind it
+            let mut ray_light = color::BLACK;
+            for _ in 0..50 {
+                let ray = Ray::new(Point::new(0., 0., 0.), Vector::new_from_coordinates(0., 0., 1.))?;
+                let (sample_light, _) = Grid::trace_ray(
+                    ray,
+                    BounceLimit::Fixed(3),
+                    &objects,
+                    &TraceSettings::default(),
+                    &mut unit_disc_iter,
+                    None,
+                )?;
+                ray_light = &ray_light + &sample_light;
+            }
+            lights_through_ball.push(ray_light.luminance() / 50.);
+        }
+
+        // * most of the light goes through both sides of the glass, the rest is reflected away from the light
+        assert!(lights_through_ball[0] > 0.8 && lights_through_ball[0] < 1.);
+        assert_eq!(lights_through_ball[1], 0.);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_coverage() -> Result<(), RayTracingError> {
+        // * same small sphere as test_pixel_is_on_edge, it is about 10 pixels wide around the center of the grid
+        let object = Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(0., 0., 10.), 0.21)),
+            material: Material::new(color::WHITE, 1., color::BLACK.to_diffusion_coefficient()?, 0.)?,
+            visibility: VisibilityFlags::default(),
+        };
+        let objects = vec![&object];
+        let grid = Grid {
+            transparent_background: true,
+            ..Grid::default()
+        };
+        let rng = XorShiftRng::seed_from_u64(9);
+        let mut unit_disc_iter: DistIter<UnitSphere, XorShiftRng, [Scalar; 3]> = UnitSphere.sample_iter(rng);
+        let mut pixel_coverage = |pixel_height_index, pixel_width_index| {
+            grid.trace_pixel_color(
+                pixel_height_index,
+                pixel_width_index,
+                16,
+                BounceLimit::Fixed(0),
+                &objects,
+                &mut unit_disc_iter,
+            )
+        };
+
+        let (center_color, center_coverage) = pixel_coverage(GRID_HEIGHT / 2, GRID_WIDTH / 2)?;
+        let (corner_color, corner_coverage) = pixel_coverage(0, 0)?;
+        let mut edge_coverages = vec![];
+        for pixel_width_index in GRID_WIDTH / 2..GRID_WIDTH / 2 + 20 {
+            let (_, coverage) = pixel_coverage(GRID_HEIGHT / 2, pixel_width_index)?;
+            if coverage > 0. && coverage < 1. {
+                edge_coverages.push(coverage);
+            }
+        }
+
+        assert_eq!((center_color, center_coverage), (color::WHITE, 1.));
+        assert_eq!((corner_color, corner_coverage), (color::BLACK, 0.));
+        assert!(!edge_coverages.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_clearcoat_highlight() -> Result<(), RayTracingError> {
+        // * a coated sphere with a black base, in front of the ray origin, and a light behind the ray origin
+        let light = Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(0., 0., -10.), 1.)),
+            material: Material::new(color::WHITE, 1., color::BLACK.to_diffusion_coefficient()?, 0.)?,
+            visibility: VisibilityFlags::default(),
+        };
+        let coated_sphere = Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(0., 0., 10.), 1.)),
+            material: Material::new(color::BLACK, 0., color::BLACK.to_diffusion_coefficient()?, 0.)?
+                .with_clearcoat(1., 0.)?,
+            visibility: VisibilityFlags::default(),
+        };
+        let objects = vec![&coated_sphere, &light];
+        let rng = XorShiftRng::seed_from_u64(11);
+        let mut unit_disc_iter: DistIter<UnitSphere, XorShiftRng, [Scalar; 3]> = UnitSphere.sample_iter(rng);
+
+        // at normal incidence the coat reflects 4% of the light straight back to the light, the base absorbs the rest
+        let number_of_rays = 5000;
+        let mut total_light = color::BLACK;
+        for _ in 0..number_of_rays {
+            let ray = Ray::new(Point::new(0., 0., 0.), Vector::new_from_coordinates(0., 0., 1.))?;
+            let (ray_light, _) = Grid::trace_ray(
+                ray,
+                BounceLimit::Fixed(2),
+                &objects,
+                &TraceSettings::default(),
+                &mut unit_disc_iter,
+                None,
+            )?;
+            total_light = &total_light + &ray_light;
+        }
+        let (r, g, b) = (&total_light * (1. / number_of_rays as Scalar)).get_components();
+
+        assert!((r - 0.04).abs() < 0.01, "reflected light is {}", r);
+        assert_eq!((r, r), (g, b));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tent_filter_normalization() -> Result<(), RayTracingError> {
+        // * a large glowing sphere fills the view, every primary ray brings back the same light
+        let glowing_wall = Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(0., 0., 1000.), 900.)),
+            material: Material::new(color::WHITE, 0.5, color::BLACK.to_diffusion_coefficient()?, 0.)?,
+            visibility: VisibilityFlags::default(),
+        };
+        let objects = vec![&glowing_wall];
+
+        let mut pixel_colors = vec![];
+        for filter in [PixelFilter::Box, PixelFilter::Tent] {
+            let grid = Grid {
+                filter,
+                ..Grid::default()
+            };
+            let rng = XorShiftRng::seed_from_u64(13);
+            let mut unit_disc_iter: DistIter<UnitSphere, XorShiftRng, [Scalar; 3]> = UnitSphere.sample_iter(rng);
+            let (pixel_color, _) = grid.trace_pixel_color(
+                GRID_HEIGHT / 2,
+                GRID_WIDTH / 2,
+                16,
+                BounceLimit::Fixed(0),
+                &objects,
+                &mut unit_disc_iter,
+            )?;
+            pixel_colors.push(pixel_color.get_components());
+        }
+
+        let (box_r, _, _) = pixel_colors[0];
+        let (tent_r, _, _) = pixel_colors[1];
+        assert!((box_r - 0.5).abs() < 1e-6);
+        assert!((tent_r - box_r).abs() < 1e-6);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_draw_aabb() -> Result<(), RayTracingError> {
+        let mut grid = make_uniform_grid(64, 36, color::BLACK);
+        let camera = Camera::new(
+            Point::new(0., 0., -10.),
+            Point::new(0., 0., 0.),
+            Vector::new_from_coordinates(0., -1., 0.),
+            60.,
+            16. / 9.,
+        )?;
+        let bb = Aabb::new(&Point::new(-1., -1., -1.), &Point::new(1., 1., 1.));
+
+        grid.draw_aabb(&bb, &camera, color::RED)?;
+
+        let is_drawn = |pixel: (Scalar, Scalar)| grid.colors[pixel.1.round() as usize][pixel.0.round() as usize] == color::RED;
+        for corner in bb.corners() {
+            let projected_corner = camera.project_to_pixel(&corner, 64, 36)?.expect("the box is in front");
+            assert!(is_drawn(projected_corner));
+        }
+        // * middle of the top front edge
+        let edge_middle = camera.project_to_pixel(&Point::new(0., -1., -1.), 64, 36)?.expect("the box is in front");
+        assert!(is_drawn(edge_middle));
+        // * the center of the box is not on any edge
+        assert_eq!(grid.colors[18][32], color::BLACK);
+        let drawn_pixels = grid.colors.iter().flatten().filter(|pixel_color| **pixel_color == color::RED).count();
+        assert!(drawn_pixels > 0 && drawn_pixels < 64 * 36 / 4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_make_image_with_budget() -> Result<(), RayTracingError> {
+        // * a white wall filling the view, lit by a light behind the eye that only covers part of the sky of the wall
+        let wall = Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(0., 0., 1000.), 900.)),
+            material: Material::default(),
+            visibility: VisibilityFlags::default(),
+        };
+        let light = Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(0., 0., -400.), 350.)),
+            material: Material::new(color::WHITE, 1., color::BLACK.to_diffusion_coefficient()?, 0.)?,
+            visibility: VisibilityFlags::default(),
+        };
+        let objects = vec![&wall, &light];
+        let mut budget = vec![vec![1; 4]; 2];
+        budget[0][0] = 0;
+        budget[1][2] = 256;
+
+        let renders = [3, 4, 5, 6, 7, 8, 9, 10]
+            .iter()
+            .map(|seed| {
+                let mut grid = make_uniform_grid(4, 2, color::WHITE);
+                grid.make_image_with_budget(&budget, BounceLimit::Fixed(1), &objects, *seed)?;
+                Ok(grid)
+            })
+            .collect::<Result<Vec<Grid>, RayTracingError>>()?;
+
+        for grid in &renders {
+            assert_eq!(grid.colors[0][0], get_background_color()?);
+            assert_eq!(grid.coverage[0][0], 0.);
+            assert!(!grid.colors[1][2].is_black());
+        }
+        // * the spread between seeds of a pixel is smaller with more samples
+        let spread = |pixel_height_index: usize, pixel_width_index: usize| {
+            let values = renders.iter().map(|grid| grid.colors[pixel_height_index][pixel_width_index].luminance());
+            let (min, max) = values.fold((f32::MAX, f32::MIN), |(min, max), value| (min.min(value), max.max(value)));
+            max - min
+        };
+        assert!(spread(1, 2) < spread(1, 1));
+        let mut ragged_budget = budget.clone();
+        ragged_budget[1].pop();
+        assert!(make_uniform_grid(4, 2, color::WHITE)
+            .make_image_with_budget(&ragged_budget, BounceLimit::Fixed(1), &objects, 3)
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_make_image_checkerboard() -> Result<(), RayTracingError> {
+        // * the lamp doesn't diffuse, so each pixel only depends on where its primary rays go
+        let lamp = Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(2., 0., 10.), 5.)),
+            material: Material::new(color::RED, 1., color::BLACK.to_diffusion_coefficient()?, 0.)?,
+            visibility: VisibilityFlags::default(),
+        };
+        let objects = vec![&lamp];
+        let mut full_render = Grid::new(16, 9)?;
+        full_render.make_image(1, BounceLimit::Fixed(1), &objects)?;
+        let mut grid = Grid::new(16, 9)?;
+        grid.colors = vec![vec![color::GREEN; 16]; 9];
+
+        grid.make_image_checkerboard(CheckerboardPass::First, 1, BounceLimit::Fixed(1), &objects, 3)?;
+
+        for pixel_height_index in 0..9 {
+            for pixel_width_index in 0..16 {
+                let pixel_color = grid.colors[pixel_height_index][pixel_width_index];
+                if (pixel_height_index + pixel_width_index) % 2 == 0 {
+                    assert_eq!(pixel_color, full_render.colors[pixel_height_index][pixel_width_index]);
+                } else {
+                    let neighbor_width_index = if pixel_width_index > 0 { pixel_width_index - 1 } else { 1 };
+                    assert_eq!(pixel_color, grid.colors[pixel_height_index][neighbor_width_index]);
+                }
+            }
+        }
+        assert!(grid.colors.iter().flatten().all(|pixel_color| *pixel_color != color::GREEN));
+        grid.make_image_checkerboard(CheckerboardPass::Second, 1, BounceLimit::Fixed(1), &objects, 3)?;
+        assert_eq!(grid.colors, full_render.colors);
+        assert_eq!(grid.coverage, full_render.coverage);
+        // * a pass over a rendered image adds its rays instead of replacing them, the gaps keep their rays
+        let mut continued_render = full_render.clone();
+        continued_render.make_image_checkerboard(CheckerboardPass::First, 3, BounceLimit::Fixed(1), &objects, 3)?;
+        assert_eq!(continued_render.samples[0][0], 4);
+        assert_eq!(continued_render.samples[0][1], 1);
+        assert_eq!(continued_render.colors[0][1], full_render.colors[0][1]);
+        // * the edges mode draws the silhouettes like `make_image`
+        let mut edges_render = Grid::new(16, 9)?;
+        edges_render.render_mode = RenderMode::Edges;
+        edges_render.make_image(1, BounceLimit::Fixed(1), &objects)?;
+        let mut edges_grid = Grid::new(16, 9)?;
+        edges_grid.render_mode = RenderMode::Edges;
+        for pass in [CheckerboardPass::First, CheckerboardPass::Second] {
+            edges_grid.make_image_checkerboard(pass, 1, BounceLimit::Fixed(1), &objects, 3)?;
+        }
+        assert_eq!(edges_grid.colors, edges_render.colors);
+        assert!(edges_grid.colors.iter().flatten().any(|pixel_color| *pixel_color == color::WHITE));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_display_color_is_gamma_encoded() -> Result<(), RayTracingError> {
+        let mut grid = make_uniform_grid(4, 2, color::WHITE);
+        let half = Color::new(0.5, 0.5, 0.5)?;
+
+        let (r, g, b) = grid.display_color(&half)?;
+
+        assert!((186..=188).contains(&r), "0.5 is written as {}", r);
+        assert_eq!((r, r), (g, b));
+        assert_eq!(grid.display_color(&color::WHITE)?, (255, 255, 255));
+        assert_eq!(grid.display_color(&color::BLACK)?, (0, 0, 0));
+        // * the linear values are written as they are without the encoding
+        grid.gamma = 1.;
+        assert_eq!(grid.display_color(&half)?, (128, 128, 128));
+        // * a light 3 times too bright is clipped unless it is tone mapped
+        let bright = &color::WHITE * 3.;
+        assert_eq!(grid.display_color(&bright)?, (255, 255, 255));
+        grid.tone_map = ToneMap::Reinhard;
+        assert_eq!(grid.display_color(&bright)?, (191, 191, 191));
+        for filmic_map in [ToneMap::Aces, ToneMap::Uncharted2] {
+            grid.tone_map = filmic_map;
+            assert!(grid.display_color(&bright)?.0 < 255, "{:?} clips the bright light", filmic_map);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_auto_exposure() -> Result<(), RayTracingError> {
+        let dark_grid = make_uniform_grid(4, 4, Color::new(0.02, 0.02, 0.02)?);
+        let mut bright_grid = make_uniform_grid(4, 4, Color::new(0.9, 0.9, 0.9)?);
+        bright_grid.colors[0][0] = color::WHITE;
+
+        let dark_exposure = dark_grid.auto_exposure(DEFAULT_EXPOSURE_KEY);
+        let bright_exposure = bright_grid.auto_exposure(DEFAULT_EXPOSURE_KEY);
+
+        assert!(dark_exposure > 1.);
+        assert!(bright_exposure < 1.);
+        // * both end up around the key
+        let exposed_luminance = |grid: &Grid, exposure: Scalar| exposure * grid.colors[1][1].luminance() as Scalar;
+        assert!((exposed_luminance(&dark_grid, dark_exposure) - DEFAULT_EXPOSURE_KEY).abs() < 1e-2);
+        assert!((exposed_luminance(&bright_grid, bright_exposure) - DEFAULT_EXPOSURE_KEY).abs() < 1e-2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_luminance_statistics() -> Result<(), RayTracingError> {
+        let gray = Color::new(0.18, 0.18, 0.18)?;
+        let gray_grid = make_uniform_grid(8, 4, gray);
+
+        let histogram = gray_grid.luminance_histogram(10);
+        assert_eq!(histogram.iter().sum::<u64>(), 32);
+        assert_eq!(histogram.iter().filter(|count| **count > 0).count(), 1);
+        assert!((gray_grid.percentile_luminance(50.) - gray.luminance() as Scalar).abs() < 1e-6);
+        // * the top half of the image is black and the bottom half white
+        let mut half_grid = make_uniform_grid(8, 4, color::BLACK);
+        for row in &mut half_grid.colors[2..] {
+            row.fill(color::WHITE);
+        }
+        half_grid.colors[0][0] = Color::new_unbounded(f32::NAN, 0., 0.);
+        assert_eq!(half_grid.luminance_histogram(2), vec![15, 16]);
+        assert_eq!(half_grid.percentile_luminance(25.), 0.);
+        assert!((half_grid.percentile_luminance(75.) - 1.).abs() < 1e-6);
+        assert_eq!(make_uniform_grid(4, 4, color::BLACK).luminance_histogram(3), vec![16, 0, 0]);
+        assert!(gray_grid.luminance_histogram(0).is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_make_image_empty_scene() -> Result<(), RayTracingError> {
+        let mut grid = make_uniform_grid(8, 4, color::WHITE);
+
+        grid.make_image(4, BounceLimit::Fixed(3), &[])?;
+
+        // * every pixel only sees the background
+        let background_color = get_background_color()?;
+        assert!(grid.colors.iter().flatten().all(|pixel_color| *pixel_color == background_color));
+        assert!(grid.coverage.iter().flatten().all(|coverage| *coverage == 0.));
+        // * no object means no silhouette either
+        grid.render_mode = RenderMode::Edges;
+        grid.make_image(4, BounceLimit::Fixed(3), &[])?;
+        assert!(grid.colors.iter().flatten().all(|pixel_color| *pixel_color == color::BLACK));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_make_image_without_points() -> Result<(), RayTracingError> {
+        let lamp = Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(0., 0., 10.), 8.)),
+            material: Material::new(color::WHITE, 1., color::BLACK.to_diffusion_coefficient()?, 0.)?,
+            visibility: VisibilityFlags::default(),
+        };
+        let objects = vec![&lamp];
+        let mut grid = make_uniform_grid(8, 4, color::RED);
+        let mut unit_disc_iter: DistIter<UnitSphere, XorShiftRng, [Scalar; 3]> =
+            UnitSphere.sample_iter(XorShiftRng::seed_from_u64(1));
+
+        grid.make_image(0, BounceLimit::Fixed(1), &objects)?;
+
+        // * the pixels are left as they were instead of becoming NaN
+        assert!(grid.colors.iter().flatten().all(|pixel_color| *pixel_color == color::RED));
+        assert!(grid.coverage.iter().flatten().all(|coverage| *coverage == 1.));
+        let (pixel_color, pixel_coverage) =
+            grid.trace_pixel_color(2, 4, 0, BounceLimit::Fixed(1), &objects, &mut unit_disc_iter)?;
+        assert_eq!(pixel_color, get_background_color()?);
+        assert_eq!(pixel_coverage, 0.);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_mirror_box_repeated_reflections() -> Result<(), RayTracingError> {
+        let half_size = 10.;
+        let mirror = Material::new(color::BLACK, 0., color::BLACK.to_diffusion_coefficient()?, 1.)?
+            .with_specular_tint(DiffusionCoefficient::new(0.8, 0.8, 0.8)?);
+        let mut walls = Vec::new();
+        for axis in [Vector::new_from_coordinates(1., 0., 0.), Vector::new_from_coordinates(0., 1., 0.), Vector::new_from_coordinates(0., 0., 1.)] {
+            for side in [-1., 1.] {
+                // * the walls face the inside of the box
+                let wall_point = &Point::new(0., 0., 0.) + &((side * half_size) * &axis);
+                walls.push(Object {
+                    shape: Box::new(Plane::new(wall_point, -side * &axis)?),
+                    material: mirror,
+                    visibility: VisibilityFlags::default(),
+                });
+            }
+        }
+        let lamp = Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(0., 0., 0.), 2.)),
+            material: Material::new(color::WHITE, 1., color::BLACK.to_diffusion_coefficient()?, 0.)?,
+            visibility: VisibilityFlags::default(),
+        };
+        let mut objects: Vec<&Object> = walls.iter().collect();
+        objects.push(&lamp);
+        let mut grid = Grid::new(48, 48)?;
+        grid.camera = Camera::new(
+            Point::new(0., 0., -9.),
+            Point::new(0., 0., 0.),
+            Vector::new_from_coordinates(0., -1., 0.),
+            120.,
+            1.,
+        )?;
+
+        grid.make_image(1, BounceLimit::Fixed(30), &objects)?;
+
+        // * every image of the lamp went through some reflections, each of them keeping 80% of the light
+        let mut number_of_reflections_seen = std::collections::BTreeSet::new();
+        for pixel_color in grid.colors.iter().flatten() {
+            let luminance = pixel_color.luminance();
+            if luminance == 0. {
+                // the ray was still bouncing between the mirrors when it ran out of bounces
+                continue;
+            }
+            let number_of_reflections = luminance.ln() / (0.8_f32).ln();
+            assert!(
+                (number_of_reflections - number_of_reflections.round()).abs() < 1e-3,
+                "luminance {} is not a power of 0.8",
+                luminance
+            );
+            number_of_reflections_seen.insert(number_of_reflections.round() as u32);
+        }
+        assert!(number_of_reflections_seen.contains(&0));
+        assert!(number_of_reflections_seen.len() >= 3, "only saw {:?}", number_of_reflections_seen);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_make_image_rows_are_independent() -> Result<(), RayTracingError> {
+        let sphere = Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(0., 0., 10.), 8.)),
+            material: Material::default(),
+            visibility: VisibilityFlags::default(),
+        };
+        let light = Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(0., -30., 0.), 20.)),
+            material: Material::new(color::WHITE, 1., color::BLACK.to_diffusion_coefficient()?, 0.)?,
+            visibility: VisibilityFlags::default(),
+        };
+        let objects = vec![&sphere, &light];
+        let mut grid = make_uniform_grid(8, 6, color::BLACK);
+
+        grid.make_image(4, BounceLimit::Fixed(2), &objects)?;
+
+        // * rendering the rows one by one in reverse order, as threads could, gives the same image
+        for pixel_height_index in (0..6).rev() {
+            let (row_colors, row_coverage) =
+                grid.render_row(pixel_height_index, 4, BounceLimit::Fixed(2), &objects, RENDER_SEED)?;
+            assert_eq!(row_colors, grid.colors[pixel_height_index]);
+            assert_eq!(row_coverage, grid.coverage[pixel_height_index]);
+        }
+        assert!(grid.colors.iter().flatten().any(|pixel_color| !pixel_color.is_black()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff() -> Result<(), RayTracingError> {
+        let gray = Color::new(0.5, 0.5, 0.5)?;
+        let grid = make_uniform_grid(4, 2, gray);
+        let mut modified_grid = make_uniform_grid(4, 2, gray);
+        modified_grid.colors[1][2] = Color::new(0.5, 0.9, 0.5)?;
+
+        let same_report = grid.diff(&grid)?;
+        let modified_report = grid.diff(&modified_grid)?;
+
+        assert_eq!(same_report.max_difference, (0., 0., 0.));
+        assert_eq!(same_report.mean_difference, (0., 0., 0.));
+        assert_eq!(same_report.number_of_differing_pixels, 0);
+        assert_eq!(modified_report.number_of_differing_pixels, 1);
+        assert_eq!(modified_report.max_difference.0, 0.);
+        assert!((modified_report.max_difference.1 - 0.4).abs() < 1e-6);
+        assert!((modified_report.mean_difference.1 - 0.4 / 8.).abs() < 1e-6);
+        assert_eq!(same_report.rms_difference, 0.);
+        assert!((modified_report.rms_difference - (0.4f32 * 0.4 / 24.).sqrt()).abs() < 1e-6);
+        assert!(grid.diff(&Grid::default()).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_make_image_until_converged() -> Result<(), RayTracingError> {
+        // * nothing to hit, every ray gives the background color
+        let mut flat_grid = make_uniform_grid(4, 2, color::WHITE);
+        let flat_passes = flat_grid.make_image_until_converged(2, BounceLimit::Fixed(1), &[], 1e-3, 8)?;
+
+        assert_eq!(flat_passes.len(), 1);
+        assert_eq!(flat_passes[0].rms_difference, 0.);
+        assert_eq!(flat_passes[0].number_of_points_per_pixel, 4);
+        // * a diffuse wall lit by a light covering part of its sky, which takes many rays to average
+        let wall = Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(0., 0., 1000.), 900.)),
+            material: Material::default(),
+            visibility: VisibilityFlags::default(),
+        };
+        let light = Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(0., 0., -400.), 350.)),
+            material: Material::new(color::WHITE, 1., color::BLACK.to_diffusion_coefficient()?, 0.)?,
+            visibility: VisibilityFlags::default(),
+        };
+        let mut noisy_grid = make_uniform_grid(4, 2, color::WHITE);
+        let noisy_passes =
+            noisy_grid.make_image_until_converged(1, BounceLimit::Fixed(1), &[&wall, &light], 0.05, 8)?;
+
+        assert!(noisy_passes.len() > 1);
+        assert!(noisy_passes.last().unwrap().rms_difference < 0.05);
+        for (pass, next_pass) in noisy_passes.iter().zip(&noisy_passes[1..]) {
+            assert_eq!(next_pass.number_of_points_per_pixel, 2 * pass.number_of_points_per_pixel);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pixel_is_on_edge() -> Result<(), RayTracingError> {
+        // * a sphere on the axis of the eye, it is seen with a radius of about 10.5 pixels on the grid
+        let sphere = Sphere::new_from_radius(&Point::new(0., 0., 10.), 0.21);
+        let object = Object {
+            shape: Box::new(sphere),
+            material: Material::default(),
+            visibility: VisibilityFlags::default(),
+        };
+        let objects = vec![&object];
+        let grid = Grid::default();
+
+        let center_height_index = GRID_HEIGHT / 2;
+        let center_width_index = GRID_WIDTH / 2;
+        let mut edge_width_indexes = vec![];
+        for pixel_width_index in center_width_index - 20..center_width_index + 20 {
+            if grid.pixel_is_on_edge(center_height_index, pixel_width_index, &objects)? {
+                edge_width_indexes.push(pixel_width_index);
+            }
+        }
+        let mut edge_height_indexes = vec![];
+        for pixel_height_index in center_height_index - 20..center_height_index + 20 {
+            if grid.pixel_is_on_edge(pixel_height_index, center_width_index, &objects)? {
+                edge_height_indexes.push(pixel_height_index);
+            }
+        }
+
+        // the silhouette is crossed once on each side of the sphere, both horizontally and vertically
+        assert_eq!(edge_width_indexes.len(), 2, "edges at {:?}", edge_width_indexes);
+        assert_eq!(edge_height_indexes.len(), 2, "edges at {:?}", edge_height_indexes);
+        assert!(edge_width_indexes[0] < center_width_index && edge_width_indexes[1] >= center_width_index);
+        assert!(edge_height_indexes[0] < center_height_index && edge_height_indexes[1] >= center_height_index);
+        // the ring is about 10 pixels away from the center
+        assert!((edge_width_indexes[1] - edge_width_indexes[0]).abs_diff(21) <= 2);
+        assert!(!grid.pixel_is_on_edge(center_height_index, center_width_index, &objects)?);
+        assert!(!grid.pixel_is_on_edge(0, 0, &objects)?);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,179 @@
+use crate::{
+    error::RayTracingError,
+    geometry::{point::Point, vector::Vector, Scalar},
+};
+
+use super::{color::Color, lambert_cosine};
+
+/// How the light received from a point or area light decreases with the distance to it
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Falloff {
+    /// the same at any distance, like the emissive objects of the path tracer
+    #[default]
+    None,
+    /// divided by the squared distance, as a physical light would
+    InverseSquare,
+}
+
+/// Light that is not an object of the scene, it can't be seen by the camera but lights the objects directly
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "type", rename_all = "snake_case")
+)]
+pub enum Light {
+    /// emits in every direction from a single point
+    Point {
+        position: Point,
+        color: Color,
+        intensity: Scalar,
+    },
+    /// parallel light coming from infinitely far away, like the sun
+    Directional {
+        direction: Vector, // where the light goes, from the light to the scene
+        color: Color,
+        intensity: Scalar,
+    },
+    /// emits from the surface of a sphere, which gives soft shadows
+    Area {
+        center: Point,
+        radius: Scalar,
+        color: Color,
+        intensity: Scalar,
+    },
+}
+
+impl Light {
+    pub fn color(&self) -> Color {
+        match self {
+            Light::Point { color, .. }
+            | Light::Directional { color, .. }
+            | Light::Area { color, .. } => *color,
+        }
+    }
+
+    pub fn intensity(&self) -> Scalar {
+        match self {
+            Light::Point { intensity, .. }
+            | Light::Directional { intensity, .. }
+            | Light::Area { intensity, .. } => *intensity,
+        }
+    }
+
+    /// Light received by a surface of normal `normal` at `point`, whether the light is hidden by an object isn't checked
+    /// the area lights are taken as a point at their center
+    pub fn illumination_at(
+        &self,
+        point: &Point,
+        normal: &Vector,
+        falloff: Falloff,
+    ) -> Result<Color, RayTracingError> {
+        let (to_light, distance) = match self {
+            Light::Point { position, .. }
+            | Light::Area {
+                center: position, ..
+            } => {
+                let to_light = Vector::new_from_points(point, position);
+                let distance = to_light.norme_vec();
+                (to_light, Some(distance))
+            }
+            // * a directional light is infinitely far away, no falloff makes sense for it
+            Light::Directional { direction, .. } => (-direction, None),
+        };
+        let attenuation = match (falloff, distance) {
+            (Falloff::InverseSquare, Some(distance)) => 1. / (distance * distance),
+            _ => 1.,
+        };
+        let light_strength = self.intensity() * attenuation * lambert_cosine(normal, &to_light)?;
+        Ok(&self.color() * light_strength)
+    }
+
+    /// Checks the values that can't be enforced by the type, such as a light read from a scene file
+    pub fn validate(&self) -> Result<(), RayTracingError> {
+        let intensity = self.intensity();
+        if !(intensity >= 0. && intensity.is_finite()) {
+            return Err(RayTracingError::CoefficientOOB(
+                intensity,
+                0.,
+                Scalar::INFINITY,
+            ));
+        }
+        match self {
+            Light::Directional { direction, .. } => direction.normalize().map(|_| ()),
+            Light::Area { radius, .. } if !(*radius > 0. && radius.is_finite()) => Err(
+                RayTracingError::CoefficientOOB(*radius, 0., Scalar::INFINITY),
+            ),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::optic::color;
+
+    #[test]
+    fn test_illumination_at_falloff() -> Result<(), RayTracingError> {
+        let light = Light::Point {
+            position: Point::new(0., -10., 0.),
+            color: color::WHITE,
+            intensity: 100.,
+        };
+        let up = Vector::new_from_coordinates(0., -1., 0.);
+        let illumination = |distance: Scalar, falloff: Falloff| -> Result<f32, RayTracingError> {
+            Ok(light
+                .illumination_at(&Point::new(0., -10. + distance, 0.), &up, falloff)?
+                .luminance())
+        };
+
+        let near = illumination(2., Falloff::InverseSquare)?;
+        let far = illumination(4., Falloff::InverseSquare)?;
+
+        assert!((near / far - 4.).abs() < 1e-4);
+        assert!((near - 25.).abs() < 1e-3);
+        assert_eq!(
+            illumination(2., Falloff::None)?,
+            illumination(4., Falloff::None)?
+        );
+        // * a surface facing away from the light gets nothing
+        let facing_away =
+            light.illumination_at(&Point::new(0., 0., 0.), &-up, Falloff::None)?;
+        assert_eq!(facing_away, color::BLACK);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate() {
+        let point_light = |intensity: Scalar| Light::Point {
+            position: Point::new(0., -10., 0.),
+            color: color::WHITE,
+            intensity,
+        };
+
+        assert!(point_light(0.).validate().is_ok());
+        assert!(point_light(100.).validate().is_ok());
+        assert!(point_light(-1.).validate().is_err());
+        assert!(point_light(Scalar::NAN).validate().is_err());
+        let pointless_sun = Light::Directional {
+            direction: Vector::new_from_coordinates(0., 0., 0.),
+            color: color::WHITE,
+            intensity: 1.,
+        };
+        assert!(pointless_sun.validate().is_err());
+        let flat_area_light = Light::Area {
+            center: Point::new(0., -10., 0.),
+            radius: 0.,
+            color: color::WHITE,
+            intensity: 1.,
+        };
+        assert!(flat_area_light.validate().is_err());
+    }
+}
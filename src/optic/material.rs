@@ -1,21 +1,114 @@
-use crate::error::RayTracingError;
+use crate::{
+    error::RayTracingError,
+    geometry::{consts, ray::Ray, vector::Vector, Scalar},
+    object::Object,
+};
 
-use super::color::*;
+use rand_distr::{DistIter, UnitSphere};
+use rand_xorshift::XorShiftRng;
 
-#[derive(Clone, Copy, Debug)]
+use super::{
+    color::*,
+    normal_map::NormalMap,
+    sampling::{uniform_sample, BounceSampler},
+    shading::ShadingContext,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "MaterialDescription", into = "MaterialDescription")
+)]
 pub struct Material {
     pub emission_color: Color,
-    emission_strength: f64,
+    emission_strength: Scalar,
     pub diffusion_coefficients: DiffusionCoefficient,
-    reflection_coeff: f64,
+    reflection_coeff: Scalar,
+    specular_tint: DiffusionCoefficient, // applied to the ray color on mirror bounces and through glass, white when clear
+    roughness: Scalar, // 0 for a perfect mirror, the reflected rays spread more as it goes to 1
+    clearcoat: Scalar, // strength of a glossy clear layer on top of the material, 0 for no coat
+    clearcoat_roughness: Scalar,
+    emission_direction: Option<(Vector, Scalar)>, // unit axis of the cone and half angle in radians, None is omnidirectional
+    ior: Option<Scalar>, // index of refraction of a dielectric such as glass, None for an opaque material
+    dispersion: Option<[Scalar; 3]>, // indices of refraction of the red, green and blue light, None to bend them alike
+    normal_map: Option<NormalMap>, // tilts the normal of the diffuse and mirror bounces, not the one of the clear coat
+}
+
+/// What is written in scene files for a material, it is built again by `Material::new` and the `with_` methods
+/// when it is read, so the coefficients are checked
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct MaterialDescription {
+    emission_color: Color,
+    emission_strength: Scalar,
+    diffusion_coefficients: DiffusionCoefficient,
+    reflection_coeff: Scalar,
+    specular_tint: DiffusionCoefficient,
+    roughness: Scalar,
+    clearcoat: Scalar,
+    clearcoat_roughness: Scalar,
+    emission_direction: Option<(Vector, Scalar)>,
+    ior: Option<Scalar>,
+    dispersion: Option<[Scalar; 3]>,
+    normal_map: Option<NormalMap>,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<MaterialDescription> for Material {
+    type Error = RayTracingError;
+    fn try_from(description: MaterialDescription) -> Result<Self, Self::Error> {
+        let mut material = Material::new(
+            description.emission_color,
+            description.emission_strength,
+            description.diffusion_coefficients,
+            description.reflection_coeff,
+        )?
+        .with_specular_tint(description.specular_tint)
+        .with_roughness(description.roughness)?
+        .with_clearcoat(description.clearcoat, description.clearcoat_roughness)?;
+        if let Some((axis, half_angle)) = description.emission_direction {
+            material = material.with_emission_direction(&axis, half_angle)?;
+        }
+        // * the index of refraction of a dispersive material is its green index
+        material = match (description.dispersion, description.ior) {
+            (Some([ior_r, ior_g, ior_b]), _) => material.with_dispersion(ior_r, ior_g, ior_b)?,
+            (None, Some(ior)) => material.with_ior(ior)?,
+            (None, None) => material,
+        };
+        match description.normal_map {
+            Some(normal_map) => material.with_normal_map(normal_map),
+            None => Ok(material),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<Material> for MaterialDescription {
+    fn from(material: Material) -> Self {
+        MaterialDescription {
+            emission_color: material.emission_color,
+            emission_strength: material.emission_strength,
+            diffusion_coefficients: material.diffusion_coefficients,
+            reflection_coeff: material.reflection_coeff,
+            specular_tint: material.specular_tint,
+            roughness: material.roughness,
+            clearcoat: material.clearcoat,
+            clearcoat_roughness: material.clearcoat_roughness,
+            emission_direction: material.emission_direction,
+            ior: material.ior,
+            dispersion: material.dispersion,
+            normal_map: material.normal_map,
+        }
+    }
 }
 
 impl Material {
     pub fn new(
         emission_color: Color,
-        emission_strength: f64,
+        emission_strength: Scalar,
         diffusion_coefficients: DiffusionCoefficient,
-        reflection_coeff: f64,
+        reflection_coeff: Scalar,
     ) -> Result<Self, RayTracingError> {
         if !(0. ..=1.).contains(&reflection_coeff) {
             Err(RayTracingError::CoefficientOOB(reflection_coeff, 0., 1.))
@@ -27,32 +120,579 @@ impl Material {
                 emission_strength,
                 diffusion_coefficients,
                 reflection_coeff,
+                specular_tint: FULL_DIFFUSION,
+                roughness: 0.,
+                clearcoat: 0.,
+                clearcoat_roughness: 0.,
+                emission_direction: None,
+                ior: None,
+                dispersion: None,
+                normal_map: None,
             })
         }
     }
 
-    pub fn emission_strength(&self) -> f64 {
+    /// Same as `new` but every coefficient is clamped to its range instead of being an error
+    pub fn new_clamped(
+        emission_color: Color,
+        emission_strength: Scalar,
+        diffusion_color: Color,
+        reflection_coeff: Scalar,
+    ) -> Self {
+        let clamp = |coefficient: Scalar| if coefficient.is_nan() { 0. } else { coefficient.clamp(0., 1.) };
+        let (dr, dg, db) = diffusion_color.get_components();
+        Material {
+            emission_color,
+            emission_strength: clamp(emission_strength),
+            diffusion_coefficients: DiffusionCoefficient::new_clamped(dr, dg, db),
+            reflection_coeff: clamp(reflection_coeff),
+            ..Material::default()
+        }
+    }
+
+    /// One matte material diffusing each color of the palette
+    pub fn palette(colors: &[Color]) -> Vec<Material> {
+        colors
+            .iter()
+            .map(|color| Material::new_clamped(BLACK, 0., *color, 0.))
+            .collect()
+    }
+
+    /// Gives the materials to the objects in turn, starting over from the first material when they run out
+    pub fn assign_round_robin(materials: &[Material], objects: &mut [Object]) {
+        if materials.is_empty() {
+            return;
+        }
+        for (object, material) in objects.iter_mut().zip(materials.iter().cycle()) {
+            object.material = *material;
+        }
+    }
+
+    /// Turns the emission into a spotlight: light is only emitted in the cone of half angle `half_angle` (radians) around `axis`
+    pub fn with_emission_direction(
+        mut self,
+        axis: &Vector,
+        half_angle: Scalar,
+    ) -> Result<Self, RayTracingError> {
+        if !(0. ..=consts::PI).contains(&half_angle) || half_angle == 0. {
+            Err(RayTracingError::CoefficientOOB(
+                half_angle,
+                0.,
+                consts::PI,
+            ))
+        } else {
+            self.emission_direction = Some((axis.normalize()?, half_angle));
+            Ok(self)
+        }
+    }
+
+    /// Makes the reflection glossy instead of mirror-like, `roughness` is between 0 (mirror) and 1
+    pub fn with_roughness(mut self, roughness: Scalar) -> Result<Self, RayTracingError> {
+        if !(0. ..=1.).contains(&roughness) {
+            Err(RayTracingError::CoefficientOOB(roughness, 0., 1.))
+        } else {
+            self.roughness = roughness;
+            Ok(self)
+        }
+    }
+
+    /// Adds a clear varnish over the material, like on car paint, both coefficients are between 0 and 1
+    pub fn with_clearcoat(mut self, clearcoat: Scalar, clearcoat_roughness: Scalar) -> Result<Self, RayTracingError> {
+        if !(0. ..=1.).contains(&clearcoat) {
+            Err(RayTracingError::CoefficientOOB(clearcoat, 0., 1.))
+        } else if !(0. ..=1.).contains(&clearcoat_roughness) {
+            Err(RayTracingError::CoefficientOOB(clearcoat_roughness, 0., 1.))
+        } else {
+            self.clearcoat = clearcoat;
+            self.clearcoat_roughness = clearcoat_roughness;
+            Ok(self)
+        }
+    }
+
+    /// Colors the reflection of the mirror part of the material, like for gold or copper
+    pub fn with_specular_tint(mut self, specular_tint: DiffusionCoefficient) -> Self {
+        self.specular_tint = specular_tint;
+        self
+    }
+
+    /// Makes the material a dielectric like glass or water, which reflects or refracts every ray instead of diffusing it,
+    /// `ior` is its index of refraction, 1.5 for glass
+    pub fn with_ior(mut self, ior: Scalar) -> Result<Self, RayTracingError> {
+        if !(ior >= 1. && ior.is_finite()) {
+            Err(RayTracingError::CoefficientOOB(ior, 1., Scalar::INFINITY))
+        } else {
+            self.ior = Some(ior);
+            Ok(self)
+        }
+    }
+
+    /// Makes the material a dielectric that bends the red, green and blue light by their own index of refraction,
+    /// like a prism, each ray going through it carries one of the channels picked at random so it takes more rays
+    /// to converge, `ior` is the green index
+    pub fn with_dispersion(mut self, ior_r: Scalar, ior_g: Scalar, ior_b: Scalar) -> Result<Self, RayTracingError> {
+        self = self.with_ior(ior_g)?;
+        for ior in [ior_r, ior_b] {
+            if !(ior >= 1. && ior.is_finite()) {
+                return Err(RayTracingError::CoefficientOOB(ior, 1., Scalar::INFINITY));
+            }
+        }
+        self.dispersion = Some([ior_r, ior_g, ior_b]);
+        Ok(self)
+    }
+
+    /// Gives the surface a relief without changing its shape, the rays still leave from the side given by the shape
+    pub fn with_normal_map(mut self, normal_map: NormalMap) -> Result<Self, RayTracingError> {
+        normal_map.validate()?;
+        self.normal_map = Some(normal_map);
+        Ok(self)
+    }
+
+    pub fn emission_strength(&self) -> Scalar {
         self.emission_strength
     }
 
-    pub fn reflection_coeff(&self) -> f64 {
+    pub fn emission_direction(&self) -> Option<(Vector, Scalar)> {
+        self.emission_direction
+    }
+
+    /// Emission strength seen from the direction `outgoing_direction` (going from the emitting surface to the viewer)
+    pub fn emission_strength_towards(
+        &self,
+        outgoing_direction: &Vector,
+    ) -> Result<Scalar, RayTracingError> {
+        match self.emission_direction {
+            None => Ok(self.emission_strength),
+            Some((axis, half_angle)) => {
+                let cos_angle = outgoing_direction.normalize()?.scalar_product(&axis);
+                let cos_half_angle = half_angle.cos();
+                if cos_angle <= cos_half_angle {
+                    Ok(0.)
+                } else {
+                    // full strength on the axis, linearly falling off to 0 at the edge of the cone
+                    Ok(self.emission_strength * (cos_angle - cos_half_angle) / (1. - cos_half_angle))
+                }
+            }
+        }
+    }
+
+    pub fn reflection_coeff(&self) -> Scalar {
         self.reflection_coeff
     }
+
+    pub fn roughness(&self) -> Scalar {
+        self.roughness
+    }
+
+    pub fn specular_tint(&self) -> DiffusionCoefficient {
+        self.specular_tint
+    }
+
+    pub fn ior(&self) -> Option<Scalar> {
+        self.ior
+    }
+
+    pub fn dispersion(&self) -> Option<[Scalar; 3]> {
+        self.dispersion
+    }
+
+    pub fn normal_map(&self) -> Option<NormalMap> {
+        self.normal_map
+    }
+
+    /// Normal used to shade the point of normal `normal` given by the shape, tilted by the normal map if there is one, normalized
+    pub fn shading_normal(&self, normal: &Vector, uv: (Scalar, Scalar)) -> Result<Vector, RayTracingError> {
+        match self.normal_map {
+            Some(normal_map) => normal_map.perturb(normal, uv),
+            None => normal.normalize(),
+        }
+    }
+
+    /// Every bounce on the material is diffuse, with neither mirror reflection, clear coat nor refraction
+    pub fn is_purely_diffuse(&self) -> bool {
+        self.reflection_coeff == 0. && self.clearcoat == 0. && self.ior.is_none()
+    }
+
+    pub fn clearcoat(&self) -> Scalar {
+        self.clearcoat
+    }
+
+    pub fn clearcoat_roughness(&self) -> Scalar {
+        self.clearcoat_roughness
+    }
+
+    /// Picks where a ray goes after hitting the material along with the attenuation to apply to its color,
+    /// None if the material absorbs all the light
+    pub fn scatter(
+        &self,
+        ctx: &ShadingContext,
+        sampler: BounceSampler,
+        unit_sphere_iter: &mut DistIter<UnitSphere, XorShiftRng, [Scalar; 3]>,
+    ) -> Result<Option<(Ray, Color)>, RayTracingError> {
+        let incoming_ray = Ray::new_unchecked(ctx.point, ctx.incoming);
+        if let Some(iors) = self.dispersion {
+            // the channel is picked with a probability of 1/3, the others are left black, so it carries 3 times the light
+            let channel = ((uniform_sample(unit_sphere_iter)? * 3.) as usize).min(2);
+            let mut channel_weights = [0.; 3];
+            channel_weights[channel] = 3.;
+            let [r, g, b] = channel_weights;
+            let scattered = self.scatter_dielectric(&incoming_ray, ctx, iors[channel], unit_sphere_iter)?;
+            return Ok(scattered.map(|(ray, attenuation)| (ray, &attenuation * &Color::new_unbounded(r, g, b))));
+        }
+        if let Some(ior) = self.ior {
+            return self.scatter_dielectric(&incoming_ray, ctx, ior, unit_sphere_iter);
+        }
+        // the clear coat reflects the part of the light given by the Fresnel term, the rest goes through to the base layer
+        // the probability of each layer is its weight, so the attenuation doesn't have to be corrected
+        let clearcoat_probability = if self.clearcoat > 0. {
+            let cos_theta = ctx.incoming.scalar_product(&ctx.normal.normalize()?).abs();
+            self.clearcoat_reflection_probability(cos_theta)
+        } else {
+            0.
+        };
+        // a reflective material sends the ray in the mirror direction with a probability of its reflection coefficient
+        let (scattered_ray, attenuation) = if clearcoat_probability > 0.
+            && uniform_sample(unit_sphere_iter)? < clearcoat_probability
+        {
+            // the coat is a clear dielectric, it doesn't tint the reflection
+            let reflected_ray = incoming_ray.glossy_reflect_about_normal(
+                &ctx.point,
+                &ctx.normal,
+                self.clearcoat_roughness,
+                unit_sphere_iter,
+            )?;
+            (reflected_ray, WHITE)
+        } else if self.reflection_coeff > 0. && uniform_sample(unit_sphere_iter)? < self.reflection_coeff {
+            // the mirror reflection is only tinted by the specular tint, not by the diffusion coefficients
+            let reflected_ray = incoming_ray.glossy_reflect_about_normal(
+                &ctx.point,
+                &ctx.shading_normal,
+                self.roughness,
+                unit_sphere_iter,
+            )?;
+            (self.leave_surface(ctx, reflected_ray.direction()), &WHITE * &self.specular_tint)
+        } else {
+            // the ray bounces randomly, the sampler takes care of the Lambert reflectance law
+            let (bounce_ray, sampling_weight) =
+                sampler.sample_bounce(&ctx.point, &ctx.shading_normal, unit_sphere_iter)?;
+            (
+                self.leave_surface(ctx, bounce_ray.direction()),
+                &(&WHITE * &self.diffusion_coefficients) * sampling_weight,
+            )
+        };
+        if attenuation == BLACK {
+            Ok(None)
+        } else {
+            Ok(Some((scattered_ray, attenuation)))
+        }
+    }
+
+    /// Ray leaving the hit point, the direction picked with the shading normal is mirrored back to the side of the surface
+    /// the shading normal is on when it goes through, so that a normal map doesn't let the light leak through the shape
+    fn leave_surface(&self, ctx: &ShadingContext, direction: Vector) -> Ray {
+        let goes_through = self.normal_map.is_some()
+            && direction.scalar_product(&ctx.normal) * ctx.shading_normal.scalar_product(&ctx.normal) < 0.;
+        let direction = if goes_through {
+            direction.reflect(&ctx.normal).unwrap_or(direction)
+        } else {
+            direction
+        };
+        Ray::spawn(&ctx.point, direction, &ctx.normal)
+    }
+
+    /// The ray is reflected with the probability given by Schlick's approximation of the Fresnel term, or else refracted,
+    /// a ray that can't leave the material is always reflected
+    fn scatter_dielectric(
+        &self,
+        incoming_ray: &Ray,
+        ctx: &ShadingContext,
+        ior: Scalar,
+        unit_sphere_iter: &mut DistIter<UnitSphere, XorShiftRng, [Scalar; 3]>,
+    ) -> Result<Option<(Ray, Color)>, RayTracingError> {
+        // * the normal of the shape points out of the material
+        let (ior_in, ior_out) = if ctx.front_face { (1., ior) } else { (ior, 1.) };
+        let attenuation = &WHITE * &self.specular_tint;
+        let Some(refracted_ray) = incoming_ray.refracted_ray(&ctx.point, &ctx.normal, ior_in, ior_out)? else {
+            return Ok(Some((incoming_ray.reflect_about_normal(&ctx.point, &ctx.normal)?, attenuation)));
+        };
+        // the reflectance depends on the angle on the side of the smaller index
+        let cos_theta = if ior_in <= ior_out {
+            ctx.incoming.scalar_product(&ctx.normal.normalize()?).abs()
+        } else {
+            refracted_ray.normalized_direction()?.scalar_product(&ctx.normal.normalize()?).abs()
+        };
+        let normal_reflectance = ((ior_in - ior_out) / (ior_in + ior_out)).powi(2);
+        let reflectance = normal_reflectance + (1. - normal_reflectance) * (1. - cos_theta).powi(5);
+        if uniform_sample(unit_sphere_iter)? < reflectance {
+            Ok(Some((incoming_ray.reflect_about_normal(&ctx.point, &ctx.normal)?, attenuation)))
+        } else {
+            Ok(Some((refracted_ray, attenuation)))
+        }
+    }
+
+    /// Light sent back from a constant `ambient` light coming from everywhere, only the diffuse part of the material reflects it
+    pub fn ambient_light(&self, ambient: &Color) -> Color {
+        if self.ior.is_some() {
+            return BLACK;
+        }
+        &(ambient * &self.diffusion_coefficients) * (1. - self.reflection_coeff)
+    }
+
+    /// Part of the light reflected by the clear coat for a ray making an angle of cosine `cos_theta` with the normal,
+    /// using Schlick's approximation of the Fresnel term for a varnish of refractive index 1.5
+    pub fn clearcoat_reflection_probability(&self, cos_theta: Scalar) -> Scalar {
+        let normal_reflectance = 0.04; // ((1.5 - 1) / (1.5 + 1))^2
+        self.clearcoat * (normal_reflectance + (1. - normal_reflectance) * (1. - cos_theta).powi(5))
+    }
 }
 
 impl Default for Material {
     fn default() -> Self {
-        let white_diff = match WHITE.to_diffusion_coefficient() {
-            Ok(diff) => diff,
-            _ => panic!(
-                "White color should always be able to convert to diffusion coefficient, check code"
-            ),
-        };
         Material {
             emission_color: BLACK,
             emission_strength: 0.,
-            diffusion_coefficients: white_diff,
+            diffusion_coefficients: FULL_DIFFUSION,
             reflection_coeff: 0.,
+            specular_tint: FULL_DIFFUSION,
+            roughness: 0.,
+            clearcoat: 0.,
+            clearcoat_roughness: 0.,
+            emission_direction: None,
+            ior: None,
+            dispersion: None,
+            normal_map: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geometry::{point::Point, shape::Sphere};
+    use crate::object::VisibilityFlags;
+    use rand::SeedableRng;
+    use rand_distr::Distribution;
+
+    use super::*;
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_deserialize_checks_coefficients() -> Result<(), Box<dyn std::error::Error>> {
+        let glass = Material::default().with_roughness(0.5)?.with_ior(1.5)?;
+        let json = serde_json::to_string(&glass)?;
+
+        assert_eq!(serde_json::from_str::<Material>(&json)?, glass);
+        for (valid, invalid) in [
+            ("\"emission_strength\":0.0", "\"emission_strength\":2.0"),
+            ("\"reflection_coeff\":0.0", "\"reflection_coeff\":-0.5"),
+            ("\"roughness\":0.5", "\"roughness\":1.5"),
+            ("\"ior\":1.5", "\"ior\":0.5"),
+        ] {
+            assert!(json.contains(valid), "{} should contain {}", json, valid);
+            assert!(serde_json::from_str::<Material>(&json.replace(valid, invalid)).is_err(), "{} is read", invalid);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_default() {
+        let material = Material::default();
+
+        assert_eq!(material.diffusion_coefficients.get_components(), (1., 1., 1.));
+        assert_eq!(material.emission_strength(), 0.);
+        assert_eq!(material.emission_color, BLACK);
+    }
+
+    #[test]
+    fn test_palette() -> Result<(), RayTracingError> {
+        let colors = [RED, Color::new(0.2, 0.4, 0.6)?, &WHITE * 1.5];
+        let palette = Material::palette(&colors);
+
+        assert_eq!(palette.len(), 3);
+        assert_eq!(palette[0].diffusion_coefficients.get_components(), RED.get_components());
+        assert_eq!(palette[1].diffusion_coefficients.get_components(), (0.2, 0.4, 0.6));
+        // * out of range colors are clamped
+        assert_eq!(palette[2].diffusion_coefficients.get_components(), (1., 1., 1.));
+        for material in &palette {
+            assert_eq!(material.emission_strength(), 0.);
+            assert_eq!(material.reflection_coeff(), 0.);
+        }
+
+        let sphere = Sphere::new_from_radius(&Point::new(0., 0., 0.), 1.);
+        let mut objects = vec![
+            Object {
+                shape: Box::new(sphere),
+                material: Material::default(),
+                visibility: VisibilityFlags::default(),
+            };
+            5
+        ];
+        Material::assign_round_robin(&palette, &mut objects);
+        let assigned: Vec<Material> = objects.iter().map(|object| object.material).collect();
+        assert_eq!(assigned, vec![palette[0], palette[1], palette[2], palette[0], palette[1]]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scatter_diffuse() -> Result<(), RayTracingError> {
+        let albedo = Color::new(0.8, 0.5, 0.2)?;
+        let material = Material::new(BLACK, 0., albedo.to_diffusion_coefficient()?, 0.)?;
+        let object = Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(0., 0., 5.), 1.)),
+            material,
+            visibility: VisibilityFlags::default(),
+        };
+        let ray = Ray::new(Point::new(0., 0., 0.), Vector::new_from_coordinates(0., 0., 1.))?;
+        let hit_info = ray.intersect(&object)?.expect("the ray goes through the sphere");
+        let ctx = ShadingContext::new(&hit_info, &ray)?;
+        let rng = XorShiftRng::seed_from_u64(5);
+        let mut unit_sphere_iter: DistIter<UnitSphere, XorShiftRng, [Scalar; 3]> = UnitSphere.sample_iter(rng);
+
+        for _ in 0..20 {
+            let (scattered_ray, attenuation) = material
+                .scatter(&ctx, BounceSampler::CosineWeighted, &mut unit_sphere_iter)?
+                .expect("the material diffuses light");
+            assert!(scattered_ray.direction().scalar_product(&ctx.normal) > 0.);
+            assert_eq!(attenuation, albedo);
         }
+        let black_material = Material::new(WHITE, 1., BLACK.to_diffusion_coefficient()?, 0.)?;
+        assert!(black_material
+            .scatter(&ctx, BounceSampler::CosineWeighted, &mut unit_sphere_iter)?
+            .is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scatter_dielectric() -> Result<(), RayTracingError> {
+        let glass = Material::default().with_ior(1.5)?;
+        let object = Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(0., 0., 5.), 1.)),
+            material: glass,
+            visibility: VisibilityFlags::default(),
+        };
+        let ray = Ray::new(Point::new(0., 0., 0.), Vector::new_from_coordinates(0., 0., 1.))?;
+        let hit_info = ray.intersect(&object)?.expect("the ray goes through the sphere");
+        let ctx = ShadingContext::new(&hit_info, &ray)?;
+        let rng = XorShiftRng::seed_from_u64(5);
+        let mut unit_sphere_iter: DistIter<UnitSphere, XorShiftRng, [Scalar; 3]> = UnitSphere.sample_iter(rng);
+
+        // * head on, glass reflects 4% of the light and lets the rest through without bending it
+        let mut number_of_refractions = 0;
+        for _ in 0..200 {
+            let (scattered_ray, attenuation) = glass
+                .scatter(&ctx, BounceSampler::CosineWeighted, &mut unit_sphere_iter)?
+                .expect("clear glass doesn't absorb light");
+            let direction = scattered_ray.normalized_direction()?;
+            assert_eq!(attenuation, WHITE);
+            assert!((direction.z.abs() - 1.).abs() < 1e-6);
+            if direction.z > 0. {
+                number_of_refractions += 1;
+            }
+        }
+        assert!(number_of_refractions > 180 && number_of_refractions < 200);
+        assert!(!glass.is_purely_diffuse());
+        assert_eq!(glass.ambient_light(&WHITE), BLACK);
+        assert!(Material::default().with_ior(0.5).is_err());
+        assert!(Material::default().with_ior(Scalar::NAN).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_dispersion_separates_colors() -> Result<(), RayTracingError> {
+        let prism_glass = Material::default().with_dispersion(1.5, 1.52, 1.54)?;
+        let object = Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(0., 0., 5.), 1.)),
+            material: prism_glass,
+            visibility: VisibilityFlags::default(),
+        };
+        // * the ray comes in at an angle of sine 0.6 with the normal
+        let ray = Ray::new(Point::new(0.6, 0., 0.), Vector::new_from_coordinates(0., 0., 1.))?;
+        let hit_info = ray.intersect(&object)?.expect("the ray goes through the sphere");
+        let ctx = ShadingContext::new(&hit_info, &ray)?;
+        let rng = XorShiftRng::seed_from_u64(5);
+        let mut unit_sphere_iter: DistIter<UnitSphere, XorShiftRng, [Scalar; 3]> = UnitSphere.sample_iter(rng);
+
+        let mut refracted_directions: [Option<Vector>; 3] = [None; 3];
+        let mut number_of_rays_per_channel = [0; 3];
+        for _ in 0..300 {
+            let (scattered_ray, attenuation) = prism_glass
+                .scatter(&ctx, BounceSampler::CosineWeighted, &mut unit_sphere_iter)?
+                .expect("clear glass doesn't absorb light");
+            let (r, g, b) = attenuation.get_components();
+            let channel = [r, g, b].iter().position(|weight| *weight > 0.).expect("one channel is carried");
+            // * each ray carries a single channel, with 3 times its light
+            assert_eq!([r, g, b].iter().filter(|weight| **weight > 0.).count(), 1);
+            assert_eq!([r, g, b][channel], 3.);
+            number_of_rays_per_channel[channel] += 1;
+            let direction = scattered_ray.normalized_direction()?;
+            if direction.z > 0. {
+                refracted_directions[channel] = Some(direction);
+            }
+        }
+        let [Some(red_direction), Some(green_direction), Some(blue_direction)] = refracted_directions else {
+            panic!("every channel is refracted at least once");
+        };
+
+        assert!(number_of_rays_per_channel.iter().all(|number_of_rays| *number_of_rays > 70));
+        // * the refracted rays bend towards the center of the sphere, the blue light more than the red one
+        assert!(blue_direction.x < green_direction.x && green_direction.x < red_direction.x);
+        assert!(red_direction.angle_with(&blue_direction) > 5e-3);
+        assert!(Material::default().with_dispersion(1.5, 0.5, 1.5).is_err());
+        assert!(Material::default().with_dispersion(1.5, 1.5, Scalar::INFINITY).is_err());
+        assert_eq!(prism_glass.ior(), Some(1.52));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_clearcoat_reflection_probability() -> Result<(), RayTracingError> {
+        let coated = Material::default().with_clearcoat(1., 0.)?;
+        let half_coated = Material::default().with_clearcoat(0.5, 0.)?;
+
+        assert!((coated.clearcoat_reflection_probability(1.) - 0.04).abs() < 1e-12);
+        assert!((coated.clearcoat_reflection_probability(0.) - 1.).abs() < 1e-12);
+        assert!(coated.clearcoat_reflection_probability(0.5) < coated.clearcoat_reflection_probability(0.2));
+        assert!((half_coated.clearcoat_reflection_probability(1.) - 0.02).abs() < 1e-12);
+        assert_eq!(Material::default().clearcoat_reflection_probability(0.), 0.);
+        assert!(Material::default().with_clearcoat(1.2, 0.).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_emission_strength_towards() -> Result<(), RayTracingError> {
+        let omnidirectional_light = Material::new(WHITE, 0.8, BLACK.to_diffusion_coefficient()?, 0.)?;
+        let spotlight = omnidirectional_light.with_emission_direction(
+            &Vector::new_from_coordinates(0., 3., 0.),
+            consts::PI / 18.,
+        )?;
+
+        let light_position = Point::new(0., 0., 0.);
+        let on_axis_point = Point::new(0., 10., 0.);
+        let off_axis_point = Point::new(-4., 10., 0.);
+        let to_on_axis_point = Vector::new_from_points(&light_position, &on_axis_point);
+        let to_off_axis_point = Vector::new_from_points(&light_position, &off_axis_point);
+
+        assert_eq!(spotlight.emission_strength_towards(&to_on_axis_point)?, 0.8);
+        assert_eq!(spotlight.emission_strength_towards(&to_off_axis_point)?, 0.);
+        assert_eq!(omnidirectional_light.emission_strength_towards(&to_off_axis_point)?, 0.8);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_with_emission_direction_invalid() -> Result<(), RayTracingError> {
+        let light = Material::new(WHITE, 1., BLACK.to_diffusion_coefficient()?, 0.)?;
+        let axis = Vector::new_from_coordinates(0., 1., 0.);
+
+        assert!(light.with_emission_direction(&axis, 0.).is_err());
+        assert!(light.with_emission_direction(&axis, 4.).is_err());
+        assert!(light
+            .with_emission_direction(&Vector::new_from_coordinates(0., 0., 0.), 0.5)
+            .is_err());
+
+        Ok(())
     }
 }
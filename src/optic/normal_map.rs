@@ -0,0 +1,107 @@
+use crate::{
+    error::RayTracingError,
+    geometry::{consts, vector::Vector, Scalar},
+};
+
+/// Procedural relief tilting the shading normal of a material, the surface itself stays flat
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "type", rename_all = "snake_case")
+)]
+pub enum NormalMap {
+    /// bumps laid out like an egg crate, `frequency` of them along each side of the uv square,
+    /// `strength` is the steepest slope of the bumps, 0 for a flat surface
+    Bumps { frequency: Scalar, strength: Scalar },
+}
+
+impl NormalMap {
+    /// Normal at the uv in the tangent frame of the surface, z going along the normal of the surface, not normalized
+    pub fn tangent_space_normal(&self, uv: (Scalar, Scalar)) -> Vector {
+        match self {
+            NormalMap::Bumps {
+                frequency,
+                strength,
+            } => {
+                let (u_phase, v_phase) = (2. * consts::PI * frequency * uv.0, 2. * consts::PI * frequency * uv.1);
+                // * the height is strength * sin(u_phase) * sin(v_phase) / (2 pi frequency), the normal leans against its slope
+                Vector::new_from_coordinates(
+                    -strength * u_phase.cos() * v_phase.sin(),
+                    -strength * u_phase.sin() * v_phase.cos(),
+                    1.,
+                )
+            }
+        }
+    }
+
+    /// Normal at the uv of a surface of normal `normal`, normalized
+    pub fn perturb(&self, normal: &Vector, uv: (Scalar, Scalar)) -> Result<Vector, RayTracingError> {
+        let [tangent, bitangent] = normal.tangent_plane_vectors()?;
+        let tangent_space_normal = self.tangent_space_normal(uv);
+        ((tangent_space_normal.x * &tangent)
+            + (tangent_space_normal.y * &bitangent)
+            + (tangent_space_normal.z * &normal.normalize()?))
+            .normalize()
+    }
+
+    /// Checks the values that can't be enforced by the type, such as a map read from a scene file
+    pub fn validate(&self) -> Result<(), RayTracingError> {
+        match self {
+            NormalMap::Bumps { frequency, .. } if !(*frequency > 0. && frequency.is_finite()) => Err(
+                RayTracingError::CoefficientOOB(*frequency, 0., Scalar::INFINITY),
+            ),
+            NormalMap::Bumps { strength, .. } if !(*strength >= 0. && strength.is_finite()) => Err(
+                RayTracingError::CoefficientOOB(*strength, 0., Scalar::INFINITY),
+            ),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_perturb() -> Result<(), RayTracingError> {
+        let normal = Vector::new_from_coordinates(0., 0., -3.);
+        let bumps = NormalMap::Bumps {
+            frequency: 4.,
+            strength: 0.5,
+        };
+        let flat_bumps = NormalMap::Bumps {
+            frequency: 4.,
+            strength: 0.,
+        };
+
+        let perturbed_normals = [(0.1, 0.3), (0.4, 0.05), (0.7, 0.9)]
+            .map(|uv| bumps.perturb(&normal, uv))
+            .into_iter()
+            .collect::<Result<Vec<Vector>, RayTracingError>>()?;
+
+        for perturbed_normal in &perturbed_normals {
+            assert!((perturbed_normal.norme_vec() - 1.).abs() < 1e-6);
+            // * the normal is tilted but stays on the same side of the surface
+            assert!(perturbed_normal.scalar_product(&normal) > 0.);
+            assert!(perturbed_normal.angle_with(&normal) > 1e-2);
+        }
+        assert!(perturbed_normals[0] != perturbed_normals[1]);
+        assert!(flat_bumps.perturb(&normal, (0.1, 0.3))? == normal.normalize()?);
+        assert!(bumps.validate().is_ok());
+        assert!(NormalMap::Bumps {
+            frequency: 0.,
+            strength: 0.5
+        }
+        .validate()
+        .is_err());
+        assert!(NormalMap::Bumps {
+            frequency: 4.,
+            strength: -1.
+        }
+        .validate()
+        .is_err());
+
+        Ok(())
+    }
+}
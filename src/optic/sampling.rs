@@ -0,0 +1,53 @@
+use crate::{
+    error::RayTracingError,
+    geometry::{point::Point, ray::Ray, vector::Vector, Scalar},
+};
+
+use rand_distr::{DistIter, UnitSphere};
+use rand_xorshift::XorShiftRng;
+
+use super::lambert_cosine;
+
+/// Uniform number in [0, 1], the z coordinate of a uniform point on the unit sphere is uniform in [-1, 1]
+pub(crate) fn uniform_sample(
+    unit_sphere_iter: &mut DistIter<UnitSphere, XorShiftRng, [Scalar; 3]>,
+) -> Result<Scalar, RayTracingError> {
+    match unit_sphere_iter.next() {
+        Some([_, _, z]) => Ok((z + 1.) / 2.),
+        None => Err(RayTracingError::IteratorDepleted()),
+    }
+}
+
+/// How the direction of a diffuse bounce is picked on the hemisphere above the hit point
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum BounceSampler {
+    /// directions follow a cos θ distribution, which cancels out the Lambert cosine term
+    #[default]
+    CosineWeighted,
+    /// directions are uniform on the hemisphere, the cos θ term has to be applied to the throughput
+    Uniform,
+}
+
+impl BounceSampler {
+    /// Returns the bounced ray along with the weight to apply to the ray color on top of the diffusion coefficients
+    pub fn sample_bounce(
+        &self,
+        point: &Point,
+        normal: &Vector,
+        unit_sphere_iter: &mut DistIter<UnitSphere, XorShiftRng, [Scalar; 3]>,
+    ) -> Result<(Ray, Scalar), RayTracingError> {
+        match self {
+            BounceSampler::CosineWeighted => Ok((
+                Ray::cos_weighted_random_ray_unit_sphere(point, normal, unit_sphere_iter)?,
+                1.,
+            )),
+            BounceSampler::Uniform => {
+                let ray = Ray::uniform_weighted_random_ray(point, normal, unit_sphere_iter)?;
+                // the lambertian brdf is albedo / pi and the pdf of the uniform hemisphere is 1 / 2pi
+                // so the estimator is albedo * cos θ * 2pi / pi = albedo * 2 cos θ
+                let cos_theta = lambert_cosine(normal, &ray.direction())?;
+                Ok((ray, 2. * cos_theta))
+            }
+        }
+    }
+}
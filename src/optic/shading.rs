@@ -0,0 +1,81 @@
+use crate::{
+    error::RayTracingError,
+    geometry::{
+        point::Point,
+        ray::{HitInfo, Ray},
+        vector::Vector,
+        Scalar,
+    },
+};
+
+/// What a material needs to know about the hit point to shade it
+#[derive(Debug, Clone, Copy)]
+pub struct ShadingContext {
+    pub point: Point,
+    pub normal: Vector, // normal given by the shape, not flipped towards the incoming ray
+    pub shading_normal: Vector, // `normal` tilted by the normal map of the material, on the same side, normalized
+    pub incoming: Vector, // unit direction of the ray arriving at the point
+    pub uv: (Scalar, Scalar), // position on the surface given by the shape, both between 0 and 1
+    pub front_face: bool, // the ray arrives on the side the normal points to
+}
+
+impl ShadingContext {
+    pub fn new(hit_info: &HitInfo, ray: &Ray) -> Result<Self, RayTracingError> {
+        let incoming = ray.normalized_direction()?;
+        let normal = hit_info.normal;
+        Ok(ShadingContext {
+            point: hit_info.point_hit,
+            normal,
+            shading_normal: hit_info.object.material.shading_normal(&normal, hit_info.uv)?,
+            incoming,
+            uv: hit_info.uv,
+            front_face: incoming.scalar_product(&normal) < 0.,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{geometry::shape::Sphere, object::{Object, VisibilityFlags}, optic::material::Material};
+
+    #[test]
+    fn test_new() -> Result<(), RayTracingError> {
+        let object = Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(0., 0., 5.), 1.)),
+            material: Material::default(),
+            visibility: VisibilityFlags::default(),
+        };
+        let outside_ray = Ray::new(
+            Point::new(0., -5., 5.),
+            Vector::new_from_coordinates(0., 3., 0.),
+        )?;
+        let inside_ray = Ray::new(
+            Point::new(0., 0., 5.),
+            Vector::new_from_coordinates(0., 3., 0.),
+        )?;
+
+        let top_context = ShadingContext::new(
+            &outside_ray
+                .intersect(&object)?
+                .expect("the ray comes from above"),
+            &outside_ray,
+        )?;
+        let bottom_context = ShadingContext::new(
+            &inside_ray
+                .intersect(&object)?
+                .expect("the ray starts inside"),
+            &inside_ray,
+        )?;
+
+        assert_eq!(top_context.point, Point::new(0., -1., 5.));
+        assert!(top_context.incoming == Vector::new_from_coordinates(0., 1., 0.));
+        assert!(top_context.front_face);
+        assert!(!bottom_context.front_face);
+        assert!(top_context.uv.1.abs() < 1e-6);
+        assert!((bottom_context.uv.1 - 1.).abs() < 1e-6);
+        assert!(top_context.shading_normal == top_context.normal.normalize()?);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,239 @@
+use std::sync::OnceLock;
+
+use super::color::Color;
+
+// the spectra are sampled at this many wavelengths evenly spread from 400nm (blue) to 700nm (red)
+const NUMBER_OF_WAVELENGTHS: usize = 16;
+
+/// How the colors of the lights and of the surfaces are combined along a ray
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ColorMode {
+    /// channel by channel, which is what a RGB renderer does
+    #[default]
+    Rgb,
+    /// the colors are turned into smooth spectra that are multiplied wavelength by wavelength, this is slower
+    /// but two colors sharing no channel can still let some light through, such as a yellow light on a magenta surface
+    Spectral,
+}
+
+impl ColorMode {
+    /// Light or reflectance `first_color` filtered by `second_color`
+    pub fn mix(&self, first_color: &Color, second_color: &Color) -> Color {
+        match self {
+            ColorMode::Rgb => first_color * second_color,
+            ColorMode::Spectral => (&Spectrum::from_rgb(first_color) * &Spectrum::from_rgb(second_color)).to_rgb(),
+        }
+    }
+
+    /// The color as it is carried along a ray in this mode
+    pub(crate) fn path_color(&self, color: &Color) -> PathColor {
+        match self {
+            ColorMode::Rgb => PathColor::Rgb(*color),
+            ColorMode::Spectral => PathColor::Spectral(Spectrum::from_rgb(color)),
+        }
+    }
+}
+
+/// Light or filter carried along a ray, kept as a spectrum in the spectral mode so that the filters of the bounces
+/// are all multiplied wavelength by wavelength and the light is only turned back to RGB once the ray is done
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum PathColor {
+    Rgb(Color),
+    Spectral(Spectrum),
+}
+
+impl PathColor {
+    pub fn to_rgb(self) -> Color {
+        match self {
+            PathColor::Rgb(color) => color,
+            PathColor::Spectral(spectrum) => spectrum.to_rgb(),
+        }
+    }
+
+    /// Filtered by `color`, in the mode of the path color
+    pub fn filter(&self, color: &Color) -> PathColor {
+        match self {
+            PathColor::Rgb(path_color) => PathColor::Rgb(path_color * color),
+            PathColor::Spectral(spectrum) => PathColor::Spectral(spectrum * &Spectrum::from_rgb(color)),
+        }
+    }
+}
+
+// * the path colors of a ray are all made in the same mode, the right one is converted if they are not
+impl std::ops::Add for &PathColor {
+    type Output = PathColor;
+    fn add(self, rhs: Self) -> Self::Output {
+        match (self, rhs) {
+            (PathColor::Rgb(color), other_path_color) => PathColor::Rgb(color + &other_path_color.to_rgb()),
+            (PathColor::Spectral(spectrum), other_path_color) => {
+                let other_spectrum = match other_path_color {
+                    PathColor::Rgb(color) => Spectrum::from_rgb(color),
+                    PathColor::Spectral(other_spectrum) => *other_spectrum,
+                };
+                let mut values = spectrum.0;
+                for (value, other_value) in values.iter_mut().zip(&other_spectrum.0) {
+                    *value += other_value;
+                }
+                PathColor::Spectral(Spectrum(values))
+            }
+        }
+    }
+}
+
+/// Value of a light or of a reflectance at each sampled wavelength
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Spectrum([f32; NUMBER_OF_WAVELENGTHS]);
+
+impl Spectrum {
+    /// Smooth spectrum made of the basis spectra weighted by the channels of the color, never negative,
+    /// a white color gives a flat spectrum so that a white light doesn't change the color of a surface
+    pub fn from_rgb(color: &Color) -> Spectrum {
+        let (r, g, b) = color.get_components();
+        let mut values = [0.; NUMBER_OF_WAVELENGTHS];
+        for (wavelength_index, value) in values.iter_mut().enumerate() {
+            let [red_basis, green_basis, blue_basis] = basis_spectra(wavelength_index);
+            *value = r * red_basis + g * green_basis + b * blue_basis;
+        }
+        Spectrum(values)
+    }
+
+    /// Color whose spectrum is the closest to this one, `from_rgb` gives back the same color,
+    /// a channel that would have to be negative is set to 0
+    pub fn to_rgb(&self) -> Color {
+        let mut basis_products = [0.; 3];
+        for (wavelength_index, value) in self.0.iter().enumerate() {
+            for (basis_product, basis) in basis_products.iter_mut().zip(basis_spectra(wavelength_index)) {
+                *basis_product += basis * value;
+            }
+        }
+        // * least squares fit of the spectrum by the basis spectra
+        let [r, g, b] = inverse_gram_matrix().map(|row| {
+            (row[0] * basis_products[0] + row[1] * basis_products[1] + row[2] * basis_products[2]).max(0.)
+        });
+        Color::new_unbounded(r, g, b)
+    }
+}
+
+impl std::ops::Mul for &Spectrum {
+    type Output = Spectrum;
+    fn mul(self, rhs: Self) -> Self::Output {
+        let mut values = self.0;
+        for (value, other_value) in values.iter_mut().zip(&rhs.0) {
+            *value *= other_value;
+        }
+        Spectrum(values)
+    }
+}
+
+/// Values of the red, green and blue basis spectra, the Bernstein polynomials of degree 2 over the visible range,
+/// they are smooth, positive and add up to 1 at every wavelength
+fn basis_spectra(wavelength_index: usize) -> [f32; 3] {
+    let t = wavelength_index as f32 / (NUMBER_OF_WAVELENGTHS - 1) as f32;
+    [t * t, 2. * t * (1. - t), (1. - t) * (1. - t)]
+}
+
+/// Inverse of the matrix of the products of the basis spectra with each other, computed once
+fn inverse_gram_matrix() -> &'static [[f32; 3]; 3] {
+    static INVERSE: OnceLock<[[f32; 3]; 3]> = OnceLock::new();
+    INVERSE.get_or_init(|| {
+        let mut gram_matrix = [[0.; 3]; 3];
+        for wavelength_index in 0..NUMBER_OF_WAVELENGTHS {
+            let basis = basis_spectra(wavelength_index);
+            for (row, first_basis) in gram_matrix.iter_mut().zip(basis) {
+                for (entry, second_basis) in row.iter_mut().zip(basis) {
+                    *entry += first_basis * second_basis;
+                }
+            }
+        }
+        // * inverse from the cofactors, the basis spectra are independent so it exists
+        let cofactor = |row: usize, column: usize| {
+            let (row_1, row_2) = ((row + 1) % 3, (row + 2) % 3);
+            let (column_1, column_2) = ((column + 1) % 3, (column + 2) % 3);
+            gram_matrix[row_1][column_1] * gram_matrix[row_2][column_2]
+                - gram_matrix[row_1][column_2] * gram_matrix[row_2][column_1]
+        };
+        let determinant: f32 = (0..3).map(|column| gram_matrix[0][column] * cofactor(0, column)).sum();
+        let mut inverse = [[0.; 3]; 3];
+        for (row, inverse_row) in inverse.iter_mut().enumerate() {
+            for (column, entry) in inverse_row.iter_mut().enumerate() {
+                *entry = cofactor(column, row) / determinant;
+            }
+        }
+        inverse
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{error::RayTracingError, optic::color};
+
+    fn assert_close(first_color: &Color, second_color: &Color) {
+        let (r_1, g_1, b_1) = first_color.get_components();
+        let (r_2, g_2, b_2) = second_color.get_components();
+        assert!(
+            (r_1 - r_2).abs() < 1e-4 && (g_1 - g_2).abs() < 1e-4 && (b_1 - b_2).abs() < 1e-4,
+            "{:?} != {:?}",
+            first_color,
+            second_color
+        );
+    }
+
+    #[test]
+    fn test_round_trip() -> Result<(), RayTracingError> {
+        for color in [color::WHITE, color::RED, Color::new(0.2, 0.7, 0.4)?, color::BLACK] {
+            assert_close(&Spectrum::from_rgb(&color).to_rgb(), &color);
+        }
+        // * white is flat
+        for value in Spectrum::from_rgb(&color::WHITE).0 {
+            assert!((value - 1.).abs() < 1e-6);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_mix() -> Result<(), RayTracingError> {
+        let yellow = Color::new(1., 1., 0.)?;
+        let magenta = Color::new(1., 0., 1.)?;
+        let surface = Color::new(0.2, 0.7, 0.4)?;
+
+        let rgb_mix = ColorMode::Rgb.mix(&yellow, &magenta);
+        let spectral_mix = ColorMode::Spectral.mix(&yellow, &magenta);
+
+        assert_eq!(rgb_mix, color::RED);
+        // * the spectra of yellow and magenta overlap beyond the red part
+        let (r, g, b) = spectral_mix.get_components();
+        assert!(r >= 0. && g >= 0. && b >= 0.);
+        assert!((r - 1.).abs() + g + b > 0.05);
+        // * a white light leaves the surface as it is
+        assert_close(&ColorMode::Spectral.mix(&color::WHITE, &surface), &surface);
+        assert_close(&ColorMode::Spectral.mix(&yellow, &surface), &ColorMode::Spectral.mix(&surface, &yellow));
+        assert_eq!(ColorMode::default(), ColorMode::Rgb);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_path_color() -> Result<(), RayTracingError> {
+        let yellow = Color::new(1., 1., 0.)?;
+        let magenta = Color::new(1., 0., 1.)?;
+        let cyan = Color::new(0., 1., 1.)?;
+
+        let rgb_path = ColorMode::Rgb.path_color(&cyan).filter(&magenta).filter(&yellow);
+        let spectral_path = ColorMode::Spectral.path_color(&cyan).filter(&magenta).filter(&yellow);
+        let converted_at_each_filter = ColorMode::Spectral.mix(&ColorMode::Spectral.mix(&cyan, &magenta), &yellow);
+
+        assert_eq!(rgb_path.to_rgb(), color::BLACK);
+        // * the three spectra overlap in the green, fitting the spectrum to RGB after each filter gives another amount
+        let (r, g, b) = spectral_path.to_rgb().get_components();
+        assert!(g > 0.5 && r < 0.05 && b < 0.05);
+        assert!((g - converted_at_each_filter.get_components().1).abs() > 0.02);
+        assert_close(
+            &(&spectral_path + &ColorMode::Rgb.path_color(&color::RED)).to_rgb(),
+            &(&spectral_path.to_rgb() + &color::RED),
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,163 @@
+use super::color::Color;
+
+// * the curves bring the unbounded linear channels of the render to the 0..=1 range of the exported image,
+// negative channels are taken as 0 by every curve
+
+/// Scales the channel, see `Grid::auto_exposure` to pick the multiplier
+pub fn exposure(channel: f32, multiplier: f32) -> f32 {
+    channel.max(0.) * multiplier
+}
+
+/// `x / (1 + x)`, never reaches 1 so even the brightest lights keep some detail
+pub fn reinhard(channel: f32) -> f32 {
+    let channel = channel.max(0.);
+    channel / (1. + channel)
+}
+
+/// Fit of the ACES filmic curve by Krzysztof Narkowicz, saturates to 1 around 10
+pub fn aces(channel: f32) -> f32 {
+    let channel = channel.max(0.);
+    (channel * (2.51 * channel + 0.03) / (channel * (2.43 * channel + 0.59) + 0.14)).clamp(0., 1.)
+}
+
+/// `x (1 + x / white_point²) / (1 + x)`, same as `reinhard` for the darker channels but `white_point` is mapped to 1
+pub fn reinhard_extended(channel: f32, white_point: f32) -> f32 {
+    let channel = channel.max(0.);
+    (channel * (1. + channel / (white_point * white_point)) / (1. + channel)).min(1.)
+}
+
+// white point of the Uncharted 2 curve, channels above it are written as 1
+const UNCHARTED2_WHITE: f32 = 11.2;
+
+/// Filmic curve of John Hable for Uncharted 2, normalized so that `UNCHARTED2_WHITE` is mapped to 1
+pub fn uncharted2(channel: f32) -> f32 {
+    // shoulder strength, linear strength, linear angle, toe strength, toe numerator and denominator
+    let (a, b, c, d, e, f) = (0.15, 0.5, 0.1, 0.2, 0.02, 0.3);
+    let curve = |x: f32| (x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f) - e / f;
+    (curve(channel.max(0.)) / curve(UNCHARTED2_WHITE)).min(1.)
+}
+
+/// Encodes a linear channel with a pure power curve, `gamma` is usually 2.2
+pub fn gamma(channel: f32, gamma: f32) -> f32 {
+    channel.max(0.).powf(1. / gamma)
+}
+
+/// Curve selected on a `Grid` to bring the exposed colors to 0..=1 on export
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ToneMap {
+    /// the channels are kept as they are, those above 1 are brought back by the gamut mapping
+    #[default]
+    Clamp,
+    /// see `reinhard`
+    Reinhard,
+    /// see `reinhard_extended`
+    ReinhardExtended { white_point: f32 },
+    /// see `aces`
+    Aces,
+    /// see `uncharted2`
+    Uncharted2,
+}
+
+impl ToneMap {
+    pub fn apply(&self, channel: f32) -> f32 {
+        match self {
+            ToneMap::Clamp => channel,
+            ToneMap::Reinhard => reinhard(channel),
+            ToneMap::ReinhardExtended { white_point } => reinhard_extended(channel, *white_point),
+            ToneMap::Aces => aces(channel),
+            ToneMap::Uncharted2 => uncharted2(channel),
+        }
+    }
+}
+
+/// Applies the curve to each channel of the color
+pub fn tone_map_color(color: &Color, curve: impl Fn(f32) -> f32) -> Color {
+    color.map_channels(curve)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::optic::color;
+
+    type Curve = fn(f32) -> f32;
+
+    // the curves that take any linear value to 0..=1
+    const CURVES: [(&str, Curve); 3] = [
+        ("reinhard", reinhard),
+        ("aces", aces),
+        ("uncharted2", uncharted2),
+    ];
+
+    #[test]
+    fn test_curves_are_monotonic() {
+        let samples: Vec<f32> = (0..2000).map(|index| index as f32 * 0.01).collect();
+        for (name, curve) in CURVES {
+            for pair in samples.windows(2) {
+                assert!(
+                    curve(pair[0]) <= curve(pair[1]),
+                    "{} decreases at {}",
+                    name,
+                    pair[0]
+                );
+            }
+        }
+        for pair in samples.windows(2) {
+            assert!(gamma(pair[0], 2.2) < gamma(pair[1], 2.2));
+            assert!(exposure(pair[0], 2.) < exposure(pair[1], 2.));
+        }
+    }
+
+    #[test]
+    fn test_black_stays_black() {
+        for (name, curve) in CURVES {
+            assert!(curve(0.).abs() < 1e-6, "{} maps 0 to {}", name, curve(0.));
+            assert!(
+                curve(-1.).abs() < 1e-6,
+                "{} maps -1 to {}",
+                name,
+                curve(-1.)
+            );
+        }
+        assert_eq!(gamma(0., 2.2), 0.);
+        assert_eq!(exposure(0., 4.), 0.);
+        assert_eq!(tone_map_color(&color::BLACK, aces), color::BLACK);
+    }
+
+    #[test]
+    fn test_saturation_at_large_inputs() {
+        for (name, curve) in CURVES {
+            assert!(curve(1e4) <= 1., "{} goes above 1", name);
+            assert!(curve(1e4) > 0.999, "{} doesn't saturate", name);
+        }
+        // * reinhard never reaches 1 while the filmic curves clip
+        assert!(reinhard(1e3) < 1.);
+        assert_eq!(aces(100.), 1.);
+        assert_eq!(uncharted2(UNCHARTED2_WHITE * 2.), 1.);
+        assert_eq!(gamma(1., 2.2), 1.);
+        let bright_red = tone_map_color(&(&color::RED * 50.), reinhard);
+        assert_eq!(bright_red.get_components(), (50. / 51., 0., 0.));
+    }
+
+    #[test]
+    fn test_tone_map() {
+        let reinhard_map = ToneMap::Reinhard;
+        let extended_map = ToneMap::ReinhardExtended { white_point: 4. };
+
+        // * reinhard gets ever closer to 1 without reaching it
+        let mut previous_gap = 1.;
+        for channel in [1., 10., 100., 1000.] {
+            let gap = 1. - reinhard_map.apply(channel);
+            assert!(gap > 0. && gap < previous_gap, "{} is mapped to {}", channel, 1. - gap);
+            previous_gap = gap;
+        }
+        assert!(previous_gap < 1e-2);
+        assert_eq!(extended_map.apply(4.), 1.);
+        assert_eq!(extended_map.apply(40.), 1.);
+        assert!(extended_map.apply(0.5) > reinhard_map.apply(0.5));
+        assert_eq!(ToneMap::default(), ToneMap::Clamp);
+        assert_eq!(ToneMap::Clamp.apply(3.), 3.);
+        assert_eq!(ToneMap::Aces.apply(2.), aces(2.));
+        assert_eq!(ToneMap::Uncharted2.apply(2.), uncharted2(2.));
+    }
+}
@@ -0,0 +1,1275 @@
+use crate::{
+    accelerator::Accelerator,
+    error::RayTracingError,
+    geometry::{
+        aabb::Aabb,
+        point::Point,
+        ray::{HitInfo, Ray, RayKind},
+        shape::{Plane, Shape, Sphere},
+        triangle::Triangle,
+        vector::Vector,
+        Scalar, TOLERANCE,
+    },
+    object::{Object, SharedObject, VisibilityFlags},
+    optic::{
+        camera::Camera,
+        color::Color,
+        image::{BounceLimit, Grid, TraceSettings},
+        light::{Falloff, Light},
+        material::Material,
+    },
+};
+
+use rand::SeedableRng;
+use rand_distr::{DistIter, Distribution, UnitSphere};
+use rand_xorshift::XorShiftRng;
+#[cfg(feature = "serde")]
+use crate::{accelerator::Bvh, optic::color};
+
+/// Handle on a material stored in the material table of a `Scene`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MaterialId(usize);
+
+/// Position of the center of an object as a function of the time of the frame
+pub type Trajectory = fn(Scalar) -> Point;
+
+/// Summary of the content of a `Scene`, to debug large scenes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SceneStats {
+    pub object_count: usize,
+    pub triangle_count: usize,
+    pub emissive_count: usize,
+    pub bounding_box: Option<Aabb>, // None if the scene is empty
+    pub memory_bytes: usize, // approximate, counts the allocated capacity of the tables and the boxed shapes of the objects
+}
+
+/// Cloning a scene copies its objects and their shapes, so the copy can be changed without touching the original
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Scene {
+    pub objects: Vec<Object>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub lights: Vec<Light>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub light_falloff: Falloff, // how the light of `lights` decreases with distance
+    materials: Vec<Material>, // * materials shared between objects, only stored once
+    #[cfg_attr(feature = "serde", serde(skip))] // functions can't be written in scene files
+    trajectories: Vec<(usize, Trajectory)>, // index of the moving object and where its center is at each time
+    #[cfg_attr(feature = "serde", serde(skip))]
+    accelerator: Option<Box<dyn Accelerator>>, // ! holds a copy of the objects, it must be set again when they change
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub disable_accelerator: bool, // for debugging, the accelerator is kept but every ray tries every object
+    #[cfg_attr(feature = "serde", serde(skip))]
+    emitters: Vec<Object>, // ! copies of the emissive objects made by `prepare`, it must be called again when they change
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub ambient: Option<Color>, // fill light given to the grids the scene is rendered in, None for pure path tracing
+}
+
+// * the accelerator is left out, it is only a faster way to look at the objects
+impl PartialEq for Scene {
+    fn eq(&self, other: &Self) -> bool {
+        self.objects == other.objects
+            && self.lights == other.lights
+            && self.light_falloff == other.light_falloff
+            && self.materials == other.materials
+            && self.trajectories == other.trajectories
+            && self.ambient == other.ambient
+            && self.disable_accelerator == other.disable_accelerator
+    }
+}
+
+impl Scene {
+    pub fn new(objects: Vec<Object>) -> Self {
+        Scene {
+            objects,
+            lights: Vec::new(),
+            light_falloff: Falloff::default(),
+            materials: Vec::new(),
+            trajectories: Vec::new(),
+            accelerator: None,
+            disable_accelerator: false,
+            emitters: Vec::new(),
+            ambient: None,
+        }
+    }
+
+    /// Makes the renders and the ray queries of the scene use `accelerator`, which should be built from the current
+    /// objects, unless `disable_accelerator` is set
+    pub fn set_accelerator(&mut self, accelerator: Box<dyn Accelerator>) {
+        self.accelerator = Some(accelerator);
+    }
+
+    /// The accelerator the scene is rendered with, None if there is none or it is disabled
+    pub fn accelerator(&self) -> Option<&dyn Accelerator> {
+        if self.disable_accelerator {
+            return None;
+        }
+        self.accelerator.as_deref()
+    }
+
+    /// Closest object hit in front of the ray origin
+    pub fn first_hit(&self, ray: &Ray) -> Result<Option<HitInfo<'_>>, RayTracingError> {
+        match self.accelerator() {
+            Some(accelerator) => accelerator.first_hit(ray),
+            None => ray.first_point_hit_by_ray(&self.object_refs(), None),
+        }
+    }
+
+    /// Makes the object at `object_index` move along `trajectory`, replacing its previous trajectory if it had one
+    pub fn set_trajectory(&mut self, object_index: usize, trajectory: Trajectory) -> Result<(), RayTracingError> {
+        if object_index >= self.objects.len() {
+            return Err(RayTracingError::NoObjectAtIndex(object_index, self.objects.len()));
+        }
+        self.trajectories.retain(|(moving_index, _)| *moving_index != object_index);
+        self.trajectories.push((object_index, trajectory));
+        Ok(())
+    }
+
+    /// The objects with the moving ones placed where they are at `time`, the others are left where they are
+    pub fn objects_at(&self, time: Scalar) -> Vec<Object> {
+        let mut objects = self.objects.clone();
+        for (object_index, trajectory) in &self.trajectories {
+            // * the objects are public, a moving object may have been removed since its trajectory was set
+            let Some(object) = objects.get_mut(*object_index) else {
+                continue;
+            };
+            let offset = Vector::new_from_points(&object.shape.position(), &trajectory(time));
+            object.shape.translate(&offset);
+        }
+        objects
+    }
+
+    /// Registers the emissive objects as lights whose light is sampled at each diffuse hit (next event estimation),
+    /// which brings less noise than waiting for the bounces to hit them, they are still hit by the rays as before
+    pub fn prepare(&mut self) {
+        self.emitters = emitters_of(&self.objects);
+    }
+
+    /// Emissive objects registered by `prepare`
+    pub fn emitters(&self) -> &[Object] {
+        &self.emitters
+    }
+
+    /// Renders the frame at `time` in `grid`, with the ambient light of the scene
+    pub fn render_at(
+        &self,
+        grid: &mut Grid,
+        time: Scalar,
+        number_of_points_per_pixel: usize,
+        bounce_limit: BounceLimit,
+    ) -> Result<(), RayTracingError> {
+        let objects = self.objects_at(time);
+        let object_refs: Vec<&Object> = objects.iter().collect();
+        grid.ambient = self.ambient;
+        grid.lights = self.lights.clone();
+        grid.light_falloff = self.light_falloff;
+        // * the accelerator holds the objects where they were, the moved ones have to be tried one by one
+        grid.accelerator = if self.trajectories.is_empty() {
+            self.accelerator().map(|accelerator| accelerator.box_clone())
+        } else {
+            None
+        };
+        // * the emitters may have moved with the other objects
+        grid.emitters = if self.emitters.is_empty() {
+            Vec::new()
+        } else {
+            emitters_of(&objects)
+        };
+        grid.make_image(number_of_points_per_pixel, bounce_limit, &object_refs)
+    }
+
+    pub fn object_refs(&self) -> Vec<&Object> {
+        self.objects.iter().collect()
+    }
+
+    /// Stores the material in the material table if an identical one is not already there, and returns its id
+    pub fn intern_material(&mut self, material: Material) -> MaterialId {
+        // ! PERF : linear search, scenes have few distinct materials for now
+        match self
+            .materials
+            .iter()
+            .position(|known_material| *known_material == material)
+        {
+            Some(index) => MaterialId(index),
+            None => {
+                self.materials.push(material);
+                MaterialId(self.materials.len() - 1)
+            }
+        }
+    }
+
+    pub fn material(&self, material_id: MaterialId) -> Result<&Material, RayTracingError> {
+        self.materials
+            .get(material_id.0)
+            .ok_or(RayTracingError::NoMaterialWithId(
+                material_id.0,
+                self.materials.len(),
+            ))
+    }
+
+    pub fn number_of_materials(&self) -> usize {
+        self.materials.len()
+    }
+
+    /// Adds a flat floor at height `y`, the objects standing on it are above, at smaller y (y goes down),
+    /// the accelerator is built again with it
+    pub fn add_ground_plane(&mut self, y: Scalar, material: Material) {
+        self.objects.push(Object {
+            shape: Box::new(Plane {
+                point: Point::new(0., y, 0.),
+                normal: Vector::new_from_coordinates(0., -1., 0.),
+            }),
+            material,
+            visibility: VisibilityFlags::default(),
+        });
+        self.accelerator = self.accelerator.take().map(|accelerator| accelerator.rebuilt(&self.objects));
+    }
+
+    pub fn stats(&self) -> SceneStats {
+        let emissive_count = self
+            .objects
+            .iter()
+            .filter(|object| {
+                object.material.emission_strength() > 0. && !object.material.emission_color.is_black()
+            })
+            .count();
+        let bounding_box = self
+            .objects
+            .iter()
+            .filter_map(|object| object.shape.bounding_box())
+            .reduce(|bounding_box, object_box| bounding_box.union(&object_box));
+        SceneStats {
+            object_count: self.objects.len(),
+            triangle_count: self
+                .objects
+                .iter()
+                .filter(|object| object.shape.as_any().downcast_ref::<Triangle>().is_some())
+                .count(),
+            emissive_count,
+            bounding_box,
+            memory_bytes: std::mem::size_of::<Scene>()
+                + self.objects.capacity() * std::mem::size_of::<Object>()
+                + self.materials.capacity() * std::mem::size_of::<Material>()
+                + self.trajectories.capacity() * std::mem::size_of::<(usize, Trajectory)>()
+                + self
+                    .objects
+                    .iter()
+                    .map(|object| std::mem::size_of_val(object.shape.as_ref()))
+                    .sum::<usize>(),
+        }
+    }
+
+    /// Builds the standalone object corresponding to a shared object, by looking up its material
+    pub fn resolve(&self, shared_object: &SharedObject) -> Result<Object, RayTracingError> {
+        Ok(Object {
+            shape: shared_object.shape.clone(),
+            material: *self.material(shared_object.material_id)?,
+            visibility: shared_object.visibility,
+        })
+    }
+
+    /// Sphere enclosing every bounded object of the scene, None if there is none
+    /// it is computed with Ritter's algorithm, so it is close to but slightly bigger than the smallest enclosing sphere
+    pub fn bounding_sphere(&self) -> Option<Sphere> {
+        let shapes: Vec<Sphere> = self
+            .objects
+            .iter()
+            .filter_map(|object| Self::enclosing_sphere(object.shape.as_ref()))
+            .collect();
+        let first_shape = *shapes.first()?;
+        let farthest_from = |point: &Point| {
+            shapes
+                .iter()
+                .copied()
+                .max_by(|first_shape, second_shape| {
+                    let first_distance = first_shape.center.distance(point) + first_shape.radius();
+                    let second_distance = second_shape.center.distance(point) + second_shape.radius();
+                    first_distance.total_cmp(&second_distance)
+                })
+                .unwrap_or(first_shape)
+        };
+        // start from the sphere enclosing the two objects that are roughly the farthest apart
+        let first_extreme = farthest_from(&first_shape.center);
+        let second_extreme = farthest_from(&first_extreme.center);
+        let mut bounding = Self::grow_to_enclose(
+            &Sphere::new_from_radius(&first_extreme.center, first_extreme.radius()),
+            &second_extreme,
+        );
+        for shape in &shapes {
+            bounding = Self::grow_to_enclose(&bounding, shape);
+        }
+        Some(bounding)
+    }
+
+    /// The shape itself for a sphere, else the sphere around its bounding box
+    fn enclosing_sphere(shape: &dyn Shape) -> Option<Sphere> {
+        if let Some(sphere) = shape.as_sphere() {
+            return Some(*sphere);
+        }
+        let Aabb { min, max } = shape.bounding_box()?;
+        let center = Point::new((min.x + max.x) / 2., (min.y + max.y) / 2., (min.z + max.z) / 2.);
+        Some(Sphere::new_from_radius(&center, center.distance(&max)))
+    }
+
+    /// Smallest sphere enclosing `bounding` and `shape`
+    fn grow_to_enclose(bounding: &Sphere, shape: &Sphere) -> Sphere {
+        let centers_distance = bounding.center.distance(&shape.center);
+        if centers_distance + shape.radius() <= bounding.radius() {
+            return *bounding;
+        }
+        if centers_distance + bounding.radius() <= shape.radius() {
+            return Sphere::new_from_radius(&shape.center, shape.radius());
+        }
+        let radius = (bounding.radius() + centers_distance + shape.radius()) / 2.;
+        let to_shape = Vector::new_from_points(&bounding.center, &shape.center);
+        let center =
+            &bounding.center + &(((radius - bounding.radius()) / centers_distance) * &to_shape);
+        Sphere::new_from_radius(&center, radius)
+    }
+
+    /// Renders the scene seen by each camera in a grid of `resolution` (width, height) pixels,
+    /// every view gets the same number of primary rays per pixel and the same seed
+    pub fn render_views(
+        &self,
+        cameras: &[Camera],
+        resolution: (usize, usize),
+        number_of_points_per_pixel: usize,
+        bounce_limit: BounceLimit,
+        seed: u64,
+    ) -> Result<Vec<Grid>, RayTracingError> {
+        let (width, height) = resolution;
+        let objects = self.object_refs();
+        let budget = vec![vec![number_of_points_per_pixel; width]; height];
+        cameras
+            .iter()
+            .map(|camera| {
+                let mut grid = Grid::new(width, height)?;
+                grid.camera = *camera;
+                grid.ambient = self.ambient;
+                grid.emitters = self.emitters.clone();
+                grid.lights = self.lights.clone();
+                grid.light_falloff = self.light_falloff;
+                grid.accelerator = self.accelerator().map(|accelerator| accelerator.box_clone());
+                grid.make_image_with_budget(&budget, bounce_limit, &objects, seed)?;
+                Ok(grid)
+            })
+            .collect()
+    }
+
+    /// Every object hit by the ray in front of its origin, sorted by distance, closest first
+    pub fn all_hits(&self, ray: &Ray) -> Result<Vec<HitInfo<'_>>, RayTracingError> {
+        if let Some(accelerator) = self.accelerator() {
+            return accelerator.all_hits(ray);
+        }
+        let mut hits = Vec::new();
+        for object in &self.objects {
+            if let Some(hit_info) = ray.intersect(object)? {
+                hits.push(hit_info);
+            }
+        }
+        hits.sort_by(|first_hit, second_hit| first_hit.hit_distance.total_cmp(&second_hit.hit_distance));
+        Ok(hits)
+    }
+
+    /// Whether an object seen by the shadow rays is between the two points, the objects are tried in order
+    /// and the first one in the way is enough, the closest one isn't looked for,
+    /// the hits closer than `TOLERANCE` to either point don't count so that the surfaces the points lie on don't block them
+    pub fn is_occluded(&self, from: &Point, to: &Point) -> Result<bool, RayTracingError> {
+        let ray = Ray::new(*from, Vector::new_from_points(from, to))?;
+        let distance = from.distance(to);
+        let is_in_the_way =
+            |hit_info: &HitInfo| hit_info.hit_distance > TOLERANCE && hit_info.hit_distance < distance - TOLERANCE;
+        // the accelerator also skips the objects away from the ray
+        if let Some(accelerator) = self.accelerator() {
+            return accelerator.is_occluded(&ray, distance - TOLERANCE);
+        }
+        for object in &self.objects {
+            if !object.visibility.is_visible_to(RayKind::Shadow) {
+                continue;
+            }
+            if ray.intersect(object)?.is_some_and(|hit_info| is_in_the_way(&hit_info)) {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Traces a single ray like `trace_pixel_color` does and returns every hit of its path, in order, along with the light it brings back
+    pub fn debug_trace(
+        &self,
+        ray: &Ray,
+        bounces: u64,
+        seed: u64,
+    ) -> Result<(Vec<HitInfo<'_>>, Color), RayTracingError> {
+        let objects = self.object_refs();
+        let rng = XorShiftRng::seed_from_u64(seed);
+        let mut unit_sphere_iter: DistIter<UnitSphere, XorShiftRng, [Scalar; 3]> =
+            UnitSphere.sample_iter(rng);
+        let mut path = Vec::new();
+        let (ray_light, _) = Grid::trace_ray(
+            *ray,
+            BounceLimit::Fixed(bounces),
+            &objects,
+            &TraceSettings {
+                ambient: self.ambient,
+                emitters: &self.emitters,
+                lights: &self.lights,
+                light_falloff: self.light_falloff,
+                accelerator: self.accelerator(),
+                ..TraceSettings::default()
+            },
+            &mut unit_sphere_iter,
+            Some(&mut path),
+        )?;
+        Ok((path, ray_light))
+    }
+
+    /// Small scene showing every field expected by `load_scene`
+    #[cfg(feature = "serde")]
+    pub fn example() -> Result<Self, RayTracingError> {
+        let light_material = Material::new(
+            color::WHITE,
+            1.,
+            color::BLACK.to_diffusion_coefficient()?,
+            0.,
+        )?;
+        let red_material = Material::new(
+            color::BLACK,
+            0.,
+            color::RED.to_diffusion_coefficient()?,
+            0.5,
+        )?;
+
+        let mut scene = Scene::new(vec![
+            Object {
+                shape: Box::new(Sphere::new_from_radius(&Point::new(0., -20., 10.), 10.)),
+                material: light_material,
+                visibility: VisibilityFlags::default(),
+            },
+            Object {
+                shape: Box::new(Sphere::new_from_radius(&Point::new(0., 0., 10.), 2.)),
+                material: red_material,
+                visibility: VisibilityFlags::default(),
+            },
+        ]);
+        scene.lights.push(Light::Point {
+            position: Point::new(10., -10., 0.),
+            color: color::WHITE,
+            intensity: 100.,
+        });
+        scene.add_ground_plane(2., red_material);
+        scene.intern_material(red_material);
+        Ok(scene)
+    }
+
+    /// Pretty printed JSON of `Scene::example`, to use as a template for scene files
+    #[cfg(feature = "serde")]
+    pub fn example_json() -> Result<String, RayTracingError> {
+        Scene::example()?.to_json()
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> Result<String, RayTracingError> {
+        serde_json::to_string_pretty(self).map_err(|error| RayTracingError::InvalidScene(error.to_string()))
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn from_json(json: &str) -> Result<Self, RayTracingError> {
+        let scene: Scene =
+            serde_json::from_str(json).map_err(|error| RayTracingError::InvalidScene(error.to_string()))?;
+        for (index, light) in scene.lights.iter().enumerate() {
+            light
+                .validate()
+                .map_err(|error| RayTracingError::InvalidScene(format!("light {}: {}", index, error)))?;
+        }
+        Ok(scene)
+    }
+}
+
+/// The spheres that emit light and can be sampled from outside, the inside of an inward sphere and the other shapes
+/// are left to the bounces
+fn emitters_of(objects: &[Object]) -> Vec<Object> {
+    objects
+        .iter()
+        .filter(|object| {
+            object.shape.as_sphere().is_some_and(|sphere| !sphere.inward)
+                && object.material.emission_strength() > 0.
+                && object.material.emission_color.max_channel() > 0.
+        })
+        .cloned()
+        .collect()
+}
+
+/// Reads a scene from a JSON file, see `Scene::example_json` for the format, a BVH of its objects is built
+/// for the renders unless the file sets `disable_accelerator`
+#[cfg(feature = "serde")]
+pub fn load_scene(path: &str) -> Result<Scene, RayTracingError> {
+    let json = std::fs::read_to_string(path)
+        .map_err(|error| RayTracingError::InvalidScene(format!("{}: {}", path, error)))?;
+    let mut scene = Scene::from_json(&json)?;
+    if !scene.disable_accelerator {
+        scene.set_accelerator(Box::new(Bvh::new(&scene.objects)));
+    }
+    Ok(scene)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        accelerator::{Bvh, UniformGrid},
+        geometry::{point::Point, shape::{ShapeHit, Sphere}, vector::Vector},
+        optic::color,
+    };
+    use rand::Rng;
+
+    use super::*;
+
+    /// Shape that fails the test when a ray is tried against it
+    #[derive(Debug, Clone, PartialEq)]
+    struct UntouchableShape;
+
+    impl Shape for UntouchableShape {
+        fn intersect(&self, _ray: &Ray) -> Result<Option<ShapeHit>, RayTracingError> {
+            panic!("the untouchable shape was tried")
+        }
+
+        fn normal_at(&self, _point: &Point) -> Vector {
+            Vector::new_from_coordinates(0., 0., -1.)
+        }
+
+        fn bounding_box(&self) -> Option<Aabb> {
+            None
+        }
+
+        fn position(&self) -> Point {
+            Point::new(0., 0., 0.)
+        }
+
+        fn translate(&mut self, _offset: &Vector) {}
+
+        fn surface_area(&self) -> Scalar {
+            0.
+        }
+    }
+
+    #[test]
+    fn test_intern_material() -> Result<(), RayTracingError> {
+        let mut scene = Scene::default();
+        let red_material = Material::new(
+            color::BLACK,
+            0.,
+            color::RED.to_diffusion_coefficient()?,
+            0.,
+        )?;
+        let light_material = Material::new(
+            color::WHITE,
+            1.,
+            color::BLACK.to_diffusion_coefficient()?,
+            0.,
+        )?;
+
+        let red_id = scene.intern_material(red_material);
+        let light_id = scene.intern_material(light_material);
+        let red_id_again = scene.intern_material(red_material);
+
+        assert_eq!(red_id, red_id_again);
+        assert_ne!(red_id, light_id);
+        assert_eq!(scene.number_of_materials(), 2);
+        assert_eq!(scene.material(light_id)?, &light_material);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve() -> Result<(), RayTracingError> {
+        let mut scene = Scene::default();
+        let material_id = scene.intern_material(Material::default());
+        let shared_object = SharedObject {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(0., 0., 5.), 1.)),
+            material_id,
+            visibility: VisibilityFlags::default(),
+        };
+
+        let object = scene.resolve(&shared_object)?;
+
+        assert_eq!(*object.shape, *shared_object.shape);
+        assert_eq!(object.material, Material::default());
+        assert!(Scene::default().resolve(&shared_object).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stats() -> Result<(), RayTracingError> {
+        let diffuse = Material::new(color::BLACK, 0., color::RED.to_diffusion_coefficient()?, 0.)?;
+        let light = Material::new(color::WHITE, 1., color::BLACK.to_diffusion_coefficient()?, 0.)?;
+        let mut objects: Vec<Object> = (0..5)
+            .map(|index| Object {
+                shape: Box::new(Sphere::new_from_radius(&Point::new(3. * index as Scalar, 0., 10.), 1.)),
+                material: diffuse,
+                visibility: VisibilityFlags::default(),
+            })
+            .collect();
+        objects.push(Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(0., -20., 10.), 5.)),
+            material: light,
+            visibility: VisibilityFlags::default(),
+        });
+        objects.push(Object {
+            shape: Box::new(Triangle::new(
+                Point::new(0., 0., 12.),
+                Point::new(1., 0., 12.),
+                Point::new(0., 1., 12.),
+            )),
+            material: diffuse,
+            visibility: VisibilityFlags::default(),
+        });
+        let scene = Scene::new(objects);
+
+        let stats = scene.stats();
+
+        assert_eq!(stats.object_count, 7);
+        assert_eq!(stats.triangle_count, 1);
+        assert_eq!(stats.emissive_count, 1);
+        let bounding_box = stats.bounding_box.expect("the scene is not empty");
+        assert_eq!(bounding_box.min, Point::new(-5., -25., 5.));
+        assert_eq!(bounding_box.max, Point::new(13., 1., 15.));
+        assert!(
+            stats.memory_bytes
+                >= 7 * std::mem::size_of::<Object>() + 6 * std::mem::size_of::<Sphere>() + std::mem::size_of::<Triangle>()
+        );
+        assert!(Scene::default().stats().bounding_box.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_ground_plane() -> Result<(), RayTracingError> {
+        let diffuse = Material::new(color::BLACK, 0., color::WHITE.to_diffusion_coefficient()?, 0.)?;
+        let mut scene = Scene::new(vec![
+            Object {
+                shape: Box::new(Sphere::new_from_radius(&Point::new(0., -1., 0.), 1.)),
+                material: diffuse,
+                visibility: VisibilityFlags::default(),
+            },
+            Object {
+                shape: Box::new(Sphere::new_from_radius(&Point::new(0., -50., 0.), 10.)),
+                material: Material::new(color::WHITE, 1., color::BLACK.to_diffusion_coefficient()?, 0.)?,
+                visibility: VisibilityFlags::default(),
+            },
+        ]);
+        scene.add_ground_plane(0., diffuse);
+        let light_center = Point::new(0., -50., 0.);
+        let ground = [&scene.objects[2]];
+        let objects = scene.object_refs();
+        // * the point of the ground below (x, z) and whether the light is the first object seen from it
+        let ground_point_is_lit = |x: Scalar, z: Scalar| -> Result<(Point, bool), RayTracingError> {
+            let downwards = Ray::new(Point::new(x, -5., z), Vector::new_from_coordinates(0., 1., 0.))?;
+            let ground_hit = downwards.first_point_hit_by_ray(&ground, None)?.expect("the ground is below");
+            let towards_light = Ray::new(
+                ground_hit.point_hit,
+                Vector::new_from_points(&ground_hit.point_hit, &light_center),
+            )?;
+            let first_hit = towards_light.first_point_hit_by_ray(&objects, Some(ground_hit.object))?;
+            let is_lit = first_hit.is_some_and(|hit_info| *hit_info.object.shape == *scene.objects[1].shape);
+            Ok((ground_hit.point_hit, is_lit))
+        };
+
+        let (shadow_point, shadow_point_is_lit) = ground_point_is_lit(0.5, 0.)?;
+        let (far_point, far_point_is_lit) = ground_point_is_lit(20., 5.)?;
+
+        assert!(shadow_point.y.abs() < 1e-3);
+        assert!(far_point.y.abs() < 1e-1);
+        assert!(!shadow_point_is_lit);
+        assert!(far_point_is_lit);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_at() -> Result<(), RayTracingError> {
+        let light = Material::new(color::WHITE, 1., color::BLACK.to_diffusion_coefficient()?, 0.)?;
+        let mut scene = Scene::new(vec![Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(0., 0., 10.), 2.)),
+            material: light,
+            visibility: VisibilityFlags::default(),
+        }]);
+        scene.set_trajectory(0, |time| Point::new(-4. + 8. * time, 0., 10.))?;
+        let mut first_frame = Grid::new(32, 18)?;
+        let mut second_frame = Grid::new(32, 18)?;
+
+        scene.render_at(&mut first_frame, 0., 1, BounceLimit::Fixed(0))?;
+        scene.render_at(&mut second_frame, 1., 1, BounceLimit::Fixed(0))?;
+
+        // * mean column of the pixels covered by the sphere
+        let mean_covered_column = |grid: &Grid| {
+            let covered_columns: Vec<usize> = grid
+                .coverage
+                .iter()
+                .flat_map(|row| row.iter().enumerate().filter(|(_, coverage)| **coverage > 0.).map(|(column, _)| column))
+                .collect();
+            covered_columns.iter().sum::<usize>() as Scalar / covered_columns.len() as Scalar
+        };
+        assert!(mean_covered_column(&first_frame) < 16.);
+        assert!(mean_covered_column(&second_frame) > 16.);
+        assert_eq!(scene.objects_at(0.5)[0].shape.position(), Point::new(0., 0., 10.));
+        // * the scene itself does not move
+        assert_eq!(scene.objects[0].shape.position(), Point::new(0., 0., 10.));
+        assert!(matches!(
+            scene.set_trajectory(1, |_| Point::new(0., 0., 0.)),
+            Err(RayTracingError::NoObjectAtIndex(1, 1))
+        ));
+        scene.objects.clear();
+        assert!(scene.objects_at(0.5).is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_views() -> Result<(), RayTracingError> {
+        let scene = Scene::new(vec![Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(0., 0., 10.), 2.)),
+            material: Material::new(color::WHITE, 1., color::BLACK.to_diffusion_coefficient()?, 0.)?,
+            visibility: VisibilityFlags::default(),
+        }]);
+        let up = Vector::new_from_coordinates(0., -1., 0.);
+        let cameras = [
+            Camera::new(Point::new(0., 0., 0.), Point::new(0., 0., 10.), up, 60., 2.)?,
+            // * looking to the right of the sphere, which is on the left of the view
+            Camera::new(Point::new(0., 0., 0.), Point::new(4., 0., 10.), up, 60., 2.)?,
+        ];
+
+        let views = scene.render_views(&cameras, (16, 8), 1, BounceLimit::Fixed(1), 0)?;
+
+        assert_eq!(views.len(), 2);
+        assert_ne!(views[0].colors, views[1].colors);
+        for view in &views {
+            assert_eq!(view.colors.len(), 8);
+            assert!(view.colors.iter().all(|row| row.len() == 16));
+            assert!(view.colors.iter().flatten().all(|pixel_color| pixel_color.is_finite()));
+            assert!(view.colors.iter().flatten().any(|pixel_color| !pixel_color.is_black()));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_accelerated_render() -> Result<(), RayTracingError> {
+        let grey = Material::new(color::BLACK, 0., Color::new(0.6, 0.6, 0.6)?.to_diffusion_coefficient()?, 0.)?;
+        let mut objects: Vec<Object> = Sphere::new_from_radius(&Point::new(0., 0., 10.), 2.)
+            .tessellate(2)
+            .into_iter()
+            .map(|triangle| Object {
+                shape: Box::new(triangle),
+                material: grey,
+                visibility: VisibilityFlags::default(),
+            })
+            .collect();
+        objects.push(Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(6., -8., 10.), 3.)),
+            material: Material::new(color::WHITE, 1., color::BLACK.to_diffusion_coefficient()?, 0.)?,
+            visibility: VisibilityFlags::default(),
+        });
+        let mut scene = Scene::new(objects);
+        scene.add_ground_plane(2., grey);
+        scene.lights.push(Light::Point {
+            position: Point::new(-5., -10., 5.),
+            color: color::WHITE,
+            intensity: 100.,
+        });
+        scene.prepare();
+        let up = Vector::new_from_coordinates(0., -1., 0.);
+        let cameras = [Camera::new(Point::new(0., -2., 0.), Point::new(0., 0., 10.), up, 40., 2.)?];
+        let render = |scene: &Scene| scene.render_views(&cameras, (16, 8), 2, BounceLimit::Fixed(2), 7);
+
+        let linear_render = render(&scene)?;
+        scene.set_accelerator(Box::new(Bvh::new(&scene.objects)));
+        let bvh_render = render(&scene)?;
+        scene.set_accelerator(Box::new(UniformGrid::new(&scene.objects)));
+        let grid_render = render(&scene)?;
+        scene.disable_accelerator = true;
+        let disabled_render = render(&scene)?;
+
+        assert!(linear_render[0].colors.iter().flatten().any(|pixel_color| !pixel_color.is_black()));
+        assert_eq!(bvh_render[0].colors, linear_render[0].colors);
+        assert_eq!(grid_render[0].colors, linear_render[0].colors);
+        assert_eq!(disabled_render[0].colors, linear_render[0].colors);
+        // * the renders go through the accelerator, one built without the objects sees nothing
+        scene.disable_accelerator = false;
+        scene.set_accelerator(Box::new(Bvh::new(&[])));
+        let mut grid = Grid::new(16, 8)?;
+        grid.camera = cameras[0];
+        scene.render_at(&mut grid, 0., 2, BounceLimit::Fixed(2))?;
+        assert!(grid.colors.iter().flatten().all(|pixel_color| pixel_color.is_black()));
+        assert!(render(&scene)?[0].colors.iter().flatten().all(|pixel_color| pixel_color.is_black()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_all_hits() -> Result<(), RayTracingError> {
+        let make_object = |z: Scalar| Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(0., 0., z), 1.)),
+            material: Material::default(),
+            visibility: VisibilityFlags::default(),
+        };
+        // not sorted on purpose, with one sphere behind the ray
+        let scene = Scene::new(vec![make_object(20.), make_object(5.), make_object(-10.), make_object(12.)]);
+        let ray = Ray::new(Point::new(0., 0., 0.), Vector::new_from_coordinates(0., 0., 1.))?;
+
+        let hits = scene.all_hits(&ray)?;
+        let mut accelerated_scene = Scene::new(scene.objects.clone());
+        accelerated_scene.set_accelerator(Box::new(Bvh::new(&scene.objects)));
+        let accelerated_hits = accelerated_scene.all_hits(&ray)?;
+
+        assert_eq!(hits.len(), 3);
+        assert_eq!(hits[0].point_hit, Point::new(0., 0., 4.));
+        assert_eq!(hits[1].point_hit, Point::new(0., 0., 11.));
+        assert_eq!(hits[2].point_hit, Point::new(0., 0., 19.));
+        assert_eq!(accelerated_hits.len(), 3);
+        assert_eq!(accelerated_scene.first_hit(&ray)?.map(|hit_info| hit_info.point_hit), Some(hits[0].point_hit));
+        assert!(hits[0].hit_distance < hits[1].hit_distance && hits[1].hit_distance < hits[2].hit_distance);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_occluded() -> Result<(), RayTracingError> {
+        let mut rng = XorShiftRng::seed_from_u64(17);
+        let mut random_point = |half_size: Scalar| {
+            Point::new(
+                rng.gen_range(-half_size..half_size),
+                rng.gen_range(-half_size..half_size),
+                rng.gen_range(-half_size..half_size),
+            )
+        };
+        let centers: Vec<Point> = (0..30).map(|_| random_point(10.)).collect();
+        let mut objects: Vec<Object> = centers
+            .iter()
+            .map(|center| Object {
+                shape: Box::new(Sphere::new_from_radius(center, 1.5)),
+                material: Material::default(),
+                visibility: VisibilityFlags::default(),
+            })
+            .collect();
+        // * a sphere only the camera sees doesn't cast shadows
+        objects[0].visibility.shadow = false;
+        let scene = Scene::new(objects);
+        let mut accelerated_scene = scene.clone();
+        accelerated_scene.set_accelerator(Box::new(Bvh::new(&scene.objects)));
+        let object_refs = scene.object_refs();
+
+        for _ in 0..500 {
+            let (from, to) = (random_point(12.), random_point(12.));
+            let distance = from.distance(&to);
+            let ray = Ray::new(from, Vector::new_from_points(&from, &to))?;
+            let is_blocked = ray
+                .first_visible_hit(&object_refs, None, RayKind::Shadow)?
+                .is_some_and(|hit_info| hit_info.hit_distance < distance);
+
+            assert_eq!(scene.is_occluded(&from, &to)?, is_blocked);
+            assert_eq!(accelerated_scene.is_occluded(&from, &to)?, is_blocked);
+        }
+        // * the same answer as the visibility test of the spheres, which looks for the first point hit from the source
+        let mut visible_scene = scene.clone();
+        visible_scene.objects[0].visibility.shadow = true;
+        let visible_refs = visible_scene.object_refs();
+        for (sphere_index, center) in centers.iter().enumerate() {
+            let sphere = Sphere::new_from_radius(center, 1.5);
+            for _ in 0..20 {
+                let direction = Vector::new_from_points(center, &random_point(1.)).normalize()?;
+                let sphere_point = center + &(1.5 * &direction);
+                let source = random_point(12.);
+                // * the hit point of a grazing ray is too far off for the comparison of the points in single precision
+                if direction.scalar_product(&Vector::new_from_points(&sphere_point, &source).normalize()?).abs() < 0.1 {
+                    continue;
+                }
+
+                let is_lit = sphere.source_is_above_horizon(&sphere_point, &source)?
+                    && !visible_scene.is_occluded(&sphere_point, &source)?;
+                assert_eq!(
+                    Sphere::source_is_visible_from_sphere_point(&visible_refs, sphere_index, &sphere_point, &source)?,
+                    is_lit
+                );
+            }
+        }
+        // * the objects after the first one in the way are never tried
+        let blocker = Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(0., 0., 5.), 1.)),
+            material: Material::default(),
+            visibility: VisibilityFlags::default(),
+        };
+        let untouchable = Object {
+            shape: Box::new(UntouchableShape),
+            material: Material::default(),
+            visibility: VisibilityFlags::default(),
+        };
+        let blocked_scene = Scene::new(vec![blocker, untouchable]);
+        assert!(blocked_scene.is_occluded(&Point::new(0., 0., 0.), &Point::new(0., 0., 10.))?);
+        assert!(scene.is_occluded(&Point::new(0., 0., 0.), &Point::new(0., 0., 0.)).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_clone_is_independent() -> Result<(), RayTracingError> {
+        let mut scene = Scene::new(vec![Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(0., 0., 10.), 1.)),
+            material: Material::default(),
+            visibility: VisibilityFlags::default(),
+        }]);
+        scene.add_ground_plane(2., Material::default());
+        scene.set_accelerator(Box::new(Bvh::new(&scene.objects)));
+        let ray = Ray::new(Point::new(0., 0., 0.), Vector::new_from_coordinates(0., 0., 1.))?;
+
+        let mut snapshot = scene.clone();
+        assert_eq!(snapshot, scene);
+        // * the copy of the accelerator still finds the objects
+        assert_eq!(
+            snapshot.first_hit(&ray)?.map(|hit_info| hit_info.point_hit),
+            Some(Point::new(0., 0., 9.))
+        );
+        snapshot.objects[0].shape.translate(&Vector::new_from_coordinates(5., 0., 0.));
+        snapshot.objects[0].material = Material::new(color::WHITE, 1., color::BLACK.to_diffusion_coefficient()?, 0.)?;
+        snapshot.objects[1].visibility.camera = false;
+        snapshot.ambient = Some(color::WHITE);
+
+        assert!(snapshot != scene);
+        assert_eq!(scene.objects[0].shape.position(), Point::new(0., 0., 10.));
+        assert_eq!(scene.objects[0].material, Material::default());
+        assert!(scene.objects[1].visibility.camera);
+        assert_eq!(scene.ambient, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_debug_trace() -> Result<(), RayTracingError> {
+        let mirror = Material::new(color::BLACK, 0., color::WHITE.to_diffusion_coefficient()?, 1.)?;
+        let light = Material::new(color::WHITE, 1., color::BLACK.to_diffusion_coefficient()?, 0.)?;
+        let scene = Scene::new(vec![
+            Object {
+                shape: Box::new(Sphere::new_from_radius(&Point::new(0., 0., 10.), 1.)),
+                material: mirror,
+                visibility: VisibilityFlags::default(),
+            },
+            Object {
+                shape: Box::new(Sphere::new_from_radius(&Point::new(0., 0., -10.), 1.)),
+                material: light,
+                visibility: VisibilityFlags::default(),
+            },
+        ]);
+        // the mirror sends the ray straight back to the light behind its origin
+        let ray = Ray::new(Point::new(0., 0., 0.), Vector::new_from_coordinates(0., 0., 1.))?;
+
+        let (path, ray_light) = scene.debug_trace(&ray, 5, 0)?;
+
+        assert_eq!(path.len(), 2);
+        assert_eq!(*path[0].object.shape, *scene.objects[0].shape);
+        assert_eq!(*path[1].object.shape, *scene.objects[1].shape);
+        assert_eq!(path[1].point_hit, Point::new(0., 0., -9.));
+        assert_eq!(ray_light, color::WHITE);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_prepare_samples_emitters() -> Result<(), RayTracingError> {
+        let lamp = Material::new(
+            color::WHITE,
+            1.,
+            color::BLACK.to_diffusion_coefficient()?,
+            0.,
+        )?;
+        let floor = Material::new(
+            color::BLACK,
+            0.,
+            color::Color::new(0.8, 0.8, 0.8)?.to_diffusion_coefficient()?,
+            0.,
+        )?;
+        // * a small lamp above a floor, and no `Light`
+        let mut scene = Scene::new(vec![Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(0., -3., 0.), 0.5)),
+            material: lamp,
+            visibility: VisibilityFlags::default(),
+        }]);
+        scene.add_ground_plane(0., floor);
+        let ray = Ray::new_from_points(&Point::new(0., -1., -4.), &Point::new(0., 0., 0.))?;
+        let mean_and_variance = |scene: &Scene| -> Result<(f32, f32), RayTracingError> {
+            let lights = (0..400)
+                .map(|seed| Ok(scene.debug_trace(&ray, 1, seed)?.1.luminance()))
+                .collect::<Result<Vec<f32>, RayTracingError>>()?;
+            let mean = lights.iter().sum::<f32>() / lights.len() as f32;
+            let variance = lights.iter().map(|light| (light - mean).powi(2)).sum::<f32>() / lights.len() as f32;
+            Ok((mean, variance))
+        };
+
+        let (path_traced_mean, path_traced_variance) = mean_and_variance(&scene)?;
+        scene.prepare();
+        let (sampled_mean, sampled_variance) = mean_and_variance(&scene)?;
+
+        assert_eq!(scene.emitters().len(), 1);
+        assert!(sampled_variance * 10. < path_traced_variance);
+        // the same light on average, up to the noise of the path tracer
+        let standard_error = (path_traced_variance / 400.).sqrt();
+        assert!((sampled_mean - path_traced_mean).abs() < 4. * standard_error);
+        assert!(sampled_mean > 0.);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lights_are_rendered() -> Result<(), RayTracingError> {
+        let grey = Material::new(
+            color::BLACK,
+            0.,
+            color::Color::new(0.8, 0.8, 0.8)?.to_diffusion_coefficient()?,
+            0.,
+        )?;
+        // * a floor lit by a point light right above it, and nothing that emits light
+        let mut scene = Scene::default();
+        scene.add_ground_plane(0., grey);
+        scene.lights.push(Light::Point {
+            position: Point::new(0., -3., 0.),
+            color: color::WHITE,
+            intensity: 1.,
+        });
+        let ray = Ray::new_from_points(&Point::new(0., -1., -4.), &Point::new(0., 0., 0.))?;
+
+        let (path, lit_floor) = scene.debug_trace(&ray, 0, 3)?;
+        let number_of_hits = path.len();
+        // * a ball between the light and the floor
+        scene.objects.push(Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(0., -1.5, 0.), 0.5)),
+            material: grey,
+            visibility: VisibilityFlags::default(),
+        });
+        let (_, shadowed_floor) = scene.debug_trace(&ray, 0, 3)?;
+
+        assert_eq!(number_of_hits, 1);
+        assert!((lit_floor.luminance() - 0.8).abs() < 1e-3);
+        assert_eq!(shadowed_floor, color::BLACK);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_light_falloff_render() -> Result<(), RayTracingError> {
+        let grey = Material::new(
+            color::BLACK,
+            0.,
+            color::Color::new(0.8, 0.8, 0.8)?.to_diffusion_coefficient()?,
+            0.,
+        )?;
+        // * the camera looks straight down at a small patch of the floor, right below the light
+        let camera = Camera::new(
+            Point::new(0., -1., 0.),
+            Point::new(0., 0., 0.),
+            Vector::new_from_coordinates(0., 0., 1.),
+            5.,
+            1.,
+        )?;
+        let mean_luminance = |light_height: Scalar, falloff: Falloff| -> Result<f32, RayTracingError> {
+            let mut scene = Scene::default();
+            scene.add_ground_plane(0., grey);
+            scene.lights.push(Light::Point {
+                position: Point::new(0., -light_height, 0.),
+                color: color::WHITE,
+                intensity: 16.,
+            });
+            scene.light_falloff = falloff;
+            let mut grid = Grid::new(4, 4)?;
+            grid.camera = camera;
+            scene.render_at(&mut grid, 0., 1, BounceLimit::Fixed(0))?;
+            Ok(grid.colors.iter().flatten().map(Color::luminance).sum::<f32>() / 16.)
+        };
+
+        let near = mean_luminance(2., Falloff::InverseSquare)?;
+        let far = mean_luminance(4., Falloff::InverseSquare)?;
+
+        // * twice as far, a quarter of the light
+        assert!((near / far - 4.).abs() < 0.05, "{} / {}", near, far);
+        assert!((near - 0.8 * 16. / 4.).abs() < 0.05);
+        // * without falloff only the angle of the light changes a little
+        assert!((mean_luminance(2., Falloff::None)? / mean_luminance(4., Falloff::None)? - 1.).abs() < 1e-3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_object_hidden_from_camera_casts_shadow() -> Result<(), RayTracingError> {
+        let lamp = Material::new(color::WHITE, 1., color::BLACK.to_diffusion_coefficient()?, 0.)?;
+        let grey = Material::new(
+            color::BLACK,
+            0.,
+            color::Color::new(0.8, 0.8, 0.8)?.to_diffusion_coefficient()?,
+            0.,
+        )?;
+        // * the blocker between the lamp and the floor is wide enough to hide the whole lamp from the floor below it
+        let blocker = Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(0., -1.5, 0.), 0.5)),
+            material: grey,
+            visibility: VisibilityFlags {
+                camera: false,
+                ..VisibilityFlags::default()
+            },
+        };
+        let lamp = Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(0., -3., 0.), 0.5)),
+            material: lamp,
+            visibility: VisibilityFlags::default(),
+        };
+        let mut scene = Scene::new(vec![lamp.clone(), blocker]);
+        scene.add_ground_plane(0., grey);
+        scene.prepare();
+        let mut unblocked_scene = Scene::new(vec![lamp]);
+        unblocked_scene.add_ground_plane(0., grey);
+        unblocked_scene.prepare();
+        let towards_blocker = Ray::new(Point::new(0., -1.5, -4.), Vector::new_from_coordinates(0., 0., 1.))?;
+        let towards_floor = Ray::new_from_points(&Point::new(0., -1., -4.), &Point::new(0., 0., 0.))?;
+
+        let (path, _) = scene.debug_trace(&towards_blocker, 1, 0)?;
+        assert!(path.is_empty());
+        let floor_light = |scene: &Scene| -> Result<f32, RayTracingError> {
+            (0..50).try_fold(0., |light, seed| Ok(light + scene.debug_trace(&towards_floor, 1, seed)?.1.luminance()))
+        };
+        assert_eq!(floor_light(&scene)?, 0.);
+        assert!(floor_light(&unblocked_scene)? > 0.);
+        // * seen by the camera again, the blocker is the first hit
+        scene.objects[1].visibility.camera = true;
+        let (path, _) = scene.debug_trace(&towards_blocker, 1, 0)?;
+        assert_eq!(*path[0].object, scene.objects[1]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ambient_light() -> Result<(), RayTracingError> {
+        // * a closed room without any lamp, no light can reach its walls
+        let wall = Material::new(
+            color::BLACK,
+            0.,
+            color::Color::new(0.5, 0.5, 0.5)?.to_diffusion_coefficient()?,
+            0.,
+        )?;
+        let mut scene = Scene::new(vec![Object {
+            shape: Box::new(Sphere::new_inward_from_radius(&Point::new(0., 0., 0.), 10.)),
+            material: wall,
+            visibility: VisibilityFlags::default(),
+        }]);
+        let ray = Ray::new(Point::new(0., 0., 0.), Vector::new_from_coordinates(0., 0., 1.))?;
+
+        let (_, light_without_ambient) = scene.debug_trace(&ray, 3, 0)?;
+        scene.ambient = Some(color::Color::new(0.1, 0.1, 0.1)?);
+        let (_, light_with_ambient) = scene.debug_trace(&ray, 0, 0)?;
+
+        assert_eq!(light_without_ambient, color::BLACK);
+        // the wall sends back half of the ambient light at the first hit
+        assert!((light_with_ambient.luminance() - 0.05).abs() < 1e-6);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_lights_from_json() -> Result<(), RayTracingError> {
+        let json = r#"{
+            "objects": [],
+            "materials": [],
+            "lights": [
+                {
+                    "type": "point",
+                    "position": { "x": 1.0, "y": -20.0, "z": 5.0 },
+                    "color": { "r": 1.0, "g": 0.9, "b": 0.8 },
+                    "intensity": 250.0
+                }
+            ]
+        }"#;
+
+        let scene = Scene::from_json(json)?;
+
+        assert_eq!(scene.lights.len(), 1);
+        match scene.lights[0] {
+            Light::Point { position, intensity, .. } => {
+                assert_eq!(position, Point::new(1., -20., 5.));
+                assert_eq!(intensity, 250.);
+            }
+            light => panic!("expected a point light, got {:?}", light),
+        }
+        assert_eq!(scene.lights[0].color(), Color::new(1., 0.9, 0.8)?);
+        // * the lights are optional, but must be valid when given
+        assert!(Scene::from_json(r#"{ "objects": [], "materials": [] }"#)?.lights.is_empty());
+        assert!(Scene::from_json(&json.replace("250.0", "-250.0")).is_err());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json_round_trip() -> Result<(), RayTracingError> {
+        let scene = Scene::example()?;
+
+        let json = Scene::example_json()?;
+        let read_back = Scene::from_json(&json)?;
+
+        assert_eq!(read_back, scene);
+        assert!(json.contains("\"diffusion_coefficients\""));
+        assert!(Scene::from_json("{\"objects\": 3}").is_err());
+        // * the ground is a plane, whose normal can't be zero
+        assert!(read_back.objects[2].shape.as_any().downcast_ref::<Plane>().is_some());
+        let flat_normal = json.replacen("\"y\": -1.0", "\"y\": 0.0", 1);
+        assert_ne!(flat_normal, json);
+        assert!(Scene::from_json(&flat_normal).is_err());
+        // * the spheres and materials are checked like the planes
+        assert!(Scene::from_json(&json.replacen("\"radius\": 10.0", "\"radius\": -10.0", 1)).is_err());
+        assert!(Scene::from_json(&json.replacen("\"reflection_coeff\": 0.5", "\"reflection_coeff\": 1.5", 1)).is_err());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_load_scene_builds_accelerator() -> Result<(), RayTracingError> {
+        let path = std::env::temp_dir().join("ray_tracing_3d_test_load_scene_builds_accelerator.json");
+        let path_name = path.to_str().expect("the temporary directory has a valid name");
+        let json = Scene::example_json()?;
+        let ray = Ray::new(Point::new(0., 0., 0.), Vector::new_from_coordinates(0., 0., 1.))?;
+
+        std::fs::write(&path, &json)?;
+        let scene = load_scene(path_name)?;
+        assert!(scene.accelerator().is_some());
+        assert_eq!(scene.first_hit(&ray)?.map(|hit_info| hit_info.point_hit), Some(Point::new(0., 0., 8.)));
+        std::fs::write(&path, json.replace("\"disable_accelerator\": false", "\"disable_accelerator\": true"))?;
+        let debugged_scene = load_scene(path_name)?;
+        assert!(debugged_scene.accelerator().is_none());
+        assert_eq!(debugged_scene.objects, scene.objects);
+        std::fs::remove_file(&path)?;
+
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_ground_plane_of_loaded_scene_is_hit() -> Result<(), RayTracingError> {
+        let path = std::env::temp_dir().join("ray_tracing_3d_test_ground_plane_of_loaded_scene_is_hit.json");
+        let path_name = path.to_str().expect("the temporary directory has a valid name");
+        let ball = Object {
+            shape: Box::new(Sphere::new_from_radius(&Point::new(0., 0., 10.), 2.)),
+            material: Material::default(),
+            visibility: VisibilityFlags::default(),
+        };
+        let downwards = Ray::new(Point::new(0., 0., 0.), Vector::new_from_coordinates(0., 1., 0.))?;
+
+        std::fs::write(&path, Scene::new(vec![ball]).to_json()?)?;
+        let mut scene = load_scene(path_name)?;
+        std::fs::remove_file(&path)?;
+        scene.add_ground_plane(5., Material::default());
+
+        // * the BVH built by `load_scene` knows about the floor
+        assert!(scene.accelerator().is_some());
+        assert_eq!(scene.first_hit(&downwards)?.map(|hit_info| hit_info.point_hit), Some(Point::new(0., 5., 0.)));
+
+        Ok(())
+    }
+}
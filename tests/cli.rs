@@ -0,0 +1,19 @@
+use std::process::Command;
+
+#[test]
+fn test_unwritable_output_is_reported() {
+    let output = Command::new(env!("CARGO_BIN_EXE_ray_tracing_3d"))
+        .args(["--quiet", "run", "-r", "4x4", "-p", "1", "-b", "1"])
+        .args(["-o", "/nonexistent_directory/image.png"])
+        .output()
+        .expect("the binary can be started");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(!output.status.success());
+    assert!(
+        stderr.starts_with("ERROR: could not render \"/nonexistent_directory/image.png\""),
+        "unexpected error output: {}",
+        stderr
+    );
+    assert!(!stderr.contains("panicked"));
+}